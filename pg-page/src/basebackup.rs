@@ -0,0 +1,152 @@
+//! Reading relation files straight out of a `pg_basebackup -Ft -z` archive, without
+//! extracting it to disk first. Such a backup is `base.tar.gz` for the data directory plus
+//! one `<oid>.tar.gz` per tablespace; each can be read with [`BaseBackup`].
+
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+
+use crate::{compile_constants::BLCKSZ, page_reader::PageReader, Error};
+
+/// Upper bound on the buffer reserved up front for an entry, as its size comes from the
+/// archive's own header. Larger files still read fine, growing the buffer as they go.
+const MAX_PREALLOCATION: usize = 1024 * BLCKSZ as usize;
+
+/// A relation segment file found in a base backup, buffered in memory.
+pub struct RelationFile {
+    /// Path inside the archive, such as `base/5/16384`, `global/1262_vm.1` or, in a
+    /// tablespace archive, `PG_15_202209061/5/16401`
+    pub path: String,
+    pub reader: PageReader<Cursor<Vec<u8>>>,
+}
+
+/// A gzip compressed base backup tar stream, either the data directory's or a tablespace's.
+pub struct BaseBackup<R: Read> {
+    archive: tar::Archive<GzDecoder<R>>,
+}
+
+impl<R: Read> BaseBackup<R> {
+    pub fn new(stream: R) -> Self {
+        BaseBackup {
+            archive: tar::Archive::new(GzDecoder::new(stream)),
+        }
+    }
+
+    /// Walks the archive, yielding each relation file as it is reached and skipping
+    /// everything else. Only one file's bytes are held at a time.
+    pub fn relation_files(&mut self) -> Result<impl Iterator<Item = Result<RelationFile, Error>> + '_, Error> {
+        let entries = self.archive.entries()?;
+        Ok(entries.filter_map(|entry| {
+            let read_entry = || -> Result<Option<RelationFile>, Error> {
+                let mut entry = entry?;
+                let path = entry.path()?.to_string_lossy().into_owned();
+                if !entry.header().entry_type().is_file() || !is_relation_file(&path) {
+                    return Ok(None);
+                }
+                let mut bytes = Vec::with_capacity((entry.size() as usize).min(MAX_PREALLOCATION));
+                entry.read_to_end(&mut bytes)?;
+                Ok(Some(RelationFile {
+                    path,
+                    reader: PageReader::new(Cursor::new(bytes)),
+                }))
+            };
+            read_entry().transpose()
+        }))
+    }
+}
+
+/// Whether `path` names a relation segment: `<filenode>[_<fork>][.<segno>]` under `base/<db>/`
+/// or `global/`, or under `<version dir>/<db>/` in a tablespace archive. The data directory
+/// archive only holds symlinks under `pg_tblspc/`.
+fn is_relation_file(path: &str) -> bool {
+    let mut components = path.trim_start_matches("./").split('/');
+    let in_relation_dir = match components.next() {
+        Some("base") => components.next().is_some_and(is_number) && components.next().is_some_and(is_relation_file_name),
+        Some("global") => components.next().is_some_and(is_relation_file_name),
+        // the tablespace version directory, such as PG_15_202209061
+        Some(dir) if dir.starts_with("PG_") => {
+            components.next().is_some_and(is_number) && components.next().is_some_and(is_relation_file_name)
+        }
+        _ => false,
+    };
+    in_relation_dir && components.next().is_none()
+}
+
+fn is_relation_file_name(name: &str) -> bool {
+    let (name, segno) = name.split_once('.').unwrap_or((name, "0"));
+    let filenode = match name.split_once('_') {
+        Some((filenode, "fsm" | "vm" | "init")) => filenode,
+        Some(_) => return false,
+        None => name,
+    };
+    is_number(filenode) && is_number(segno)
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+    use crate::test_util::{page_bytes, tuple};
+
+    fn tar_gz(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, bytes) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o600);
+            header.set_cksum();
+            builder.append_data(&mut header, path, bytes.as_slice()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_relation_files() {
+        let page = page_bytes(&[tuple(1, vec![1]), tuple(2, vec![2])]);
+        let archive = tar_gz(&[
+            ("PG_VERSION", b"15\n".to_vec()),
+            ("base/5/16384", [page.clone(), page.clone()].concat()),
+            ("base/5/pg_filenode.map", vec![0; 512]),
+            ("base/5/16384_fsm", page.clone()),
+            ("global/1262.1", page.clone()),
+            ("backup_label", b"START WAL LOCATION".to_vec()),
+        ]);
+
+        let mut backup = BaseBackup::new(archive.as_slice());
+        let files: Vec<_> = backup.relation_files().unwrap().map(Result::unwrap).collect();
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["base/5/16384", "base/5/16384_fsm", "global/1262.1"]);
+
+        let pages = files.into_iter().next().unwrap().reader.into_iter();
+        let tuples: usize = pages.map(|page| page.unwrap().iter_tuples().count()).sum();
+        assert_eq!(tuples, 4);
+    }
+
+    #[test]
+    fn test_tablespace_relation_files() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let archive = tar_gz(&[
+            ("PG_15_202209061/5/16401", page.clone()),
+            ("PG_15_202209061/5/16401_vm", page),
+        ]);
+
+        let mut backup = BaseBackup::new(archive.as_slice());
+        let paths: Vec<_> = backup.relation_files().unwrap().map(|file| file.unwrap().path).collect();
+        assert_eq!(paths, vec!["PG_15_202209061/5/16401", "PG_15_202209061/5/16401_vm"]);
+    }
+
+    #[test]
+    fn test_is_relation_file() {
+        assert!(is_relation_file("./base/1/1259"));
+        assert!(is_relation_file("PG_15_202209061/5/16401.2"));
+        assert!(!is_relation_file("pg_tblspc/16400/PG_15_202209061/5/16401.2"));
+        assert!(!is_relation_file("base/5/16384_foo"));
+        assert!(!is_relation_file("base/5/pg_internal.init"));
+        assert!(!is_relation_file("pg_wal/000000010000000000000001"));
+    }
+}
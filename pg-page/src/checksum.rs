@@ -0,0 +1,159 @@
+//! The PostgreSQL data page checksum (`src/include/storage/checksum_impl.h`).
+//!
+//! The page is treated as an array of 32-bit words split across 32 parallel FNV-1a style
+//! sums, each seeded with its own offset. Two extra rounds of zeros are mixed in, the sums
+//! are XORed together, the block number is folded in and the result is reduced to a
+//! nonzero 16-bit value.
+
+use crate::util::{ByteEncodeError, ByteEncodeResult, GetByteSliceExt};
+
+/// number of checksums to calculate in parallel
+const N_SUMS: usize = 32;
+/// prime multiplier of FNV-1a hash
+const FNV_PRIME: u32 = 16777619;
+/// offset position of the checksum field in the page header
+const CHECKSUM_OFFSET: usize = 8;
+
+/// base offsets to initialize each of the parallel FNV hashes into a different initial state
+const CHECKSUM_BASE_OFFSETS: [u32; N_SUMS] = [
+    0x5B1F36E9, 0xB8525960, 0x02AB50AA, 0x1DE66D2A, 0x79FF467A, 0x9BB9F8A3, 0x217E7CD2, 0x83E13D2C,
+    0xF8D4474F, 0xE39EB970, 0x42C6AE16, 0x993216FA, 0x7B093B5D, 0x98DAFF3C, 0xF718902A, 0x0B1C9CDB,
+    0xE58F764B, 0x187636BC, 0x5D7B3BB1, 0xE73DE7DE, 0x92BEC979, 0xCCA6C0B2, 0x304A0979, 0x85AA43D4,
+    0x783125BB, 0x6CA8EAA2, 0xE407EAC6, 0x4B5CFC3E, 0x9FBF8C76, 0x15CA20BE, 0xF2CA9FD3, 0x959BD756,
+];
+
+fn checksum_comp(checksum: u32, value: u32) -> u32 {
+    let tmp = checksum ^ value;
+    tmp.wrapping_mul(FNV_PRIME) ^ (tmp >> 17)
+}
+
+/// Block checksum of a page image whose checksum field has already been zeroed.
+fn pg_checksum_block(page: &[u8]) -> u32 {
+    let mut sums = CHECKSUM_BASE_OFFSETS;
+    for row in page.chunks_exact(4 * N_SUMS) {
+        for (sum, word) in sums.iter_mut().zip(row.chunks_exact(4)) {
+            *sum = checksum_comp(*sum, u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        }
+    }
+    for _ in 0..2 {
+        for sum in sums.iter_mut() {
+            *sum = checksum_comp(*sum, 0);
+        }
+    }
+    sums.iter().fold(0, |result, sum| result ^ sum)
+}
+
+/// Checksum of a full page image, ignoring whatever is stored in its `pd_checksum` field.
+///
+/// The image length should be a multiple of 128 bytes, as every valid page size is; bytes
+/// past the last full 128 are left out of the sum.
+///
+/// Panics if the image is too short to hold the `pd_checksum` field, under 10 bytes.
+pub fn pg_checksum_page(page: &[u8], block_number: u32) -> u16 {
+    assert!(
+        page.len() >= CHECKSUM_OFFSET + 2,
+        "page image of {} bytes has no checksum field",
+        page.len()
+    );
+    let mut page = page.to_vec();
+    page[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 2].fill(0);
+    let checksum = pg_checksum_block(&page) ^ block_number;
+    ((checksum % 65535) + 1) as u16
+}
+
+/// Stores the checksum of a full page image for block `block_number` in its header, then
+/// writes the image out. Fails without writing anything unless the image length is a
+/// nonzero multiple of 128 bytes.
+pub fn write_block_checksummed(
+    image: &mut [u8],
+    block_number: u32,
+    writer: &mut impl std::io::Write,
+) -> ByteEncodeResult<()> {
+    if image.is_empty() || !image.len().is_multiple_of(4 * N_SUMS) {
+        return Err(ByteEncodeError::InvalidSize {
+            expected: image.len().next_multiple_of(4 * N_SUMS).max(4 * N_SUMS),
+            actual: image.len(),
+        });
+    }
+    let checksum = pg_checksum_page(image, block_number);
+    image
+        .get_byte_slice_mut(CHECKSUM_OFFSET, CHECKSUM_OFFSET + 2)?
+        .copy_from_slice(&checksum.to_le_bytes());
+    Ok(writer.write_all(image)?)
+}
+
+/// CRC-32C (Castagnoli), the `pg_crc32c` Postgres uses for pg_control and WAL records.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        page_reader::PageReader,
+        test_util::{page_bytes, tuple},
+    };
+    use std::io::Cursor;
+    use test_case::test_case;
+
+    /// A page filled with a byte pattern, so every lane sees different words.
+    fn pattern_page(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 31 + 7) as u8).collect()
+    }
+
+    // expected values from the checksum_impl.h loop compiled in C with the matching BLCKSZ
+    #[test_case(4096, 0, 0xC371)]
+    #[test_case(4096, 7, 0xC372)]
+    #[test_case(8192, 0, 0xC7E8)]
+    #[test_case(16384, 0, 0x2D73)]
+    #[test_case(16384, 7, 0x2D78)]
+    #[test_case(32768, 7, 0xA190)]
+    fn test_checksum_page_sizes(size: usize, block_number: u32, expected: u16) {
+        assert_eq!(pg_checksum_page(&pattern_page(size), block_number), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "page image of 9 bytes has no checksum field")]
+    fn test_checksum_page_too_short() {
+        pg_checksum_page(&[0; 9], 0);
+    }
+
+    #[test]
+    fn test_write_block_checksummed() {
+        let mut image = page_bytes(&[tuple(1, vec![1, 2, 3])]);
+        let mut written = Vec::new();
+        write_block_checksummed(&mut image, 3, &mut written).unwrap();
+        assert_eq!(written, image);
+
+        let page = PageReader::new(Cursor::new(written)).read_next_page().unwrap().unwrap();
+        assert_ne!(page.header_data.pd_checksum, 0);
+        assert!(page.verify_checksum(3));
+        assert!(!page.verify_checksum(4));
+    }
+
+    #[test]
+    fn test_write_block_checksummed_invalid_size() {
+        let mut written = Vec::new();
+        for (len, expected) in [(0, 128), (4, 128), (8200, 8320)] {
+            assert!(matches!(
+                write_block_checksummed(&mut vec![0; len], 0, &mut written),
+                Err(ByteEncodeError::InvalidSize { expected: e, actual }) if e == expected && actual == len
+            ));
+        }
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+}
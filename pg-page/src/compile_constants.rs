@@ -1,3 +1,6 @@
 pub const TOAST_TUPLE_TARGET: u32 = 2048;
 pub const TOAST_TUPLE_THRESHOLD: u32 = 2048;
 pub const TOAST_MAX_CHUNK_SIZE: u32 = 2048;
+pub const BLCKSZ: u32 = 8192;
+/// Blocks per relation segment file (1GB with the default BLCKSZ)
+pub const RELSEG_SIZE: u32 = 131072;
@@ -17,6 +17,7 @@ use crate::util::{ByteEncodeResult, ByteEncoded};
 /// wise to change this without good reason.
 ///
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockIdData {
     /// block number
     pub bi_hi: u16,
@@ -24,14 +25,31 @@ pub struct BlockIdData {
     pub bi_lo: u16,
 }
 
+impl BlockIdData {
+    pub fn from_block_number(block_number: u32) -> Self {
+        BlockIdData {
+            bi_hi: (block_number >> 16) as u16,
+            bi_lo: block_number as u16,
+        }
+    }
+
+    pub fn block_number(&self) -> u32 {
+        (self.bi_hi as u32) << 16 | self.bi_lo as u32
+    }
+}
+
 impl ByteEncoded for BlockIdData {
     fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.bi_hi.encode());
-        buf.extend(self.bi_lo.encode());
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
         buf
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.bi_hi.encode_into(buf);
+        self.bi_lo.encode_into(buf);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let bi_hi = u16::decode(&bytes[0..2])?;
         let bi_lo = u16::decode(&bytes[2..4])?;
@@ -54,3 +72,29 @@ impl ByteEncoded for BlockIdData {
         4
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_number_zero() {
+        let block_id = BlockIdData::from_block_number(0);
+        assert_eq!(block_id, BlockIdData { bi_hi: 0, bi_lo: 0 });
+        assert_eq!(block_id.block_number(), 0);
+    }
+
+    #[test]
+    fn test_block_number_max() {
+        let block_id = BlockIdData::from_block_number(u32::MAX);
+        assert_eq!(block_id, BlockIdData { bi_hi: u16::MAX, bi_lo: u16::MAX });
+        assert_eq!(block_id.block_number(), u32::MAX);
+    }
+
+    #[test]
+    fn test_block_number_halves() {
+        let block_id = BlockIdData { bi_hi: 1, bi_lo: 2 };
+        assert_eq!(block_id.block_number(), 65538);
+        assert_eq!(BlockIdData::from_block_number(65538), block_id);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{
+    util::{read_f64, read_u16, read_u32, ByteEncodeResult, ByteEncoded, GetByteSliceExt},
+    Error,
+};
+
+use super::page_lazy::PageLazy;
+
+/// page is a leaf
+pub const BTP_LEAF: u16 = 1 << 0;
+/// page is the root of the tree
+pub const BTP_ROOT: u16 = 1 << 1;
+/// page has been deleted from the tree
+pub const BTP_DELETED: u16 = 1 << 2;
+/// page is the metapage
+pub const BTP_META: u16 = 1 << 3;
+/// empty, but still in the tree
+pub const BTP_HALF_DEAD: u16 = 1 << 4;
+/// rightmost page of a split group
+pub const BTP_SPLIT_END: u16 = 1 << 5;
+/// page has LP_DEAD tuples (deprecated)
+pub const BTP_HAS_GARBAGE: u16 = 1 << 6;
+/// right sibling's downlink is missing
+pub const BTP_INCOMPLETE_SPLIT: u16 = 1 << 7;
+/// a deleted page that holds a BTDeletedPageData with a full transaction id
+pub const BTP_HAS_FULLXID: u16 = 1 << 8;
+
+/// Magic number stored at the start of the metapage.
+pub const BTREE_MAGIC: u32 = 0x053162;
+/// Block number of the metapage.
+pub const BTREE_METAPAGE: u32 = 0;
+/// Metapage version written by Postgres 13 and later.
+pub const BTREE_VERSION: u32 = 4;
+/// Oldest metapage version that has the fields after `btm_fastlevel`.
+pub const BTREE_NOVAC_VERSION: u32 = 3;
+
+///
+/// The contents of a B-tree metapage (BTMetaPageData), the first block of every index.
+///
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Default)]
+pub struct BTMetaPage {
+    /// should contain BTREE_MAGIC
+    pub btm_magic: u32,
+    /// nbtree version, BTREE_VERSION for current indexes
+    pub btm_version: u32,
+    /// current root location
+    pub btm_root: u32,
+    /// tree level of the root page
+    pub btm_level: u32,
+    /// current "fast" root location
+    pub btm_fastroot: u32,
+    /// tree level of the "fast" root page
+    pub btm_fastlevel: u32,
+    /// number of deleted, not yet recycled pages found by the last cleanup. Before
+    /// Postgres 14 this held btm_oldest_btpo_xact instead.
+    pub btm_last_cleanup_num_delpages: u32,
+    /// number of heap tuples during the last cleanup, -1 if unknown
+    pub btm_last_cleanup_num_heap_tuples: f64,
+    /// whether deduplication can be used safely
+    pub btm_allequalimage: bool,
+}
+
+impl ByteEncoded for BTMetaPage {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.btm_magic.encode_into(buf);
+        self.btm_version.encode_into(buf);
+        self.btm_root.encode_into(buf);
+        self.btm_level.encode_into(buf);
+        self.btm_fastroot.encode_into(buf);
+        self.btm_fastlevel.encode_into(buf);
+        self.btm_last_cleanup_num_delpages.encode_into(buf);
+        // padding to align the float8
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(&self.btm_last_cleanup_num_heap_tuples.to_le_bytes());
+        buf.push(self.btm_allequalimage as u8);
+        buf.extend_from_slice(&[0; 7]);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        Ok(BTMetaPage {
+            btm_magic: read_u32(&bytes[0..4]),
+            btm_version: read_u32(&bytes[4..8]),
+            btm_root: read_u32(&bytes[8..12]),
+            btm_level: read_u32(&bytes[12..16]),
+            btm_fastroot: read_u32(&bytes[16..20]),
+            btm_fastlevel: read_u32(&bytes[20..24]),
+            btm_last_cleanup_num_delpages: read_u32(&bytes[24..28]),
+            btm_last_cleanup_num_heap_tuples: read_f64(&bytes[32..40]),
+            btm_allequalimage: bytes[40] != 0,
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 48];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        48
+    }
+}
+
+///
+/// The special space of a B-tree page (BTPageOpaqueData).
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct BTPageOpaque {
+    /// left sibling, or 0 if leftmost
+    pub btpo_prev: u32,
+    /// right sibling, or 0 if rightmost
+    pub btpo_next: u32,
+    /// tree level, 0 for leaves. Before Postgres 14 this was a union that held the
+    /// deleting transaction's xid on deleted pages instead.
+    pub btpo_level: u32,
+    /// BTP_* flag bits
+    pub btpo_flags: u16,
+    /// vacuum cycle ID of the latest split
+    pub btpo_cycleid: u16,
+}
+
+impl BTPageOpaque {
+    pub fn is_leaf(&self) -> bool {
+        self.btpo_flags & BTP_LEAF != 0
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.btpo_flags & BTP_ROOT != 0
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.btpo_flags & BTP_DELETED != 0
+    }
+
+    pub fn is_meta(&self) -> bool {
+        self.btpo_flags & BTP_META != 0
+    }
+
+    /// Whether the page is half-dead: unlinked from its parent, awaiting deletion.
+    pub fn is_half_dead(&self) -> bool {
+        self.btpo_flags & BTP_HALF_DEAD != 0
+    }
+
+    /// Whether the page is the rightmost page of a split group, which vacuum has to
+    /// revisit when the split happened during its cycle.
+    pub fn is_split_end(&self) -> bool {
+        self.btpo_flags & BTP_SPLIT_END != 0
+    }
+
+    /// Whether the page has LP_DEAD tuples. Postgres no longer relies on this bit.
+    pub fn has_garbage(&self) -> bool {
+        self.btpo_flags & BTP_HAS_GARBAGE != 0
+    }
+
+    /// The vacuum cycle ID of the latest split of the page, 0 if none is pending.
+    pub fn vacuum_cycle_id(&self) -> u16 {
+        self.btpo_cycleid
+    }
+
+    /// Whether index maintenance still has work on the page: deleted or half-dead, or
+    /// split during a vacuum cycle.
+    pub fn needs_cleanup(&self) -> bool {
+        self.is_deleted() || self.is_half_dead() || self.btpo_cycleid != 0
+    }
+}
+
+impl ByteEncoded for BTPageOpaque {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.btpo_prev.encode_into(buf);
+        self.btpo_next.encode_into(buf);
+        self.btpo_level.encode_into(buf);
+        self.btpo_flags.encode_into(buf);
+        self.btpo_cycleid.encode_into(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        Ok(BTPageOpaque {
+            btpo_prev: read_u32(&bytes[0..4]),
+            btpo_next: read_u32(&bytes[4..8]),
+            btpo_level: read_u32(&bytes[8..12]),
+            btpo_flags: read_u16(&bytes[12..14]),
+            btpo_cycleid: read_u16(&bytes[14..16]),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 16];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        16
+    }
+}
+
+impl PageLazy {
+    /// The B-tree opaque data kept in the special space of an index page.
+    pub fn btree_opaque(&self) -> Result<BTPageOpaque, Error> {
+        Ok(BTPageOpaque::decode(self.special_bytes()?)?)
+    }
+
+    /// The B-tree metapage contents, read from the start of the page's data area.
+    /// Fails unless `btm_magic` is BTREE_MAGIC.
+    pub fn btree_meta(&self) -> Result<BTMetaPage, Error> {
+        let meta = BTMetaPage::decode(&self.data)?;
+        if meta.btm_magic != BTREE_MAGIC {
+            return Err(Error::InvalidByteEncoding(format!(
+                "btree metapage magic {:#x}, expected {:#x}",
+                meta.btm_magic, BTREE_MAGIC
+            )));
+        }
+        Ok(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{page_lazy, BTREE_LEAF_PAGE, HEAP_PAGE},
+        util::ByteEncodeError,
+    };
+
+    /// Metapage (block 0) of the btree index whose leaf is `BTREE_LEAF_PAGE`.
+    const BTREE_META_PAGE: &[u8] = include_bytes!("../../test-data/btree_meta_page.bin");
+
+    #[test]
+    fn test_btree_opaque_leaf_page() {
+        let page = page_lazy(BTREE_LEAF_PAGE);
+        let opaque = page.btree_opaque().unwrap();
+        assert_eq!(
+            opaque,
+            BTPageOpaque {
+                btpo_prev: 0,
+                btpo_next: 0,
+                btpo_level: 0,
+                btpo_flags: BTP_LEAF | BTP_ROOT,
+                btpo_cycleid: 0,
+            }
+        );
+        assert!(opaque.is_leaf() && opaque.is_root());
+        assert!(!opaque.is_deleted() && !opaque.is_meta());
+        assert_eq!(opaque.encode(), page.special_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_btree_opaque_flags() {
+        let opaque = |btpo_flags: u16| BTPageOpaque {
+            btpo_flags,
+            ..Default::default()
+        };
+        assert!(opaque(BTP_DELETED).is_deleted());
+        assert!(opaque(BTP_HALF_DEAD).is_half_dead());
+        assert!(opaque(BTP_SPLIT_END).is_split_end());
+        assert!(opaque(BTP_HAS_GARBAGE).has_garbage());
+
+        let leaf = opaque(BTP_LEAF);
+        assert!(!leaf.is_deleted() && !leaf.is_half_dead() && !leaf.is_split_end() && !leaf.has_garbage());
+        assert!(!leaf.needs_cleanup());
+        assert!(opaque(BTP_LEAF | BTP_DELETED).needs_cleanup());
+        assert!(opaque(BTP_LEAF | BTP_HALF_DEAD).needs_cleanup());
+        assert!(!opaque(BTP_LEAF | BTP_HAS_GARBAGE).needs_cleanup());
+    }
+
+    #[test]
+    fn test_btree_vacuum_cycle_id() {
+        let opaque = BTPageOpaque {
+            btpo_flags: BTP_LEAF | BTP_SPLIT_END,
+            btpo_cycleid: 0x1234,
+            ..Default::default()
+        };
+        assert_eq!(opaque.vacuum_cycle_id(), 0x1234);
+        assert!(opaque.needs_cleanup());
+        assert_eq!(BTPageOpaque::decode(&opaque.encode()).unwrap().vacuum_cycle_id(), 0x1234);
+    }
+
+    #[test]
+    fn test_btree_opaque_heap_page() {
+        assert!(matches!(
+            page_lazy(HEAP_PAGE).btree_opaque(),
+            Err(Error::ByteEncoding(ByteEncodeError::NotEnoughBytes { expected: 16, actual: 0 }))
+        ));
+    }
+
+    #[test]
+    fn test_btree_meta() {
+        let page = page_lazy(BTREE_META_PAGE);
+        let meta = page.btree_meta().unwrap();
+        assert_eq!(
+            meta,
+            BTMetaPage {
+                btm_magic: BTREE_MAGIC,
+                btm_version: BTREE_VERSION,
+                btm_root: 1,
+                btm_level: 0,
+                btm_fastroot: 1,
+                btm_fastlevel: 0,
+                btm_last_cleanup_num_delpages: 0,
+                btm_last_cleanup_num_heap_tuples: -1.0,
+                btm_allequalimage: true,
+            }
+        );
+        assert_eq!(meta.encode(), &page.data[..48]);
+        assert!(page.btree_opaque().unwrap().is_meta());
+    }
+
+    #[test]
+    fn test_btree_meta_bad_magic() {
+        assert!(matches!(page_lazy(BTREE_LEAF_PAGE).btree_meta(), Err(Error::InvalidByteEncoding(_))));
+        assert!(matches!(page_lazy(HEAP_PAGE).btree_meta(), Err(Error::InvalidByteEncoding(_))));
+    }
+}
@@ -0,0 +1,382 @@
+//! `global/pg_control`, the cluster's control file, in the layout of pg_control_version
+//! 1300 (Postgres 13 to 16). The struct is followed by zero padding up to
+//! PG_CONTROL_FILE_SIZE bytes.
+
+use crate::{
+    checksum::crc32c,
+    util::{ByteEncodeResult, ByteEncoded, DecodeContext, GetByteSliceExt},
+};
+
+/// pg_control_version this layout matches
+pub const PG_CONTROL_VERSION: u32 = 1300;
+/// Size of the pg_control file on disk
+pub const PG_CONTROL_FILE_SIZE: usize = 8192;
+/// Length of `mock_authentication_nonce`
+pub const MOCK_AUTH_NONCE_LEN: usize = 32;
+/// Offset of the `crc` field, which covers every byte before it
+const CRC_OFFSET: usize = 288;
+
+/// DBState values of `state`
+pub const DB_STARTUP: u32 = 0;
+pub const DB_SHUTDOWNED: u32 = 1;
+pub const DB_SHUTDOWNED_IN_RECOVERY: u32 = 2;
+pub const DB_SHUTDOWNING: u32 = 3;
+pub const DB_IN_CRASH_RECOVERY: u32 = 4;
+pub const DB_IN_ARCHIVE_RECOVERY: u32 = 5;
+pub const DB_IN_PRODUCTION: u32 = 6;
+
+///
+/// The contents of a checkpoint record (CheckPoint), as copied into pg_control.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct CheckPoint {
+    /// where to start replay: the LSN of the record logged just before the checkpoint began
+    pub redo: u64,
+    /// current timeline
+    pub this_timeline_id: u32,
+    /// previous timeline, if this record begins a new one, else the same as above
+    pub prev_timeline_id: u32,
+    /// whether full_page_writes was on
+    pub full_page_writes: bool,
+    /// next free transaction id, with its epoch in the high 32 bits
+    pub next_xid: u64,
+    /// next free OID
+    pub next_oid: u32,
+    /// next free MultiXactId
+    pub next_multi: u32,
+    /// next free MultiXact offset
+    pub next_multi_offset: u32,
+    /// cluster-wide minimum datfrozenxid
+    pub oldest_xid: u32,
+    /// database with minimum datfrozenxid
+    pub oldest_xid_db: u32,
+    /// cluster-wide minimum datminmxid
+    pub oldest_multi: u32,
+    /// database with minimum datminmxid
+    pub oldest_multi_db: u32,
+    /// time stamp of the checkpoint, in seconds since the Unix epoch
+    pub time: i64,
+    /// oldest xid with a valid commit timestamp
+    pub oldest_commit_ts_xid: u32,
+    /// newest xid with a valid commit timestamp
+    pub newest_commit_ts_xid: u32,
+    /// oldest xid still running, only set by online checkpoints
+    pub oldest_active_xid: u32,
+}
+
+impl ByteEncoded for CheckPoint {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.redo.encode_into(buf);
+        self.this_timeline_id.encode_into(buf);
+        self.prev_timeline_id.encode_into(buf);
+        self.full_page_writes.encode_into(buf);
+        buf.extend_from_slice(&[0; 7]);
+        self.next_xid.encode_into(buf);
+        self.next_oid.encode_into(buf);
+        self.next_multi.encode_into(buf);
+        self.next_multi_offset.encode_into(buf);
+        self.oldest_xid.encode_into(buf);
+        self.oldest_xid_db.encode_into(buf);
+        self.oldest_multi.encode_into(buf);
+        self.oldest_multi_db.encode_into(buf);
+        buf.extend_from_slice(&[0; 4]);
+        self.time.encode_into(buf);
+        self.oldest_commit_ts_xid.encode_into(buf);
+        self.newest_commit_ts_xid.encode_into(buf);
+        self.oldest_active_xid.encode_into(buf);
+        buf.extend_from_slice(&[0; 4]);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let field =
+            |start: usize, len: usize, context: &'static str| bytes.get_byte_slice(start, start + len).at(context);
+        Ok(CheckPoint {
+            redo: u64::decode(field(0, 8, "CheckPoint.redo")?)?,
+            this_timeline_id: u32::decode(field(8, 4, "CheckPoint.ThisTimeLineID")?)?,
+            prev_timeline_id: u32::decode(field(12, 4, "CheckPoint.PrevTimeLineID")?)?,
+            full_page_writes: bool::decode(field(16, 1, "CheckPoint.fullPageWrites")?)
+                .at("CheckPoint.fullPageWrites")?,
+            next_xid: u64::decode(field(24, 8, "CheckPoint.nextXid")?)?,
+            next_oid: u32::decode(field(32, 4, "CheckPoint.nextOid")?)?,
+            next_multi: u32::decode(field(36, 4, "CheckPoint.nextMulti")?)?,
+            next_multi_offset: u32::decode(field(40, 4, "CheckPoint.nextMultiOffset")?)?,
+            oldest_xid: u32::decode(field(44, 4, "CheckPoint.oldestXid")?)?,
+            oldest_xid_db: u32::decode(field(48, 4, "CheckPoint.oldestXidDB")?)?,
+            oldest_multi: u32::decode(field(52, 4, "CheckPoint.oldestMulti")?)?,
+            oldest_multi_db: u32::decode(field(56, 4, "CheckPoint.oldestMultiDB")?)?,
+            time: i64::decode(field(64, 8, "CheckPoint.time")?)?,
+            oldest_commit_ts_xid: u32::decode(field(72, 4, "CheckPoint.oldestCommitTsXid")?)?,
+            newest_commit_ts_xid: u32::decode(field(76, 4, "CheckPoint.newestCommitTsXid")?)?,
+            oldest_active_xid: u32::decode(field(80, 4, "CheckPoint.oldestActiveXid")?)?,
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 88];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        88
+    }
+}
+
+///
+/// The contents of `global/pg_control` (ControlFileData).
+///
+#[derive(Debug, PartialEq, PartialOrd, Clone, Default)]
+pub struct ControlFileData {
+    /// unique system identifier, to make sure WAL files match the cluster
+    pub system_identifier: u64,
+    /// PG_CONTROL_VERSION of the server that wrote the file
+    pub pg_control_version: u32,
+    /// catalog version, CATALOG_VERSION_NO
+    pub catalog_version_no: u32,
+    /// DB_* state of the cluster
+    pub state: u32,
+    /// time stamp of the last pg_control update
+    pub time: i64,
+    /// LSN of the last checkpoint record
+    pub check_point: u64,
+    /// copy of the last checkpoint record
+    pub check_point_copy: CheckPoint,
+    /// current fake LSN value, for unlogged relations
+    pub unlogged_lsn: u64,
+    /// WAL position recovery must reach before the database is consistent
+    pub min_recovery_point: u64,
+    pub min_recovery_point_tli: u32,
+    /// start of an online backup being restored, or 0
+    pub backup_start_point: u64,
+    /// end of the backup being restored, or 0
+    pub backup_end_point: u64,
+    /// whether an end-of-backup record must be seen before the database is consistent
+    pub backup_end_required: bool,
+    /// wal_level, as the WalLevel enum (0 minimal, 1 replica, 2 logical)
+    pub wal_level: i32,
+    pub wal_log_hints: bool,
+    pub max_connections: i32,
+    pub max_worker_processes: i32,
+    pub max_wal_senders: i32,
+    pub max_prepared_xacts: i32,
+    pub max_locks_per_xact: i32,
+    pub track_commit_timestamp: bool,
+    /// MAXIMUM_ALIGNOF of the server build
+    pub max_align: u32,
+    /// 1234567.0, to check the float format
+    pub float_format: f64,
+    /// BLCKSZ of the server build
+    pub blcksz: u32,
+    /// RELSEG_SIZE of the server build
+    pub relseg_size: u32,
+    pub xlog_blcksz: u32,
+    pub xlog_seg_size: u32,
+    pub name_data_len: u32,
+    pub index_max_keys: u32,
+    pub toast_max_chunk_size: u32,
+    pub loblksize: u32,
+    pub float8_by_val: bool,
+    /// 0 when data checksums are off
+    pub data_checksum_version: u32,
+    pub mock_authentication_nonce: [u8; MOCK_AUTH_NONCE_LEN],
+    /// CRC-32C of all the bytes before it
+    pub crc: u32,
+}
+
+impl ControlFileData {
+    /// CRC-32C of the encoded fields before `crc`, as Postgres computes it.
+    pub fn compute_crc(&self) -> u32 {
+        crc32c(&self.encode()[..CRC_OFFSET])
+    }
+
+    /// Whether the stored `crc` matches the contents, which fails for a torn or corrupt
+    /// file as well as for a pg_control_version with another layout.
+    pub fn verify_crc(&self) -> bool {
+        self.crc == self.compute_crc()
+    }
+}
+
+impl ByteEncoded for ControlFileData {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.system_identifier.encode_into(buf);
+        self.pg_control_version.encode_into(buf);
+        self.catalog_version_no.encode_into(buf);
+        self.state.encode_into(buf);
+        buf.extend_from_slice(&[0; 4]);
+        self.time.encode_into(buf);
+        self.check_point.encode_into(buf);
+        self.check_point_copy.encode_into(buf);
+        self.unlogged_lsn.encode_into(buf);
+        self.min_recovery_point.encode_into(buf);
+        self.min_recovery_point_tli.encode_into(buf);
+        buf.extend_from_slice(&[0; 4]);
+        self.backup_start_point.encode_into(buf);
+        self.backup_end_point.encode_into(buf);
+        self.backup_end_required.encode_into(buf);
+        buf.extend_from_slice(&[0; 3]);
+        self.wal_level.encode_into(buf);
+        self.wal_log_hints.encode_into(buf);
+        buf.extend_from_slice(&[0; 3]);
+        self.max_connections.encode_into(buf);
+        self.max_worker_processes.encode_into(buf);
+        self.max_wal_senders.encode_into(buf);
+        self.max_prepared_xacts.encode_into(buf);
+        self.max_locks_per_xact.encode_into(buf);
+        self.track_commit_timestamp.encode_into(buf);
+        buf.extend_from_slice(&[0; 3]);
+        self.max_align.encode_into(buf);
+        self.float_format.encode_into(buf);
+        self.blcksz.encode_into(buf);
+        self.relseg_size.encode_into(buf);
+        self.xlog_blcksz.encode_into(buf);
+        self.xlog_seg_size.encode_into(buf);
+        self.name_data_len.encode_into(buf);
+        self.index_max_keys.encode_into(buf);
+        self.toast_max_chunk_size.encode_into(buf);
+        self.loblksize.encode_into(buf);
+        self.float8_by_val.encode_into(buf);
+        buf.extend_from_slice(&[0; 3]);
+        self.data_checksum_version.encode_into(buf);
+        self.mock_authentication_nonce.encode_into(buf);
+        self.crc.encode_into(buf);
+        buf.extend_from_slice(&[0; 4]);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let field =
+            |start: usize, len: usize, context: &'static str| bytes.get_byte_slice(start, start + len).at(context);
+        Ok(ControlFileData {
+            system_identifier: u64::decode(field(0, 8, "ControlFileData.system_identifier")?)?,
+            pg_control_version: u32::decode(field(8, 4, "ControlFileData.pg_control_version")?)?,
+            catalog_version_no: u32::decode(field(12, 4, "ControlFileData.catalog_version_no")?)?,
+            state: u32::decode(field(16, 4, "ControlFileData.state")?)?,
+            time: i64::decode(field(24, 8, "ControlFileData.time")?)?,
+            check_point: u64::decode(field(32, 8, "ControlFileData.checkPoint")?)?,
+            check_point_copy: CheckPoint::decode(field(40, 88, "ControlFileData.checkPointCopy")?)
+                .at("ControlFileData.checkPointCopy")?,
+            unlogged_lsn: u64::decode(field(128, 8, "ControlFileData.unloggedLSN")?)?,
+            min_recovery_point: u64::decode(field(136, 8, "ControlFileData.minRecoveryPoint")?)?,
+            min_recovery_point_tli: u32::decode(field(144, 4, "ControlFileData.minRecoveryPointTLI")?)?,
+            backup_start_point: u64::decode(field(152, 8, "ControlFileData.backupStartPoint")?)?,
+            backup_end_point: u64::decode(field(160, 8, "ControlFileData.backupEndPoint")?)?,
+            backup_end_required: bool::decode(field(168, 1, "ControlFileData.backupEndRequired")?)
+                .at("ControlFileData.backupEndRequired")?,
+            wal_level: i32::decode(field(172, 4, "ControlFileData.wal_level")?)?,
+            wal_log_hints: bool::decode(field(176, 1, "ControlFileData.wal_log_hints")?)
+                .at("ControlFileData.wal_log_hints")?,
+            max_connections: i32::decode(field(180, 4, "ControlFileData.MaxConnections")?)?,
+            max_worker_processes: i32::decode(field(184, 4, "ControlFileData.max_worker_processes")?)?,
+            max_wal_senders: i32::decode(field(188, 4, "ControlFileData.max_wal_senders")?)?,
+            max_prepared_xacts: i32::decode(field(192, 4, "ControlFileData.max_prepared_xacts")?)?,
+            max_locks_per_xact: i32::decode(field(196, 4, "ControlFileData.max_locks_per_xact")?)?,
+            track_commit_timestamp: bool::decode(field(200, 1, "ControlFileData.track_commit_timestamp")?)
+                .at("ControlFileData.track_commit_timestamp")?,
+            max_align: u32::decode(field(204, 4, "ControlFileData.maxAlign")?)?,
+            float_format: f64::decode(field(208, 8, "ControlFileData.floatFormat")?)?,
+            blcksz: u32::decode(field(216, 4, "ControlFileData.blcksz")?)?,
+            relseg_size: u32::decode(field(220, 4, "ControlFileData.relseg_size")?)?,
+            xlog_blcksz: u32::decode(field(224, 4, "ControlFileData.xlog_blcksz")?)?,
+            xlog_seg_size: u32::decode(field(228, 4, "ControlFileData.xlog_seg_size")?)?,
+            name_data_len: u32::decode(field(232, 4, "ControlFileData.nameDataLen")?)?,
+            index_max_keys: u32::decode(field(236, 4, "ControlFileData.indexMaxKeys")?)?,
+            toast_max_chunk_size: u32::decode(field(240, 4, "ControlFileData.toast_max_chunk_size")?)?,
+            loblksize: u32::decode(field(244, 4, "ControlFileData.loblksize")?)?,
+            float8_by_val: bool::decode(field(248, 1, "ControlFileData.float8ByVal")?)
+                .at("ControlFileData.float8ByVal")?,
+            data_checksum_version: u32::decode(field(252, 4, "ControlFileData.data_checksum_version")?)?,
+            mock_authentication_nonce: <[u8; MOCK_AUTH_NONCE_LEN]>::decode(field(
+                256,
+                MOCK_AUTH_NONCE_LEN,
+                "ControlFileData.mock_authentication_nonce",
+            )?)?,
+            crc: u32::decode(field(CRC_OFFSET, 4, "ControlFileData.crc")?)?,
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 296];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        296
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// pg_control of a Postgres 15 cluster in production, with data checksums on.
+    const PG_CONTROL: &[u8] = include_bytes!("../../test-data/pg_control.bin");
+
+    #[test]
+    fn test_decode_pg_control() {
+        assert_eq!(PG_CONTROL.len(), PG_CONTROL_FILE_SIZE);
+        let control = ControlFileData::decode(PG_CONTROL).unwrap();
+        assert_eq!(control.system_identifier, 7697245161540327200);
+        assert_eq!(control.pg_control_version, PG_CONTROL_VERSION);
+        assert_eq!(control.catalog_version_no, 202209061);
+        assert_eq!(control.state, DB_IN_PRODUCTION);
+        assert_eq!(control.check_point, 0x18E_5FC0);
+        assert_eq!(control.unlogged_lsn, 0x3E8);
+        assert_eq!(control.wal_level, 1);
+        assert_eq!(control.max_connections, 100);
+        assert_eq!(control.float_format, 1234567.0);
+        assert_eq!((control.blcksz, control.relseg_size), (8192, 131072));
+        assert_eq!(control.xlog_seg_size, 16 * 1024 * 1024);
+        assert_eq!(control.toast_max_chunk_size, 1996);
+        assert_eq!(control.data_checksum_version, 1);
+
+        let checkpoint = control.check_point_copy;
+        assert_eq!(checkpoint.redo, 0x18E_5F88);
+        assert_eq!((checkpoint.this_timeline_id, checkpoint.prev_timeline_id), (1, 1));
+        assert!(checkpoint.full_page_writes);
+        assert_eq!(checkpoint.next_xid, 745);
+        assert_eq!(checkpoint.next_oid, 24576);
+        assert_eq!((checkpoint.next_multi, checkpoint.next_multi_offset), (1, 0));
+        assert_eq!((checkpoint.oldest_xid, checkpoint.oldest_xid_db), (716, 1));
+        assert_eq!(checkpoint.oldest_active_xid, 745);
+        assert_eq!(checkpoint.time, control.time);
+    }
+
+    #[test]
+    fn test_verify_crc() {
+        let control = ControlFileData::decode(PG_CONTROL).unwrap();
+        assert!(control.verify_crc());
+        assert_eq!(control.encode(), &PG_CONTROL[..296]);
+
+        let mut changed = control.clone();
+        changed.state = DB_SHUTDOWNED;
+        assert!(!changed.verify_crc());
+        changed.crc = changed.compute_crc();
+        assert!(changed.verify_crc());
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let err = ControlFileData::decode(&PG_CONTROL[..100]).unwrap_err();
+        assert!(err.to_string().contains("ControlFileData.checkPointCopy"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{Read, Seek};
+
+use crate::{compile_constants::BLCKSZ, page_reader::PageReader, util::ByteEncodeResult};
+
+use super::page_lazy::PageLazy;
+
+/// Number of free space categories, one byte each
+pub const FSM_CATEGORIES: u32 = 256;
+/// Bytes of free space each category step stands for
+pub const FSM_CAT_STEP: u32 = BLCKSZ / FSM_CATEGORIES;
+/// Free space reported for the top category: MaxHeapTupleSize, the largest request a
+/// page can satisfy
+pub const MAX_FSM_REQUEST_SIZE: u32 = BLCKSZ - 32;
+/// Nodes of the binary tree in each FSM page: what is left after the page header and
+/// `fp_next_slot`
+pub const NODES_PER_PAGE: u32 = BLCKSZ - 24 - 4;
+/// Inner nodes of each page's tree
+pub const NON_LEAF_NODES_PER_PAGE: u32 = BLCKSZ / 2 - 1;
+/// Leaf nodes of each page's tree, one per heap block or lower-level FSM page
+pub const SLOTS_PER_FSM_PAGE: u32 = NODES_PER_PAGE - NON_LEAF_NODES_PER_PAGE;
+/// Levels of FSM pages, enough to address every heap block
+pub const FSM_TREE_DEPTH: u32 = 3;
+
+/// Approximate free bytes a category stands for, like fsm_space_cat_to_avail.
+pub fn category_to_avail(category: u8) -> u16 {
+    if category as u32 == FSM_CATEGORIES - 1 {
+        MAX_FSM_REQUEST_SIZE as u16
+    } else {
+        (category as u32 * FSM_CAT_STEP) as u16
+    }
+}
+
+/// Physical block of the bottom-level FSM page holding `heap_block`, and the leaf slot
+/// within it, like fsm_get_location followed by fsm_logical_to_physical.
+///
+/// FSM pages are stored depth first: the root at block 0, then the first level-1 page, then
+/// the bottom-level pages it covers, and so on.
+pub fn fsm_location(heap_block: u32) -> (u32, u32) {
+    let logical_page = heap_block / SLOTS_PER_FSM_PAGE;
+    let slot = heap_block % SLOTS_PER_FSM_PAGE;
+    let mut leafno = logical_page;
+    let mut pages = 0;
+    for _ in 0..FSM_TREE_DEPTH {
+        pages += leafno + 1;
+        leafno /= SLOTS_PER_FSM_PAGE;
+    }
+    (pages - 1, slot)
+}
+
+///
+/// The free space map fork (`<filenode>_fsm`) of a relation: a tree of FSM pages whose
+/// bottom level keeps a one-byte free space category per heap block.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FreeSpaceMap {
+    pub pages: Vec<PageLazy>,
+}
+
+impl FreeSpaceMap {
+    pub fn new(pages: Vec<PageLazy>) -> Self {
+        FreeSpaceMap { pages }
+    }
+
+    /// Reads every page of a free space map fork.
+    pub fn from_reader<R: Read + Seek>(reader: PageReader<R>) -> ByteEncodeResult<Self> {
+        Ok(FreeSpaceMap {
+            pages: reader.into_iter().collect::<ByteEncodeResult<_>>()?,
+        })
+    }
+
+    /// The stored free space category of `heap_block`, 0 if the map doesn't reach it.
+    pub fn category(&self, heap_block: u32) -> u8 {
+        let (fsm_block, slot) = fsm_location(heap_block);
+        // fp_nodes starts after fp_next_slot, leaves after the inner nodes
+        let node = (4 + NON_LEAF_NODES_PER_PAGE + slot) as usize;
+        self.pages
+            .get(fsm_block as usize)
+            .and_then(|page| page.data.get(node))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Approximate free bytes Postgres believes `heap_block` has. Categories are only
+    /// updated by vacuum and when an insert finds a page fuller than recorded, so this can
+    /// be stale.
+    pub fn free_space(&self, heap_block: u32) -> u16 {
+        category_to_avail(self.category(heap_block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, page_lazy, relation_reader};
+
+    /// FSM fork of `fixture_heap` after a vacuum, three pages for its single heap block.
+    const HEAP_FSM: &[u8] = include_bytes!("../../test-data/heap_fsm.bin");
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(SLOTS_PER_FSM_PAGE, 4069);
+        assert_eq!(FSM_CAT_STEP, 32);
+    }
+
+    #[test]
+    fn test_category_to_avail() {
+        assert_eq!(category_to_avail(0), 0);
+        assert_eq!(category_to_avail(190), 6080);
+        assert_eq!(category_to_avail(254), 8128);
+        assert_eq!(category_to_avail(255), 8160);
+    }
+
+    #[test]
+    fn test_fsm_location() {
+        assert_eq!(fsm_location(0), (2, 0));
+        assert_eq!(fsm_location(4068), (2, 4068));
+        assert_eq!(fsm_location(4069), (3, 0));
+        assert_eq!(fsm_location(2 * 4069 + 5), (4, 5));
+        // the second level-1 page comes before the bottom pages it covers
+        assert_eq!(fsm_location(4069 * 4069), (4069 + 3, 0));
+    }
+
+    #[test]
+    fn test_free_space_fixture() {
+        let map = FreeSpaceMap::from_reader(relation_reader(&[HEAP_FSM.to_vec()])).unwrap();
+        assert_eq!(map.pages.len(), 3);
+        assert_eq!(map.category(0), 190);
+        assert_eq!(map.free_space(0), 6080);
+        assert_eq!(map.free_space(1), 0);
+        assert_eq!(map.free_space(4069), 0);
+    }
+
+    #[test]
+    fn test_free_space_second_page() {
+        let mut leaf = page_bytes(&[]);
+        // heap block 4069 + 7, slot 7 of the second bottom-level page
+        leaf[24 + 4 + 4095 + 7] = 255;
+        let empty = || page_lazy(&page_bytes(&[]));
+        let map = FreeSpaceMap::new(vec![empty(), empty(), empty(), page_lazy(&leaf)]);
+        assert_eq!(map.free_space(4069 + 7), 8160);
+        assert_eq!(map.free_space(7), 0);
+    }
+}
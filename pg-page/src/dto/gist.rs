@@ -0,0 +1,137 @@
+use crate::{
+    util::{read_u16, read_u32, ByteEncodeResult, ByteEncoded, GetByteSliceExt},
+    Error,
+};
+
+use super::{page_lazy::PageLazy, page_xl_log_rex_ptr::PageXLogRecPtr};
+
+/// leaf page
+pub const F_LEAF: u16 = 1 << 0;
+/// the page has been deleted
+pub const F_DELETED: u16 = 1 << 1;
+/// some tuples on the page were deleted
+pub const F_TUPLES_DELETED: u16 = 1 << 2;
+/// page to the right has no downlink
+pub const F_FOLLOW_RIGHT: u16 = 1 << 3;
+/// some tuples on the page are dead, but not deleted yet
+pub const F_HAS_GARBAGE: u16 = 1 << 4;
+
+/// Identifies GiST pages, kept in the last two bytes of the special space.
+pub const GIST_PAGE_ID: u16 = 0xFF81;
+
+///
+/// The special space of a GiST page (GISTPageOpaqueData).
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct GistPageOpaque {
+    /// node sequence number: the LSN of the split that created the right sibling
+    pub nsn: PageXLogRecPtr,
+    /// next page if any
+    pub rightlink: u32,
+    /// F_* flag bits
+    pub flags: u16,
+    /// should contain GIST_PAGE_ID
+    pub gist_page_id: u16,
+}
+
+impl GistPageOpaque {
+    pub fn is_leaf(&self) -> bool {
+        self.flags & F_LEAF != 0
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.flags & F_DELETED != 0
+    }
+}
+
+impl ByteEncoded for GistPageOpaque {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.nsn.encode_into(buf);
+        self.rightlink.encode_into(buf);
+        self.flags.encode_into(buf);
+        self.gist_page_id.encode_into(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        Ok(GistPageOpaque {
+            nsn: PageXLogRecPtr::decode(&bytes[0..8])?,
+            rightlink: read_u32(&bytes[8..12]),
+            flags: read_u16(&bytes[12..14]),
+            gist_page_id: read_u16(&bytes[14..16]),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 16];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        16
+    }
+}
+
+impl PageLazy {
+    /// The GiST opaque data kept in the special space of an index page.
+    /// Fails unless `gist_page_id` is GIST_PAGE_ID.
+    pub fn gist_opaque(&self) -> Result<GistPageOpaque, Error> {
+        let opaque = GistPageOpaque::decode(self.special_bytes()?)?;
+        if opaque.gist_page_id != GIST_PAGE_ID {
+            return Err(Error::InvalidByteEncoding(format!(
+                "GiST page id {:#x}, expected {:#x}",
+                opaque.gist_page_id, GIST_PAGE_ID
+            )));
+        }
+        Ok(opaque)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::page_with_special;
+
+    #[test]
+    fn test_gist_opaque_root_leaf() {
+        // special space of the only page of a GiST index on a point column
+        let special = [0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x81, 0xFF];
+        let opaque = page_with_special(&special).gist_opaque().unwrap();
+        assert_eq!(
+            opaque,
+            GistPageOpaque {
+                nsn: PageXLogRecPtr { xlogid: 0, xrecoff: 0 },
+                rightlink: u32::MAX,
+                flags: F_LEAF,
+                gist_page_id: GIST_PAGE_ID,
+            }
+        );
+        assert!(opaque.is_leaf() && !opaque.is_deleted());
+        assert_eq!(opaque.encode(), special);
+    }
+
+    #[test]
+    fn test_gist_opaque_split_page() {
+        let special = [0, 0, 0, 0, 0x28, 0x60, 0x85, 0x01, 7, 0, 0, 0, 0x0A, 0x00, 0x81, 0xFF];
+        let opaque = GistPageOpaque::decode(&special).unwrap();
+        assert_eq!(opaque.nsn.to_u64(), 0x0185_6028);
+        assert_eq!(opaque.rightlink, 7);
+        assert_eq!(opaque.flags, F_DELETED | F_FOLLOW_RIGHT);
+        assert!(opaque.is_deleted() && !opaque.is_leaf());
+
+        let mut wrong_id = special;
+        wrong_id[14] = 0x82;
+        assert!(matches!(page_with_special(&wrong_id).gist_opaque(), Err(Error::InvalidByteEncoding(_))));
+    }
+}
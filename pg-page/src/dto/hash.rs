@@ -0,0 +1,317 @@
+use crate::{
+    util::{read_f64, read_u16, read_u32, ByteEncodeResult, ByteEncoded, GetByteSliceExt},
+    Error,
+};
+
+use super::page_lazy::PageLazy;
+
+/// unused page, such as one added by a split but never filled
+pub const LH_UNUSED_PAGE: u16 = 0;
+/// overflow page of a bucket chain
+pub const LH_OVERFLOW_PAGE: u16 = 1 << 0;
+/// primary page of a bucket
+pub const LH_BUCKET_PAGE: u16 = 1 << 1;
+/// bitmap page tracking free overflow pages
+pub const LH_BITMAP_PAGE: u16 = 1 << 2;
+/// the metapage
+pub const LH_META_PAGE: u16 = 1 << 3;
+/// new bucket of a split that is still being filled
+pub const LH_BUCKET_BEING_POPULATED: u16 = 1 << 4;
+/// old bucket of a split that is still in progress
+pub const LH_BUCKET_BEING_SPLIT: u16 = 1 << 5;
+/// old bucket still holding tuples moved out by a split
+pub const LH_BUCKET_NEEDS_SPLIT_CLEANUP: u16 = 1 << 6;
+/// page has LP_DEAD tuples
+pub const LH_PAGE_HAS_DEAD_TUPLES: u16 = 1 << 7;
+/// the bits telling the page type
+pub const LH_PAGE_TYPE: u16 = LH_OVERFLOW_PAGE | LH_BUCKET_PAGE | LH_BITMAP_PAGE | LH_META_PAGE;
+
+/// Identifies hash index pages, kept in the last two bytes of the special space.
+pub const HASHO_PAGE_ID: u16 = 0xFF80;
+/// Magic number stored at the start of the metapage.
+pub const HASH_MAGIC: u32 = 0x6440640;
+/// Block number of the metapage.
+pub const HASH_METAPAGE: u32 = 0;
+/// Number of entries in `hashm_spares`.
+pub const HASH_MAX_SPLITPOINTS: usize = 98;
+/// Number of entries in `hashm_mapp` for 8 kB pages.
+pub const HASH_MAX_BITMAPS: usize = 1024;
+
+///
+/// The special space of a hash index page (HashPageOpaqueData).
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct HashPageOpaque {
+    /// previous page in the bucket chain, or for primary bucket pages the
+    /// hashm_maxbucket value as of the last split of the bucket
+    pub hasho_prevblkno: u32,
+    /// next page in the bucket chain
+    pub hasho_nextblkno: u32,
+    /// bucket number this page belongs to
+    pub hasho_bucket: u32,
+    /// page type code and LH_* flag bits
+    pub hasho_flag: u16,
+    /// should contain HASHO_PAGE_ID
+    pub hasho_page_id: u16,
+}
+
+impl HashPageOpaque {
+    /// The LH_* page type bits of `hasho_flag`.
+    pub fn page_type(&self) -> u16 {
+        self.hasho_flag & LH_PAGE_TYPE
+    }
+}
+
+impl ByteEncoded for HashPageOpaque {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.hasho_prevblkno.encode_into(buf);
+        self.hasho_nextblkno.encode_into(buf);
+        self.hasho_bucket.encode_into(buf);
+        self.hasho_flag.encode_into(buf);
+        self.hasho_page_id.encode_into(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        Ok(HashPageOpaque {
+            hasho_prevblkno: read_u32(&bytes[0..4]),
+            hasho_nextblkno: read_u32(&bytes[4..8]),
+            hasho_bucket: read_u32(&bytes[8..12]),
+            hasho_flag: read_u16(&bytes[12..14]),
+            hasho_page_id: read_u16(&bytes[14..16]),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 16];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        16
+    }
+}
+
+///
+/// The contents of a hash index metapage (HashMetaPageData), the first block of every
+/// hash index.
+///
+#[derive(Debug, PartialEq, PartialOrd, Clone, Default)]
+pub struct HashMetaPage {
+    /// should contain HASH_MAGIC
+    pub hashm_magic: u32,
+    /// version ID
+    pub hashm_version: u32,
+    /// number of tuples stored in the table
+    pub hashm_ntuples: f64,
+    /// target fill factor (tuples/bucket)
+    pub hashm_ffactor: u16,
+    /// index page size (bytes)
+    pub hashm_bsize: u16,
+    /// bitmap array size (bytes), must be a power of 2
+    pub hashm_bmsize: u16,
+    /// log2(bitmap array size in bits)
+    pub hashm_bmshift: u16,
+    /// ID of maximum bucket in use
+    pub hashm_maxbucket: u32,
+    /// mask to modulo into entire table
+    pub hashm_highmask: u32,
+    /// mask to modulo into lower half of table
+    pub hashm_lowmask: u32,
+    /// splitpoint from which overflow pages are being allocated
+    pub hashm_ovflpoint: u32,
+    /// lowest-number free overflow page
+    pub hashm_firstfree: u32,
+    /// number of bitmap pages
+    pub hashm_nmaps: u32,
+    /// hash function id from pg_proc
+    pub hashm_procid: u32,
+    /// spare pages before each splitpoint, HASH_MAX_SPLITPOINTS entries
+    pub hashm_spares: Vec<u32>,
+    /// blknos of bitmap pages, HASH_MAX_BITMAPS entries
+    pub hashm_mapp: Vec<u32>,
+}
+
+/// Offset of `hashm_spares` within HashMetaPageData.
+const HASHM_SPARES_OFFSET: usize = 52;
+/// Offset of `hashm_mapp` within HashMetaPageData.
+const HASHM_MAPP_OFFSET: usize = HASHM_SPARES_OFFSET + HASH_MAX_SPLITPOINTS * 4;
+
+impl ByteEncoded for HashMetaPage {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        self.hashm_magic.encode_into(buf);
+        self.hashm_version.encode_into(buf);
+        buf.extend_from_slice(&self.hashm_ntuples.to_le_bytes());
+        self.hashm_ffactor.encode_into(buf);
+        self.hashm_bsize.encode_into(buf);
+        self.hashm_bmsize.encode_into(buf);
+        self.hashm_bmshift.encode_into(buf);
+        self.hashm_maxbucket.encode_into(buf);
+        self.hashm_highmask.encode_into(buf);
+        self.hashm_lowmask.encode_into(buf);
+        self.hashm_ovflpoint.encode_into(buf);
+        self.hashm_firstfree.encode_into(buf);
+        self.hashm_nmaps.encode_into(buf);
+        self.hashm_procid.encode_into(buf);
+        for i in 0..HASH_MAX_SPLITPOINTS {
+            self.hashm_spares.get(i).copied().unwrap_or(0).encode_into(buf);
+        }
+        for i in 0..HASH_MAX_BITMAPS {
+            self.hashm_mapp.get(i).copied().unwrap_or(0).encode_into(buf);
+        }
+        // trailing padding up to the struct's alignment
+        buf.resize(start + Self::byte_size() as usize, 0);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        let words = |offset: usize, count: usize| {
+            bytes[offset..offset + count * 4].chunks_exact(4).map(read_u32).collect()
+        };
+        Ok(HashMetaPage {
+            hashm_magic: read_u32(&bytes[0..4]),
+            hashm_version: read_u32(&bytes[4..8]),
+            hashm_ntuples: read_f64(&bytes[8..16]),
+            hashm_ffactor: read_u16(&bytes[16..18]),
+            hashm_bsize: read_u16(&bytes[18..20]),
+            hashm_bmsize: read_u16(&bytes[20..22]),
+            hashm_bmshift: read_u16(&bytes[22..24]),
+            hashm_maxbucket: read_u32(&bytes[24..28]),
+            hashm_highmask: read_u32(&bytes[28..32]),
+            hashm_lowmask: read_u32(&bytes[32..36]),
+            hashm_ovflpoint: read_u32(&bytes[36..40]),
+            hashm_firstfree: read_u32(&bytes[40..44]),
+            hashm_nmaps: read_u32(&bytes[44..48]),
+            hashm_procid: read_u32(&bytes[48..52]),
+            hashm_spares: words(HASHM_SPARES_OFFSET, HASH_MAX_SPLITPOINTS),
+            hashm_mapp: words(HASHM_MAPP_OFFSET, HASH_MAX_BITMAPS),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = vec![0; Self::byte_size() as usize];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        4544
+    }
+}
+
+impl PageLazy {
+    /// The hash index opaque data kept in the special space of an index page.
+    /// Fails unless `hasho_page_id` is HASHO_PAGE_ID.
+    pub fn hash_opaque(&self) -> Result<HashPageOpaque, Error> {
+        let opaque = HashPageOpaque::decode(self.special_bytes()?)?;
+        if opaque.hasho_page_id != HASHO_PAGE_ID {
+            return Err(Error::InvalidByteEncoding(format!(
+                "hash page id {:#x}, expected {:#x}",
+                opaque.hasho_page_id, HASHO_PAGE_ID
+            )));
+        }
+        Ok(opaque)
+    }
+
+    /// The hash index metapage contents, read from the start of the page's data area.
+    /// Fails unless `hashm_magic` is HASH_MAGIC.
+    pub fn hash_meta(&self) -> Result<HashMetaPage, Error> {
+        let meta = HashMetaPage::decode(&self.data)?;
+        if meta.hashm_magic != HASH_MAGIC {
+            return Err(Error::InvalidByteEncoding(format!(
+                "hash metapage magic {:#x}, expected {:#x}",
+                meta.hashm_magic, HASH_MAGIC
+            )));
+        }
+        Ok(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_lazy, BTREE_LEAF_PAGE};
+
+    /// Metapage (block 0) of a hash index on `fixture_heap (id)`.
+    const HASH_META_PAGE: &[u8] = include_bytes!("../../test-data/hash_meta_page.bin");
+    /// Primary page of bucket 0 (block 1) of the same index.
+    const HASH_BUCKET_PAGE: &[u8] = include_bytes!("../../test-data/hash_bucket_page.bin");
+
+    #[test]
+    fn test_hash_opaque() {
+        let page = page_lazy(HASH_BUCKET_PAGE);
+        let opaque = page.hash_opaque().unwrap();
+        assert_eq!(
+            opaque,
+            HashPageOpaque {
+                hasho_prevblkno: 1,
+                hasho_nextblkno: u32::MAX,
+                hasho_bucket: 0,
+                hasho_flag: LH_BUCKET_PAGE,
+                hasho_page_id: HASHO_PAGE_ID,
+            }
+        );
+        assert_eq!(opaque.page_type(), LH_BUCKET_PAGE);
+        assert_eq!(page_lazy(HASH_META_PAGE).hash_opaque().unwrap().page_type(), LH_META_PAGE);
+
+        assert!(matches!(page_lazy(BTREE_LEAF_PAGE).hash_opaque(), Err(Error::InvalidByteEncoding(_))));
+    }
+
+    #[test]
+    fn test_hash_opaque_round_trip() {
+        let page = page_lazy(HASH_BUCKET_PAGE);
+        let special = page.special_bytes().unwrap();
+        assert_eq!(HashPageOpaque::decode(special).unwrap().encode(), special);
+    }
+
+    #[test]
+    fn test_hash_meta() {
+        let page = page_lazy(HASH_META_PAGE);
+        let meta = page.hash_meta().unwrap();
+        assert_eq!(meta.hashm_version, 4);
+        assert_eq!(meta.hashm_ntuples, 40.0);
+        assert_eq!(meta.hashm_ffactor, 307);
+        assert_eq!(meta.hashm_bsize, 8152);
+        assert_eq!(meta.hashm_bmsize, 4096);
+        assert_eq!(meta.hashm_bmshift, 15);
+        assert_eq!((meta.hashm_maxbucket, meta.hashm_highmask, meta.hashm_lowmask), (1, 3, 1));
+        assert_eq!((meta.hashm_ovflpoint, meta.hashm_firstfree, meta.hashm_nmaps), (1, 0, 1));
+        // hashint4
+        assert_eq!(meta.hashm_procid, 450);
+        assert_eq!(&meta.hashm_spares[..3], &[0, 1, 0]);
+        assert_eq!(meta.hashm_spares.len(), HASH_MAX_SPLITPOINTS);
+        assert_eq!(&meta.hashm_mapp[..2], &[3, 0]);
+        assert_eq!(meta.hashm_mapp.len(), HASH_MAX_BITMAPS);
+
+        assert!(matches!(page_lazy(HASH_BUCKET_PAGE).hash_meta(), Err(Error::InvalidByteEncoding(_))));
+    }
+
+    #[test]
+    fn test_hash_meta_round_trip() {
+        let page = page_lazy(HASH_META_PAGE);
+        let bytes = &page.data[..HashMetaPage::byte_size() as usize];
+        assert_eq!(HashMetaPage::decode(bytes).unwrap().encode(), bytes);
+    }
+}
@@ -1,7 +1,35 @@
-use crate::util::{ByteEncodeResult, ByteEncoded, GetByteSliceExt, ByteEncodeError};
+use crate::util::{read_u16, read_u32, MAXIMUM_ALIGNOF, ByteEncodeResult, ByteEncoded, GetByteSliceExt, ByteEncodeError};
 
-use super::item_pointer_data::ItemPointerData;
+use super::{item_pointer_data::ItemPointerData, snapshot::Snapshot};
 
+///
+/// MinimalTuple is an alternative representation that is used for transient
+/// tuples inside the executor, in places where transaction status information
+/// is not required, the tuple rowtype is known, and shaving off a few bytes
+/// is worthwhile because we need to store many tuples.  The representation
+/// is chosen so that tuple access routines can work with either full or
+/// minimal tuples via a HeapTupleData pointer structure.  The access routines
+/// see no difference, except that they must not access the transaction status
+/// or t_ctid fields because those aren't there.
+///
+/// For the most part, MinimalTuples should be accessed via TupleTableSlot
+/// routines.  These routines will prevent access to the "system columns"
+/// and thereby prevent accidental use of the nonexistent fields.
+///
+/// MinimalTupleData contains a length word, some padding, and fields matching
+/// HeapTupleHeaderData beginning with t_infomask2. The padding is chosen so
+/// that offsetof(t_infomask2) is the same modulo MAXIMUM_ALIGNOF in both
+/// structs.   This makes data alignment rules equivalent in both cases.
+///
+/// When a minimal tuple is accessed via a HeapTupleData pointer, t_data is
+/// set to point MINIMAL_TUPLE_OFFSET bytes before the actual start of the
+/// minimal tuple --- that is, where a full tuple matching the minimal tuple's
+/// data would start.  This trick is what makes the structs seem equivalent.
+///
+/// Note that t_hoff is computed the same as in a full tuple, hence it includes
+/// the MINIMAL_TUPLE_OFFSET distance.  t_len does not include that, however.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct MinimalTupleData {
     /// actual length of minimal tuple
     pub t_len: u32,
@@ -17,9 +45,91 @@ pub struct MinimalTupleData {
 
     /// ^ - 23 bytes - ^
     pub t_hoff: u8,
-    /// bitmap of NULLs
+    /// bitmap of NULLs, followed by any alignment padding before the data
     pub t_bits: Vec<u8>,
-    // MORE DATA FOLLOWS AT END OF STRUCT
+    /// user data, from t_hoff - MINIMAL_TUPLE_OFFSET up to t_len
+    pub data: Vec<u8>,
+}
+
+/// Distance between where a full tuple header would start and the start of a minimal tuple.
+pub const MINIMAL_TUPLE_OFFSET: usize = ((18 - 4) / MAXIMUM_ALIGNOF) * MAXIMUM_ALIGNOF;
+/// Padding after t_len that lines t_infomask2 up with the full tuple header modulo MAXALIGN.
+pub const MINIMAL_TUPLE_PADDING: usize = (18 - 4) % MAXIMUM_ALIGNOF;
+/// Offset of t_infomask2 within a minimal tuple.
+pub const MINIMAL_TUPLE_DATA_OFFSET: usize = 4 + MINIMAL_TUPLE_PADDING;
+
+impl ByteEncoded for MinimalTupleData {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.t_len as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.t_len.encode_into(buf);
+        let mut padding = self.mt_padding.clone();
+        padding.resize(MINIMAL_TUPLE_PADDING, 0);
+        buf.extend(padding);
+        self.t_infomask2.encode_into(buf);
+        self.t_infomask.encode_into(buf);
+        self.t_hoff.encode_into(buf);
+        buf.extend_from_slice(&self.t_bits);
+        buf.extend_from_slice(&self.data);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let t_len = u32::decode(bytes.get_byte_slice(0, 4)?)?;
+        if bytes.len() != t_len as usize {
+            return Err(ByteEncodeError::InvalidSize {
+                expected: t_len as usize,
+                actual: bytes.len(),
+            });
+        }
+        let mt_padding = bytes.get_byte_slice(4, MINIMAL_TUPLE_DATA_OFFSET)?.to_vec();
+        let t_infomask2 = u16::decode(bytes.get_byte_slice(MINIMAL_TUPLE_DATA_OFFSET, MINIMAL_TUPLE_DATA_OFFSET + 2)?)?;
+        let t_infomask = u16::decode(bytes.get_byte_slice(MINIMAL_TUPLE_DATA_OFFSET + 2, MINIMAL_TUPLE_DATA_OFFSET + 4)?)?;
+        let t_hoff = u8::decode(bytes.get_byte_slice(MINIMAL_TUPLE_DATA_OFFSET + 4, MINIMAL_TUPLE_DATA_OFFSET + 5)?)?;
+        // t_hoff counts from where a full tuple header would start
+        let data_start = (t_hoff as usize)
+            .checked_sub(MINIMAL_TUPLE_OFFSET)
+            .filter(|start| *start >= MINIMAL_TUPLE_DATA_OFFSET + 5)
+            .ok_or(ByteEncodeError::InvalidSize {
+                expected: MINIMAL_TUPLE_OFFSET + MINIMAL_TUPLE_DATA_OFFSET + 5,
+                actual: t_hoff as usize,
+            })?;
+        let t_bits = bytes.get_byte_slice(MINIMAL_TUPLE_DATA_OFFSET + 5, data_start)?.to_vec();
+        let data = bytes.get_byte_slice(data_start, bytes.len())?.to_vec();
+        Ok(MinimalTupleData {
+            t_len,
+            mt_padding,
+            t_infomask2,
+            t_infomask,
+            t_hoff,
+            t_bits,
+            data,
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        use std::io::Read;
+
+        let t_len = u32::decode_from_reader(reader)?;
+        // t_len comes from the input, so the buffer grows as bytes arrive instead of being
+        // sized by it up front
+        let mut bytes = t_len.encode();
+        reader.take((t_len as u64).saturating_sub(4)).read_to_end(&mut bytes)?;
+        if bytes.len() < t_len as usize {
+            return Err(ByteEncodeError::NotEnoughBytes {
+                expected: t_len as usize,
+                actual: bytes.len(),
+            });
+        }
+        Self::decode(&bytes)
+    }
 }
 
 ///
@@ -94,6 +204,7 @@ pub struct MinimalTupleData {
 /// MAXALIGN.
 ///
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HeapTupleHeaderData {
     /// insert XID stamp
     pub t_xmin: u32,
@@ -132,6 +243,20 @@ pub struct DatumTupleFields {
     pub datum_typeid: u32,
 }
 
+/// Whether tuples may carry the system OID column, which PostgreSQL removed in version 12.
+///
+/// When present, the OID is stored just before the user data, inside the space accounted for by
+/// `t_hoff`, and is flagged with HEAP_HASOID_OLD.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum HeapLayout {
+    /// Tables created before PostgreSQL 12 `WITH OIDS`
+    WithOid,
+    /// PostgreSQL 12 and later
+    #[default]
+    WithoutOid,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum TField3 {
     /// current TID of this or newer row version
     /// inserting or deleting command ID, or both
@@ -143,17 +268,22 @@ pub enum TField3 {
 
 impl ByteEncoded for HeapTupleHeaderData {
     fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.t_xmin.encode());
-        buf.extend(self.t_xmax.encode());
-        buf.extend(self.t_field3.encode());
-        buf.extend(self.t_infomask2.encode());
-        buf.extend(self.t_infomask.encode());
-        buf.extend(self.t_hoff.encode());
-        buf.extend(self.data.encode());
+        let mut buf = Vec::with_capacity(23 + self.data.len());
+        self.encode_into(&mut buf);
         buf
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.t_xmin.encode_into(buf);
+        self.t_xmax.encode_into(buf);
+        self.t_field3.encode_into(buf);
+        self.t_ctid.encode_into(buf);
+        self.t_infomask2.encode_into(buf);
+        self.t_infomask.encode_into(buf);
+        self.t_hoff.encode_into(buf);
+        self.data.encode_into(buf);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let t_xmin = u32::decode(bytes.get_byte_slice(0, 4)?)?;
         let t_xmax = u32::decode(bytes.get_byte_slice(4, 8)?)?;
@@ -179,9 +309,11 @@ impl ByteEncoded for HeapTupleHeaderData {
         self.t_xmin.encode_into_writer(writer)?;
         self.t_xmax.encode_into_writer(writer)?;
         self.t_field3.encode_into_writer(writer)?;
+        self.t_ctid.encode_into_writer(writer)?;
         self.t_infomask2.encode_into_writer(writer)?;
         self.t_infomask.encode_into_writer(writer)?;
         self.t_hoff.encode_into_writer(writer)?;
+        writer.write_all(&self.data)?;
         Ok(())
     }
 
@@ -208,69 +340,670 @@ impl ByteEncoded for HeapTupleHeaderData {
     }
 }
 
+impl std::fmt::Display for HeapTupleHeaderData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HeapTuple(xmin={},xmax={},field3={},ctid=({},{}),natts={},infomask={:#06x},infomask2={:#06x},hoff={},data_len={})",
+            self.t_xmin,
+            self.t_xmax,
+            self.t_field3,
+            self.t_ctid.block_number(),
+            self.t_ctid.ip_posid,
+            self.natts(),
+            self.t_infomask,
+            self.t_infomask2,
+            self.t_hoff,
+            self.data.len()
+        )
+    }
+}
+
 impl HeapTupleHeaderData {
-    pub fn visible_to_tx(&self, xid: u32) -> bool {
-        todo!()
+    /// MVCC visibility of the tuple for a snapshot, following HeapTupleSatisfiesMVCC.
+    ///
+    /// The tuple is visible when its inserting transaction committed before the snapshot
+    /// and its deleting transaction, if any, did not. Commit status is taken from the hint
+    /// bits in `t_infomask` since the commit log is not available:
+    ///
+    /// - a frozen xmin (HEAP_XMIN_FROZEN, both HEAP_XMIN_COMMITTED and HEAP_XMIN_INVALID set)
+    ///   is committed and older than every snapshot, so it is visible without comparing xids
+    /// - a committed xmin is visible unless the snapshot still sees it as in progress
+    /// - an xmin that is invalid, or not hinted as committed, is treated as not visible
+    /// - an xmax that is invalid, zero, only a locker or a MultiXactId does not hide the tuple
+    /// - a committed xmax hides the tuple unless the snapshot sees it as in progress
+    /// - an xmax not hinted as committed is treated as still running, so the tuple stays visible
+    pub fn visible_to_tx(&self, snapshot: &Snapshot) -> bool {
+        if self.t_infomask & HEAP_XMIN_FROZEN != HEAP_XMIN_FROZEN {
+            if self.t_infomask & HEAP_XMIN_COMMITTED == 0 {
+                return false;
+            }
+            if snapshot.is_in_progress(self.t_xmin) {
+                return false;
+            }
+        }
+
+        if self.t_infomask & HEAP_XMAX_INVALID != 0
+            || self.t_xmax == INVALID_TRANSACTION_ID
+            || self.t_infomask & (HEAP_XMAX_LOCK_ONLY | HEAP_XMAX_IS_MULTI) != 0
+            || self.t_infomask & HEAP_XMAX_COMMITTED == 0
+        {
+            return true;
+        }
+        snapshot.is_in_progress(self.t_xmax)
+    }
+
+    /// Per-attribute null flags (true for null) read from `t_bits`, or `None` when
+    /// HEAP_HASNULL is not set and no attribute is null. Also `None` if the tuple is too
+    /// short to hold the bitmap.
+    pub fn null_bitmap(&self) -> Option<Vec<bool>> {
+        if self.t_infomask & HEAP_HASNULL == 0 {
+            return None;
+        }
+        let natts = self.natts() as usize;
+        let bits = self.data.get(..natts.div_ceil(8))?;
+        Some((0..natts).map(|i| bits[i / 8] & (1 << (i % 8)) == 0).collect())
+    }
+
+    /// The user data of the tuple, starting at `t_hoff`.
+    pub fn user_data(&self) -> ByteEncodeResult<&[u8]> {
+        let start = (self.t_hoff as usize).checked_sub(SIZEOF_HEAP_TUPLE_HEADER).ok_or(
+            ByteEncodeError::NotEnoughBytes {
+                expected: SIZEOF_HEAP_TUPLE_HEADER,
+                actual: self.t_hoff as usize,
+            },
+        )?;
+        self.data.get_byte_slice(start, self.data.len())
+    }
+
+    /// The tuple's data from `explicit_hoff` onward, ignoring the stored `t_hoff`.
+    ///
+    /// This is an escape hatch for recovering data from tuples whose `t_hoff` is corrupt
+    /// (below the fixed header size or beyond the tuple); prefer `user_data` otherwise.
+    /// Offsets inside the fixed header are treated as its end and offsets past the tuple
+    /// give an empty slice, so the result is best effort and never fails.
+    pub fn payload_from(&self, explicit_hoff: u8) -> &[u8] {
+        let start = (explicit_hoff as usize).saturating_sub(SIZEOF_HEAP_TUPLE_HEADER);
+        &self.data[start.min(self.data.len())..]
+    }
+
+    /// Interprets the overlaid `t_field3`: the xid of an old-style VACUUM FULL that moved
+    /// the tuple when HEAP_MOVED bits are set, the command id otherwise.
+    pub fn field3(&self) -> TField3 {
+        if self.t_infomask & HEAP_MOVED != 0 {
+            TField3::Xvac(self.t_field3)
+        } else {
+            TField3::CommandId(self.t_field3)
+        }
+    }
+
+    /// Whether the command id in `t_field3` is a combo command id, standing for a cmin and
+    /// cmax pair only the originating backend can resolve.
+    pub fn is_combo_cid(&self) -> bool {
+        self.t_infomask & HEAP_COMBOCID != 0
+    }
+
+    /// The system OID of the tuple, if the layout allows one and HEAP_HASOID_OLD is set.
+    pub fn oid(&self, layout: HeapLayout) -> ByteEncodeResult<Option<u32>> {
+        if layout == HeapLayout::WithoutOid || self.t_infomask & HEAP_HASOID_OLD == 0 {
+            return Ok(None);
+        }
+        let end = (self.t_hoff as usize).saturating_sub(SIZEOF_HEAP_TUPLE_HEADER);
+        let start = end.checked_sub(4).ok_or(ByteEncodeError::NotEnoughBytes {
+            expected: SIZEOF_HEAP_TUPLE_HEADER + 4,
+            actual: self.t_hoff as usize,
+        })?;
+        Ok(Some(u32::decode(self.data.get_byte_slice(start, end)?)?))
+    }
+
+    /// Whether the tuple is dead to every transaction that started after `oldest_xid`,
+    /// so its storage can be reclaimed.
+    ///
+    /// Without access to the commit log only the hint bits are trusted: an aborted
+    /// insert, or a delete whose commit is hinted and that precedes `oldest_xid`.
+    /// Tuples whose fate is not hinted are reported as not dead.
+    pub fn is_dead(&self, oldest_xid: u32) -> bool {
+        if self.t_infomask & HEAP_XMIN_FROZEN == HEAP_XMIN_INVALID {
+            return true;
+        }
+        if self.t_infomask & HEAP_XMIN_COMMITTED == 0
+            || self.t_infomask & HEAP_XMAX_INVALID != 0
+            || self.t_xmax == INVALID_TRANSACTION_ID
+            || self.t_infomask & (HEAP_XMAX_LOCK_ONLY | HEAP_XMAX_IS_MULTI) != 0
+        {
+            return false;
+        }
+        self.t_infomask & HEAP_XMAX_COMMITTED != 0
+            && transaction_id_precedes(self.t_xmax, oldest_xid)
+    }
+
+    /// Whether the tuple is fully frozen, as every tuple on an all-frozen page must be: xmin
+    /// is frozen (HEAP_XMIN_FROZEN, or FrozenTransactionId from before 9.4) and there is no
+    /// xmax that a later freeze would still have to deal with.
+    pub fn is_frozen(&self) -> bool {
+        let xmin_frozen = self.t_infomask & HEAP_XMIN_FROZEN == HEAP_XMIN_FROZEN
+            || self.t_xmin == FROZEN_TRANSACTION_ID;
+        let no_xmax = self.t_infomask & HEAP_XMAX_INVALID != 0 || self.t_xmax == INVALID_TRANSACTION_ID;
+        xmin_frozen && no_xmax
+    }
+
+    /// Number of attributes stored in the tuple, from the low bits of `t_infomask2`.
+    pub fn natts(&self) -> u16 {
+        self.t_infomask2 & HEAP_NATTS_MASK
+    }
+
+    /// Whether the tuple was updated with a HOT update, so its newer version is a
+    /// heap-only tuple on the same page.
+    pub fn is_hot_updated(&self) -> bool {
+        self.t_infomask2 & HEAP_HOT_UPDATED != 0
+    }
+
+    /// Whether the tuple is a heap-only tuple, created by a HOT update and not referenced
+    /// from any index.
+    pub fn is_heap_only(&self) -> bool {
+        self.t_infomask2 & HEAP_ONLY_TUPLE != 0
+    }
+
+    /// Whether this tuple was updated or deleted in a way that modified its
+    /// key columns, which conflicts with FOR KEY SHARE lockers.
+    pub fn keys_updated(&self) -> bool {
+        self.t_infomask2 & HEAP_KEYS_UPDATED != 0
+    }
+
+    /// Whether this is the latest version of its row: `t_ctid` points to the tuple itself
+    /// (`own_tid`), or there is no valid xmax that could have replaced it.
+    pub fn is_latest_version(&self, own_tid: &ItemPointerData) -> bool {
+        self.t_ctid == *own_tid
+            || self.t_xmax == INVALID_TRANSACTION_ID
+            || self.t_infomask & HEAP_XMAX_INVALID != 0
+    }
+
+    /// Encodes the tuple zero-padded to exactly `lp_len` bytes, the storage its line
+    /// pointer covers. Fails if the tuple doesn't fit.
+    pub fn encode_padded(&self, lp_len: u16) -> ByteEncodeResult<Vec<u8>> {
+        let mut bytes = self.encode();
+        if bytes.len() > lp_len as usize {
+            return Err(ByteEncodeError::TooManyBytes {
+                expected: lp_len as usize,
+                actual: bytes.len(),
+            });
+        }
+        bytes.resize(lp_len as usize, 0);
+        Ok(bytes)
+    }
+}
+
+/// A heap tuple header whose payload borrows from the page instead of being copied, for
+/// scans that only look at a tuple briefly.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct HeapTupleHeaderRef<'a> {
+    pub t_xmin: u32,
+    pub t_xmax: u32,
+    pub t_field3: u32,
+    pub t_ctid: ItemPointerData,
+    pub t_infomask2: u16,
+    pub t_infomask: u16,
+    pub t_hoff: u8,
+    /// everything after the fixed header, as in [`HeapTupleHeaderData::data`]
+    pub data: &'a [u8],
+}
+
+impl<'a> HeapTupleHeaderRef<'a> {
+    pub fn decode(bytes: &'a [u8]) -> ByteEncodeResult<Self> {
+        let data = bytes.get(SIZEOF_HEAP_TUPLE_HEADER..).ok_or(ByteEncodeError::NotEnoughBytes {
+            expected: SIZEOF_HEAP_TUPLE_HEADER,
+            actual: bytes.len(),
+        })?;
+        Ok(HeapTupleHeaderRef {
+            t_xmin: read_u32(&bytes[0..4]),
+            t_xmax: read_u32(&bytes[4..8]),
+            t_field3: read_u32(&bytes[8..12]),
+            t_ctid: ItemPointerData::decode(&bytes[12..18])?,
+            t_infomask2: read_u16(&bytes[18..20]),
+            t_infomask: read_u16(&bytes[20..22]),
+            t_hoff: bytes[22],
+            data,
+        })
+    }
+
+    /// Copies the tuple out of the page.
+    pub fn to_header_data(&self) -> HeapTupleHeaderData {
+        HeapTupleHeaderData {
+            t_xmin: self.t_xmin,
+            t_xmax: self.t_xmax,
+            t_field3: self.t_field3,
+            t_ctid: self.t_ctid,
+            t_infomask2: self.t_infomask2,
+            t_infomask: self.t_infomask,
+            t_hoff: self.t_hoff,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+pub const INVALID_TRANSACTION_ID: u32 = 0;
+pub const FROZEN_TRANSACTION_ID: u32 = 2;
+pub const FIRST_NORMAL_TRANSACTION_ID: u32 = 3;
+
+/// Whether xid `a` is older than `b`, accounting for wraparound of normal XIDs.
+pub fn transaction_id_precedes(a: u32, b: u32) -> bool {
+    if a < FIRST_NORMAL_TRANSACTION_ID || b < FIRST_NORMAL_TRANSACTION_ID {
+        return a < b;
     }
+    (a.wrapping_sub(b) as i32) < 0
 }
 
+/// Size of the fixed part of the heap tuple header, up to where `t_bits` begins.
+pub const SIZEOF_HEAP_TUPLE_HEADER: usize = 23;
+
 ///
 /// information stored in t_infomask:
 /// has null attribute(s)
-const HEAP_HASNULL: u16 = 0x0001;
+pub const HEAP_HASNULL: u16 = 0x0001;
 
 /// has variable-width attribute(s)
-const HEAP_HASVARWIDTH: u16 = 0x0002;
+pub const HEAP_HASVARWIDTH: u16 = 0x0002;
 
 /// has external stored attribute(s)
-const HEAP_HASEXTERNAL: u16 = 0x0004;
+pub const HEAP_HASEXTERNAL: u16 = 0x0004;
 
 /// has an object-id field  
-const HEAP_HASOID_OLD: u16 = 0x0008;
+pub const HEAP_HASOID_OLD: u16 = 0x0008;
 
 /// xmax is a key-shared locker
-const HEAP_XMAX_KEYSHR_LOCK: u16 = 0x0010;
+pub const HEAP_XMAX_KEYSHR_LOCK: u16 = 0x0010;
 
 /// t_cid is a combo CID
-const HEAP_COMBOCID: u16 = 0x0020;
+pub const HEAP_COMBOCID: u16 = 0x0020;
 
 /// xmax is exclusive locker
-const HEAP_XMAX_EXCL_LOCK: u16 = 0x0040; // xmax, if valid, is only a locker
-const HEAP_XMAX_LOCK_ONLY: u16 = 0x0080;
+pub const HEAP_XMAX_EXCL_LOCK: u16 = 0x0040; // xmax, if valid, is only a locker
+pub const HEAP_XMAX_LOCK_ONLY: u16 = 0x0080;
 
 /// xmax is a shared locker
-const HEAP_XMAX_SHR_LOCK: u16 = (HEAP_XMAX_EXCL_LOCK | HEAP_XMAX_KEYSHR_LOCK);
+pub const HEAP_XMAX_SHR_LOCK: u16 = HEAP_XMAX_EXCL_LOCK | HEAP_XMAX_KEYSHR_LOCK;
 
-const HEAP_LOCK_MASK: u16 = (HEAP_XMAX_SHR_LOCK | HEAP_XMAX_EXCL_LOCK | HEAP_XMAX_KEYSHR_LOCK);
+pub const HEAP_LOCK_MASK: u16 = HEAP_XMAX_SHR_LOCK | HEAP_XMAX_EXCL_LOCK | HEAP_XMAX_KEYSHR_LOCK;
 
 /// t_xmin committed
-const HEAP_XMIN_COMMITTED: u16 = 0x0100;
+pub const HEAP_XMIN_COMMITTED: u16 = 0x0100;
 
 /// t_xmin invalid/aborted
-const HEAP_XMIN_INVALID: u16 = 0x0200;
-const HEAP_XMIN_FROZEN: u16 = (HEAP_XMIN_COMMITTED | HEAP_XMIN_INVALID);
+pub const HEAP_XMIN_INVALID: u16 = 0x0200;
+pub const HEAP_XMIN_FROZEN: u16 = HEAP_XMIN_COMMITTED | HEAP_XMIN_INVALID;
 
 /// t_xmax committed
-const HEAP_XMAX_COMMITTED: u16 = 0x0400;
+pub const HEAP_XMAX_COMMITTED: u16 = 0x0400;
 
 /// t_xmax invalid/aborted
-const HEAP_XMAX_INVALID: u16 = 0x0800;
+pub const HEAP_XMAX_INVALID: u16 = 0x0800;
 
 /// t_xmax is a MultiXactId
-const HEAP_XMAX_IS_MULTI: u16 = 0x1000;
+pub const HEAP_XMAX_IS_MULTI: u16 = 0x1000;
+
+/// commit status hints, set lazily by readers once the xmin/xmax outcome is known
+pub const HEAP_HINT_BITS: u16 = HEAP_XMIN_COMMITTED | HEAP_XMIN_INVALID | HEAP_XMAX_COMMITTED | HEAP_XMAX_INVALID;
 
 /// this is UPDATEd version of row
-const HEAP_UPDATED: u16 = 0x2000;
+pub const HEAP_UPDATED: u16 = 0x2000;
 
 /// moved to another place by pre-9.0
 /// VACUUM FULL; kept for binary
 /// upgrade support
-const HEAP_MOVED_OFF: u16 = 0x4000;
+pub const HEAP_MOVED_OFF: u16 = 0x4000;
 
 /// moved from another place by pre-9.0
 /// VACUUM FULL; kept for binary
 /// upgrade support  
-const HEAP_MOVED_IN: u16 = 0x8000;
-const HEAP_MOVED: u16 = (HEAP_MOVED_OFF | HEAP_MOVED_IN);
+pub const HEAP_MOVED_IN: u16 = 0x8000;
+pub const HEAP_MOVED: u16 = HEAP_MOVED_OFF | HEAP_MOVED_IN;
 /// visibility-related bits
-const HEAP_XACT_MASK: u16 = 0xFFF0;
+pub const HEAP_XACT_MASK: u16 = 0xFFF0;
+
+///
+/// information stored in t_infomask2:
+/// 11 bits for number of attributes
+pub const HEAP_NATTS_MASK: u16 = 0x07FF;
+
+/// tuple was updated and key cols modified, or tuple deleted
+pub const HEAP_KEYS_UPDATED: u16 = 0x2000;
+
+/// tuple was HOT-updated
+pub const HEAP_HOT_UPDATED: u16 = 0x4000;
+
+/// this is heap-only tuple
+pub const HEAP_ONLY_TUPLE: u16 = 0x8000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dto::BlockIdData, test_util::tuple};
+
+    fn tuple_with_infomask2(t_infomask2: u16) -> HeapTupleHeaderData {
+        HeapTupleHeaderData {
+            t_xmin: 1,
+            t_xmax: 2,
+            t_field3: 0,
+            t_ctid: ItemPointerData {
+                ip_blkid: BlockIdData { bi_hi: 0, bi_lo: 0 },
+                ip_posid: 1,
+            },
+            t_infomask2,
+            t_infomask: 0,
+            t_hoff: 24,
+            data: vec![0],
+        }
+    }
+
+    #[test]
+    fn test_oid_with_oid_layout() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_infomask = HEAP_HASOID_OLD;
+        tuple.t_hoff = 32;
+        // padding up to 28, then the OID, then one int4 column
+        tuple.data = vec![0, 0, 0, 0, 0, 0x39, 0x30, 0, 0, 7, 0, 0, 0];
+
+        assert_eq!(tuple.oid(HeapLayout::WithOid).unwrap(), Some(12345));
+        assert_eq!(tuple.user_data().unwrap(), &[7, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_oid_without_oid_layout() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_hoff = 24;
+        tuple.data = vec![0, 7, 0, 0, 0];
+
+        assert_eq!(tuple.oid(HeapLayout::WithoutOid).unwrap(), None);
+        assert_eq!(tuple.oid(HeapLayout::WithOid).unwrap(), None);
+        assert_eq!(tuple.user_data().unwrap(), &[7, 0, 0, 0]);
+
+        // a stray HEAP_HASOID_OLD bit is ignored for modern tables
+        tuple.t_infomask = HEAP_HASOID_OLD;
+        assert_eq!(tuple.oid(HeapLayout::WithoutOid).unwrap(), None);
+    }
+
+    #[test]
+    fn test_transaction_id_precedes() {
+        assert!(transaction_id_precedes(5, 10));
+        assert!(!transaction_id_precedes(10, 5));
+        assert!(transaction_id_precedes(u32::MAX - 5, 10));
+        assert!(transaction_id_precedes(2, 10));
+    }
+
+    #[test]
+    fn test_is_dead() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_xmax = 10;
+        tuple.t_infomask = HEAP_XMIN_COMMITTED | HEAP_XMAX_COMMITTED;
+        assert!(tuple.is_dead(11));
+        assert!(!tuple.is_dead(10));
+
+        tuple.t_infomask = HEAP_XMIN_COMMITTED;
+        assert!(!tuple.is_dead(11));
+
+        tuple.t_infomask = HEAP_XMIN_COMMITTED | HEAP_XMAX_INVALID;
+        assert!(!tuple.is_dead(11));
+
+        tuple.t_infomask = HEAP_XMIN_INVALID;
+        assert!(tuple.is_dead(0));
+    }
+
+    #[test]
+    fn test_visible_to_tx_insert() {
+        let snapshot = Snapshot::new(100, 105, vec![102]);
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_xmax = 0;
+        tuple.t_infomask = HEAP_XMIN_COMMITTED | HEAP_XMAX_INVALID;
+
+        tuple.t_xmin = 50;
+        assert!(tuple.visible_to_tx(&snapshot));
+        tuple.t_xmin = 101;
+        assert!(tuple.visible_to_tx(&snapshot));
+        // in progress, or started after the snapshot
+        tuple.t_xmin = 102;
+        assert!(!tuple.visible_to_tx(&snapshot));
+        tuple.t_xmin = 105;
+        assert!(!tuple.visible_to_tx(&snapshot));
+
+        // aborted or unhinted inserts
+        tuple.t_xmin = 50;
+        tuple.t_infomask = HEAP_XMIN_INVALID | HEAP_XMAX_INVALID;
+        assert!(!tuple.visible_to_tx(&snapshot));
+        tuple.t_infomask = HEAP_XMAX_INVALID;
+        assert!(!tuple.visible_to_tx(&snapshot));
+
+        // frozen tuples are visible whatever their xmin says
+        tuple.t_xmin = 200;
+        tuple.t_infomask = HEAP_XMIN_FROZEN | HEAP_XMAX_INVALID;
+        assert!(tuple.visible_to_tx(&snapshot));
+    }
+
+    #[test]
+    fn test_visible_to_tx_delete() {
+        let snapshot = Snapshot::new(100, 105, vec![102]);
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_xmin = 50;
+        tuple.t_infomask = HEAP_XMIN_COMMITTED | HEAP_XMAX_COMMITTED;
+
+        tuple.t_xmax = 60;
+        assert!(!tuple.visible_to_tx(&snapshot));
+        tuple.t_xmax = 102;
+        assert!(tuple.visible_to_tx(&snapshot));
+        tuple.t_xmax = 110;
+        assert!(tuple.visible_to_tx(&snapshot));
+
+        // lockers and unhinted deleters don't hide the tuple
+        tuple.t_xmax = 60;
+        tuple.t_infomask = HEAP_XMIN_COMMITTED | HEAP_XMAX_COMMITTED | HEAP_XMAX_LOCK_ONLY;
+        assert!(tuple.visible_to_tx(&snapshot));
+        tuple.t_infomask = HEAP_XMIN_COMMITTED;
+        assert!(tuple.visible_to_tx(&snapshot));
+    }
+
+    #[test]
+    fn test_null_bitmap_alternating() {
+        let mut tuple = tuple_with_infomask2(10);
+        tuple.t_infomask = HEAP_HASNULL;
+        tuple.t_hoff = 32;
+        // a set bit means not null: attributes 0, 2, 4, 6 and 8 have values
+        tuple.data = vec![0b0101_0101, 0b01, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        assert_eq!(
+            tuple.null_bitmap(),
+            Some(vec![false, true, false, true, false, true, false, true, false, true])
+        );
+    }
+
+    #[test]
+    fn test_null_bitmap_without_nulls() {
+        let mut tuple = tuple_with_infomask2(10);
+        tuple.data = vec![0xFF, 0xFF];
+        assert_eq!(tuple.null_bitmap(), None);
+
+        tuple.t_infomask = HEAP_HASNULL;
+        tuple.data = vec![0xFF];
+        assert_eq!(tuple.null_bitmap(), None);
+    }
+
+    #[test]
+    fn test_natts_and_flags() {
+        let tuple = tuple_with_infomask2(HEAP_HOT_UPDATED | HEAP_KEYS_UPDATED | 1600);
+        assert_eq!(tuple.natts(), 1600);
+        assert!(tuple.is_hot_updated());
+        assert!(!tuple.is_heap_only());
+        assert!(tuple.keys_updated());
+
+        let tuple = tuple_with_infomask2(HEAP_ONLY_TUPLE | 3);
+        assert_eq!(tuple.natts(), 3);
+        assert!(!tuple.is_hot_updated());
+        assert!(tuple.is_heap_only());
+        assert!(!tuple.keys_updated());
+
+        let tuple = tuple_with_infomask2(HEAP_NATTS_MASK | HEAP_HOT_UPDATED | HEAP_ONLY_TUPLE);
+        assert_eq!(tuple.natts(), 2047);
+        assert!(tuple.is_hot_updated());
+        assert!(tuple.is_heap_only());
+    }
+
+    #[test]
+    fn test_keys_updated() {
+        assert!(tuple_with_infomask2(HEAP_KEYS_UPDATED | 3).keys_updated());
+        assert!(!tuple_with_infomask2(3).keys_updated());
+        assert!(!tuple_with_infomask2(HEAP_HOT_UPDATED | 3).keys_updated());
+    }
+
+    #[test]
+    fn test_is_latest_version_self_pointing() {
+        let tuple = tuple_with_infomask2(1);
+        let own_tid = tuple.t_ctid;
+        assert!(tuple.is_latest_version(&own_tid));
+    }
+
+    #[test]
+    fn test_is_latest_version_updated() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_ctid.ip_posid = 2;
+        let own_tid = ItemPointerData {
+            ip_blkid: BlockIdData { bi_hi: 0, bi_lo: 0 },
+            ip_posid: 1,
+        };
+        assert!(!tuple.is_latest_version(&own_tid));
+
+        tuple.t_infomask |= HEAP_XMAX_INVALID;
+        assert!(tuple.is_latest_version(&own_tid));
+        tuple.t_infomask = 0;
+        tuple.t_xmax = INVALID_TRANSACTION_ID;
+        assert!(tuple.is_latest_version(&own_tid));
+    }
+
+    fn minimal_tuple() -> MinimalTupleData {
+        // one nullable int4 column present and one null column: bitmap 0b01, then padding up
+        // to t_hoff 24, which is 16 bytes into the minimal tuple
+        MinimalTupleData {
+            t_len: 20,
+            mt_padding: vec![0; MINIMAL_TUPLE_PADDING],
+            t_infomask2: 2,
+            t_infomask: HEAP_HASNULL,
+            t_hoff: 24,
+            t_bits: vec![0b01],
+            data: vec![42, 0, 0, 0],
+        }
+    }
+
+    #[test]
+    fn test_minimal_tuple_offsets() {
+        assert_eq!(MINIMAL_TUPLE_OFFSET, 8);
+        assert_eq!(MINIMAL_TUPLE_PADDING, 6);
+        assert_eq!(MINIMAL_TUPLE_DATA_OFFSET, 10);
+    }
+
+    #[test]
+    fn test_minimal_tuple_round_trip() {
+        let tuple = minimal_tuple();
+        let bytes = tuple.encode();
+        assert_eq!(bytes.len(), tuple.t_len as usize);
+        assert_eq!(bytes[10..15], [2, 0, HEAP_HASNULL as u8, (HEAP_HASNULL >> 8) as u8, 24]);
+        assert_eq!(MinimalTupleData::decode(&bytes).unwrap(), tuple);
+
+        let mut written = vec![];
+        tuple.encode_into_writer(&mut written).unwrap();
+        written.extend_from_slice(&[0xFF; 3]);
+        let mut reader = written.as_slice();
+        assert_eq!(MinimalTupleData::decode_from_reader(&mut reader).unwrap(), tuple);
+        assert_eq!(reader, &[0xFF; 3]);
+    }
+
+    #[test]
+    fn test_minimal_tuple_truncated_reader() {
+        let mut bytes = u32::MAX.encode();
+        bytes.extend_from_slice(&minimal_tuple().encode()[4..]);
+        assert!(matches!(
+            MinimalTupleData::decode_from_reader(&mut bytes.as_slice()),
+            Err(ByteEncodeError::NotEnoughBytes { expected, actual }) if expected == u32::MAX as usize && actual == bytes.len()
+        ));
+    }
+
+    #[test]
+    fn test_minimal_tuple_bad_hoff() {
+        let mut tuple = minimal_tuple();
+        tuple.t_hoff = 8;
+        tuple.t_bits.clear();
+        tuple.t_len = 19;
+        assert!(MinimalTupleData::decode(&tuple.encode()).is_err());
+    }
+
+    #[test]
+    fn test_payload_from_bad_hoff() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.data = vec![0, 7, 0, 0, 0];
+        tuple.t_hoff = 200;
+        assert!(tuple.user_data().is_err());
+
+        assert_eq!(tuple.payload_from(24), &[7, 0, 0, 0]);
+        assert_eq!(tuple.payload_from(0), &[0, 7, 0, 0, 0]);
+        assert!(tuple.payload_from(200).is_empty());
+    }
+
+    #[test]
+    fn test_field3_command_id() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_field3 = 7;
+        assert_eq!(tuple.field3(), TField3::CommandId(7));
+        assert!(!tuple.is_combo_cid());
+
+        tuple.t_infomask = HEAP_COMBOCID;
+        assert_eq!(tuple.field3(), TField3::CommandId(7));
+        assert!(tuple.is_combo_cid());
+    }
+
+    #[test]
+    fn test_field3_xvac() {
+        let mut tuple = tuple_with_infomask2(1);
+        tuple.t_field3 = 1234;
+        for moved in [HEAP_MOVED_OFF, HEAP_MOVED_IN] {
+            tuple.t_infomask = moved;
+            assert_eq!(tuple.field3(), TField3::Xvac(1234));
+        }
+    }
+
+    #[test]
+    fn test_encode_padded() {
+        let tuple = tuple(1, vec![1, 2, 3]);
+        let padded = tuple.encode_padded(32).unwrap();
+        assert_eq!(padded.len(), 32);
+        assert_eq!(padded[..26], tuple.encode()[..]);
+        assert!(padded[26..].iter().all(|b| *b == 0));
+        assert_eq!(tuple.encode_padded(26).unwrap(), tuple.encode());
+        assert!(matches!(
+            tuple.encode_padded(25),
+            Err(ByteEncodeError::TooManyBytes { expected: 25, actual: 26 })
+        ));
+    }
+
+    #[test]
+    fn test_is_frozen() {
+        let mut hinted = tuple(100, vec![]);
+        assert!(!hinted.is_frozen());
+        hinted.t_infomask = HEAP_XMIN_FROZEN;
+        assert!(hinted.is_frozen());
+        hinted.t_xmax = 120;
+        assert!(!hinted.is_frozen());
+        hinted.t_infomask |= HEAP_XMAX_INVALID;
+        assert!(hinted.is_frozen());
+
+        let mut old_style = tuple(FROZEN_TRANSACTION_ID, vec![]);
+        assert!(old_style.is_frozen());
+        old_style.t_infomask = HEAP_XMIN_COMMITTED;
+        assert!(old_style.is_frozen());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut tuple = tuple_with_infomask2(3 | HEAP_HOT_UPDATED);
+        tuple.t_infomask = HEAP_HASVARWIDTH | HEAP_XMAX_INVALID;
+        assert_eq!(
+            tuple.to_string(),
+            "HeapTuple(xmin=1,xmax=2,field3=0,ctid=(0,1),natts=3,infomask=0x0802,infomask2=0x4003,hoff=24,data_len=1)"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::util::{maxalign, ByteEncodeError, ByteEncodeResult, ByteEncoded, GetByteSliceExt};
+
+use super::item_pointer_data::ItemPointerData;
+
+///
+/// Index tuple header structure
+///
+/// All index tuples start with IndexTupleData.  If the HasNulls bit is set,
+/// this is followed by an IndexAttributeBitMapData.  The index attribute
+/// values follow, beginning at a MAXALIGN boundary.
+///
+/// Note that the space allocated for the bitmap does not vary with the number
+/// of attributes; that is because we don't have room to store the number of
+/// attributes in the header.  Given the MAXALIGN constraint there's no space
+/// savings to be had anyway, for usual values of INDEX_MAX_KEYS.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct IndexTupleData {
+    /// reference TID to heap tuple
+    pub t_tid: ItemPointerData,
+    ///
+    /// t_info is laid out in the following fashion:
+    ///
+    /// 15th (high) bit: has nulls
+    /// 14th bit: has var-width attributes
+    /// 13th bit: AM-defined meaning
+    /// 12-0 bit: size of tuple
+    ///
+    pub t_info: u16,
+    /// everything after the fixed header: the null bitmap if any, padding and the key
+    pub data: Vec<u8>,
+    // MORE DATA FOLLOWS AT END OF STRUCT
+}
+
+pub const INDEX_SIZE_MASK: u16 = 0x1FFF;
+/// reserved for use by index AMs
+pub const INDEX_AM_RESERVED_BIT: u16 = 0x2000;
+pub const INDEX_VAR_MASK: u16 = 0x4000;
+pub const INDEX_NULL_MASK: u16 = 0x8000;
+
+/// Size of the fixed IndexTupleData header
+pub const SIZEOF_INDEX_TUPLE_DATA: usize = 8;
+/// Size of IndexAttributeBitMapData, which holds INDEX_MAX_KEYS (32) bits
+pub const SIZEOF_INDEX_ATTRIBUTE_BITMAP: usize = 4;
+
+impl IndexTupleData {
+    /// Size of the whole tuple as recorded in t_info.
+    pub fn size(&self) -> usize {
+        (self.t_info & INDEX_SIZE_MASK) as usize
+    }
+
+    pub fn has_nulls(&self) -> bool {
+        self.t_info & INDEX_NULL_MASK != 0
+    }
+
+    pub fn has_varwidths(&self) -> bool {
+        self.t_info & INDEX_VAR_MASK != 0
+    }
+
+    /// The null bitmap, present only if the tuple has nulls.
+    pub fn null_bitmap(&self) -> ByteEncodeResult<Option<&[u8]>> {
+        if !self.has_nulls() {
+            return Ok(None);
+        }
+        Ok(Some(self.data.get_byte_slice(0, SIZEOF_INDEX_ATTRIBUTE_BITMAP)?))
+    }
+
+    /// Offset of the key data from the start of the tuple, like IndexInfoFindDataOffset.
+    pub fn data_offset(&self) -> usize {
+        if self.has_nulls() {
+            maxalign(SIZEOF_INDEX_TUPLE_DATA + SIZEOF_INDEX_ATTRIBUTE_BITMAP)
+        } else {
+            maxalign(SIZEOF_INDEX_TUPLE_DATA)
+        }
+    }
+
+    /// The indexed key attribute data, up to the tuple size recorded in t_info.
+    pub fn key_payload(&self) -> &[u8] {
+        let end = self.size().saturating_sub(SIZEOF_INDEX_TUPLE_DATA).min(self.data.len());
+        let start = (self.data_offset() - SIZEOF_INDEX_TUPLE_DATA).min(end);
+        &self.data[start..end]
+    }
+}
+
+impl ByteEncoded for IndexTupleData {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(SIZEOF_INDEX_TUPLE_DATA + self.data.len());
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.t_tid.encode_into(buf);
+        self.t_info.encode_into(buf);
+        buf.extend_from_slice(&self.data);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let t_tid = ItemPointerData::decode(bytes.get_byte_slice(0, 6)?)?;
+        let t_info = u16::decode(bytes.get_byte_slice(6, 8)?)?;
+        let data = bytes
+            .get(SIZEOF_INDEX_TUPLE_DATA..)
+            .ok_or(ByteEncodeError::NotEnoughBytes {
+                expected: SIZEOF_INDEX_TUPLE_DATA,
+                actual: bytes.len(),
+            })?
+            .to_vec();
+        Ok(IndexTupleData { t_tid, t_info, data })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        self.t_tid.encode_into_writer(writer)?;
+        self.t_info.encode_into_writer(writer)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let t_tid = ItemPointerData::decode_from_reader(reader)?;
+        let t_info = u16::decode_from_reader(reader)?;
+        let mut data = vec![0; ((t_info & INDEX_SIZE_MASK) as usize).saturating_sub(SIZEOF_INDEX_TUPLE_DATA)];
+        reader.read_exact(&mut data)?;
+        Ok(IndexTupleData { t_tid, t_info, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dto::{deserialize_index_attrs, Datum, TupleDesc},
+        test_util::{page_lazy, BTREE_LEAF_PAGE, INT4},
+    };
+
+    #[test]
+    fn test_btree_key() {
+        let page = page_lazy(BTREE_LEAF_PAGE);
+        let item_id = page.item_id(3).unwrap();
+        let tuple = IndexTupleData::decode(page.item_bytes(&item_id).unwrap()).unwrap();
+
+        assert_eq!(tuple.t_tid.block_number(), 0);
+        assert_eq!(tuple.t_tid.ip_posid, 3);
+        assert_eq!(tuple.size(), 16);
+        assert!(!tuple.has_nulls());
+        assert_eq!(tuple.key_payload(), &[3, 0, 0, 0, 0, 0, 0, 0]);
+
+        let desc = TupleDesc::new(vec![INT4]);
+        let values = deserialize_index_attrs(&tuple, &desc).unwrap();
+        assert_eq!(values, vec![Datum::Value(vec![3, 0, 0, 0])]);
+        assert_eq!(IndexTupleData::decode(&tuple.encode()).unwrap(), tuple);
+    }
+
+    #[test]
+    fn test_nulls() {
+        let tuple = IndexTupleData {
+            t_tid: ItemPointerData::decode(&[0, 0, 0, 0, 1, 0]).unwrap(),
+            t_info: INDEX_NULL_MASK | 24,
+            // bitmap: attribute 1 null, attribute 2 present
+            data: vec![0b10, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0],
+        };
+        assert_eq!(tuple.data_offset(), 16);
+        assert_eq!(tuple.key_payload(), &[9, 0, 0, 0, 0, 0, 0, 0]);
+
+        let desc = TupleDesc::new(vec![INT4, INT4]);
+        let values = deserialize_index_attrs(&tuple, &desc).unwrap();
+        assert_eq!(values, vec![Datum::Null, Datum::Value(vec![9, 0, 0, 0])]);
+    }
+}
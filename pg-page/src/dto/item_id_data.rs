@@ -24,10 +24,11 @@ pub struct ItemIdData {
 /// lp_flags has these possible states.  An UNUSED line pointer is available
 /// for immediate re-use, the other states are not.
 ///
-///      Redirect
+/// Redirect:
 /// In a REDIRECT pointer, lp_off holds offset number for next line pointer
 ///
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LpFlags {
     /// unused (should always have lp_len=0)
     Unused = 0,
@@ -39,11 +40,21 @@ pub enum LpFlags {
     Dead = 3,
 }
 
+impl LpFlags {
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
 impl ByteEncoded for ItemIdData {
     fn encode(&self) -> Vec<u8> {
         self.lp.to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.lp);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let mut lp = [0_u8; 4];
         lp.copy_from_slice(bytes);
@@ -72,6 +83,18 @@ impl ByteEncoded for ItemIdData {
 }
 
 impl ItemIdData {
+    pub fn new(off: u16, len: u16, flags: LpFlags) -> Self {
+        let mut item_id = ItemIdData::default();
+        item_id.set_lp_off(off);
+        item_id.set_lp_len(len);
+        item_id.set_flags(flags);
+        item_id
+    }
+
+    pub fn set_flags(&mut self, flags: LpFlags) {
+        self.set_lp_flags(flags.as_u8());
+    }
+
     pub fn flags(&self) -> LpFlags {
         match self.lp_flags() {
             0 => LpFlags::Unused,
@@ -96,4 +119,116 @@ impl ItemIdData {
     pub fn is_unused(&self) -> bool {
         self.flags() == LpFlags::Unused
     }
-}
\ No newline at end of file
+
+    /// Decodes a line pointer written by a big-endian server. Its bitfields are packed from
+    /// the most significant bit of the word, `lp_off` first, so reversing the bytes alone
+    /// does not recover them.
+    pub fn decode_big_endian(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let word = u32::decode(bytes)?.swap_bytes();
+        let mut item_id = ItemIdData::default();
+        item_id.set_lp_off((word >> 17) as u16);
+        item_id.set_lp_flags(((word >> 15) & 0b11) as u8);
+        item_id.set_lp_len((word & 0x7FFF) as u16);
+        Ok(item_id)
+    }
+
+    /// The line pointer as a big-endian server lays it out, the inverse of
+    /// [`ItemIdData::decode_big_endian`].
+    pub fn encode_big_endian(&self) -> Vec<u8> {
+        let word = (self.lp_off() as u32) << 17 | (self.lp_flags() as u32) << 15 | self.lp_len() as u32;
+        word.to_be_bytes().to_vec()
+    }
+}
+
+impl std::fmt::Display for ItemIdData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ItemId(off={},len={},flags={:?})", self.lp_off(), self.lp_len(), self.flags())
+    }
+}
+
+/// Serialized as the decoded `lp_off`, `lp_len` and `lp_flags` rather than the packed bits.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ItemIdData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ItemIdData", 3)?;
+        state.serialize_field("lp_off", &self.lp_off())?;
+        state.serialize_field("lp_len", &self.lp_len())?;
+        state.serialize_field("lp_flags", &self.flags())?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ItemIdData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            lp_off: u16,
+            lp_len: u16,
+            lp_flags: LpFlags,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        // both are 15-bit fields
+        if fields.lp_off > 0x7FFF || fields.lp_len > 0x7FFF {
+            return Err(serde::de::Error::custom(format!(
+                "line pointer offset {} or length {} does not fit in 15 bits",
+                fields.lp_off, fields.lp_len
+            )));
+        }
+        Ok(ItemIdData::new(fields.lp_off, fields.lp_len, fields.lp_flags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let item_id = ItemIdData::new(8000, 40, LpFlags::Normal);
+        assert_eq!(item_id.lp_off(), 8000);
+        assert_eq!(item_id.lp_len(), 40);
+        assert_eq!(item_id.flags(), LpFlags::Normal);
+        assert_eq!(ItemIdData::decode(&item_id.encode()).unwrap(), item_id);
+    }
+
+    #[test]
+    fn test_set_flags() {
+        let mut item_id = ItemIdData::new(8000, 40, LpFlags::Normal);
+        for flags in [LpFlags::Unused, LpFlags::Redirect, LpFlags::Dead, LpFlags::Normal] {
+            item_id.set_flags(flags);
+            assert_eq!(item_id.flags(), flags);
+            assert_eq!(item_id.lp_flags(), flags.as_u8());
+            assert_eq!(item_id.lp_off(), 8000);
+            assert_eq!(item_id.lp_len(), 40);
+        }
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let item_id = ItemIdData::new(8000, 40, LpFlags::Redirect);
+        // lp_off in the top 15 bits, then lp_flags, then lp_len
+        let word: u32 = 8000 << 17 | 2 << 15 | 40;
+        assert_eq!(item_id.encode_big_endian(), word.to_be_bytes());
+        assert_eq!(ItemIdData::decode_big_endian(&word.to_be_bytes()).unwrap(), item_id);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ItemIdData::new(3, 2, LpFlags::Normal).to_string(), "ItemId(off=3,len=2,flags=Normal)");
+        assert_eq!(ItemIdData::new(5, 0, LpFlags::Redirect).to_string(), "ItemId(off=5,len=0,flags=Redirect)");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let item_id = ItemIdData::new(8000, 40, LpFlags::Redirect);
+        let json = serde_json::to_string(&item_id).unwrap();
+        assert_eq!(json, r#"{"lp_off":8000,"lp_len":40,"lp_flags":"Redirect"}"#);
+        assert_eq!(serde_json::from_str::<ItemIdData>(&json).unwrap(), item_id);
+        assert!(serde_json::from_str::<ItemIdData>(r#"{"lp_off":32768,"lp_len":0,"lp_flags":"Unused"}"#).is_err());
+    }
+}
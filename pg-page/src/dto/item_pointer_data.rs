@@ -3,6 +3,7 @@ use crate::util::{ByteEncodeResult, ByteEncoded};
 use super::block_id_data::BlockIdData;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ItemPointerData {
     /// block number
     pub ip_blkid: BlockIdData,
@@ -10,14 +11,43 @@ pub struct ItemPointerData {
     pub ip_posid: u16,
 }
 
+/// Block number ItemPointerSetMovedPartitions stores in the t_ctid of a tuple whose update
+/// moved it to another partition.
+pub const MOVED_PARTITIONS_BLOCK_NUMBER: u32 = 0xFFFF_FFFF;
+/// Offset number ItemPointerSetMovedPartitions stores alongside it.
+pub const MOVED_PARTITIONS_OFFSET_NUMBER: u16 = 0xFFFD;
+
+impl ItemPointerData {
+    pub fn block_number(&self) -> u32 {
+        self.ip_blkid.block_number()
+    }
+
+    /// Whether this is the sentinel left in t_ctid when an update moved the row to
+    /// another partition, so there is no successor to follow.
+    pub fn is_moved_partitions(&self) -> bool {
+        self.block_number() == MOVED_PARTITIONS_BLOCK_NUMBER
+            && self.ip_posid == MOVED_PARTITIONS_OFFSET_NUMBER
+    }
+}
+
+impl std::fmt::Display for ItemPointerData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ItemPointer(blk={},off={})", self.block_number(), self.ip_posid)
+    }
+}
+
 impl ByteEncoded for ItemPointerData {
     fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.ip_blkid.encode());
-        buf.extend(self.ip_posid.encode());
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
         buf
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.ip_blkid.encode_into(buf);
+        self.ip_posid.encode_into(buf);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let ip_blkid = BlockIdData::decode(&bytes[0..4])?;
         let ip_posid = u16::decode(&bytes[4..6])?;
@@ -40,3 +70,26 @@ impl ByteEncoded for ItemPointerData {
         6
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_number() {
+        let tid = ItemPointerData {
+            ip_blkid: BlockIdData::from_block_number(u32::MAX),
+            ip_posid: 3,
+        };
+        assert_eq!(tid.block_number(), u32::MAX);
+    }
+
+    #[test]
+    fn test_display() {
+        let tid = ItemPointerData {
+            ip_blkid: BlockIdData::from_block_number(70000),
+            ip_posid: 6,
+        };
+        assert_eq!(tid.to_string(), "ItemPointer(blk=70000,off=6)");
+    }
+}
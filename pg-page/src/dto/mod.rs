@@ -1,13 +1,25 @@
 pub mod block_id_data;
+pub mod btree;
+pub mod control;
+pub mod fsm;
+pub mod gist;
+pub mod hash;
 pub mod heap_tuple_header_data;
+pub mod index_tuple_data;
 pub mod item_id_data;
 pub mod item_pointer_data;
 pub mod page;
 pub mod page_header_data;
 pub mod page_xl_log_rex_ptr;
 pub mod page_lazy;
+pub mod sequence;
+pub mod snapshot;
+pub mod spgist;
+pub mod tuple_desc;
+pub mod vm;
 
 pub use {
-    block_id_data::*, heap_tuple_header_data::*, item_id_data::*, item_pointer_data::*, page::*,
-    page_header_data::*, page_xl_log_rex_ptr::*, page_lazy::*,
+    block_id_data::*, btree::*, control::*, fsm::*, gist::*, hash::*, heap_tuple_header_data::*, index_tuple_data::*, item_id_data::*, item_pointer_data::*, page::*,
+    page_header_data::*, page_xl_log_rex_ptr::*, page_lazy::*, sequence::*, snapshot::*, spgist::*,
+    tuple_desc::*, vm::*,
 };
@@ -1,4 +1,4 @@
-use crate::util::{ByteEncodeResult, ByteEncoded, GetByteSliceExt};
+use crate::util::{maxalign, ByteEncodeResult, ByteEncoded, GetByteSliceExt};
 
 use super::{
     *
@@ -45,22 +45,23 @@ impl Page {
         })
     }
 
+    /// Reserves a normal line pointer and MAXALIGN'd storage for a tuple with `data_size`
+    /// bytes after its 23 byte header, or returns `None` if they don't fit in the free space.
     pub fn reserve_tuple(&mut self, data_size: u16) -> Option<ItemIdData> {
-        let tuple_size = HeapTupleHeaderData::byte_size() + data_size;
-        // TODO: add logic for alignment and null bitmap
-        if self.header_data.pd_upper - self.header_data.pd_lower < tuple_size + ItemPointerData::byte_size() {
+        let tuple_size = (SIZEOF_HEAP_TUPLE_HEADER as u16).checked_add(data_size)?;
+        // TODO: add logic for null bitmap
+        let aligned_size = maxalign(tuple_size as usize);
+        let free_space = self.header_data.pd_upper.saturating_sub(self.header_data.pd_lower);
+        if (free_space as usize) < aligned_size + ItemIdData::byte_size() as usize {
             None
         } else {
-            let mut item_id = ItemIdData::default();
-            item_id.set_lp_off(self.header_data.pd_upper - tuple_size);
-            item_id.set_lp_len(tuple_size);
+            let aligned_size = aligned_size as u16;
+            let item_id = ItemIdData::new(self.header_data.pd_upper - aligned_size, tuple_size, LpFlags::Normal);
 
             self.item_id_data.push(item_id);
 
-            self.header_data.pd_lower += ItemPointerData::byte_size();
-            self.header_data.pd_upper -= tuple_size;
-            
-            assert!(self.header_data.pd_upper >= self.header_data.pd_lower);
+            self.header_data.pd_lower += ItemIdData::byte_size();
+            self.header_data.pd_upper -= aligned_size;
 
             Some(item_id)
         }
@@ -78,4 +79,31 @@ impl Page {
         // self.item_id_data = new_item_id_data;
         // self.items = new_items;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, tuple};
+
+    #[test]
+    fn test_reserve_tuple_normal() {
+        let bytes = page_bytes(&[tuple(1, vec![0])]);
+        let mut page = Page::from_reader(&mut bytes.as_slice()).unwrap();
+        let item_id = page.reserve_tuple(32).unwrap();
+        assert_eq!(item_id.flags(), LpFlags::Normal);
+        assert_eq!(page.item_id_data.last(), Some(&item_id));
+        assert_eq!(page.header_data.pd_lower, 24 + 2 * 4);
+    }
+
+    #[test]
+    fn test_reserve_tuple_maxaligned() {
+        let bytes = page_bytes(&[]);
+        let mut page = Page::from_reader(&mut bytes.as_slice()).unwrap();
+        let first = page.reserve_tuple(5).unwrap();
+        let second = page.reserve_tuple(5).unwrap();
+        assert_eq!((first.lp_off(), first.lp_len()), (8192 - 32, 28));
+        assert_eq!((second.lp_off(), second.lp_len()), (8192 - 64, 28));
+        assert_eq!(page.header_data.pd_upper, 8192 - 64);
+    }
+}
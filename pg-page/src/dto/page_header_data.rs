@@ -1,4 +1,4 @@
-use crate::util::{ByteEncodeResult, ByteEncoded};
+use crate::{util::{ByteEncodeResult, ByteEncoded, DecodeContext, Endianness, GetByteSliceExt}, Error};
 
 use super::page_xl_log_rex_ptr::PageXLogRecPtr;
 
@@ -48,6 +48,7 @@ use super::page_xl_log_rex_ptr::PageXLogRecPtr;
 /// are 15 bits.
 ///
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PageHeaderData {
     /// LSN: next byte after last byte of WAL record for last change to this page
     pub pd_lsn: PageXLogRecPtr,
@@ -69,29 +70,33 @@ pub struct PageHeaderData {
 
 impl ByteEncoded for PageHeaderData {
     fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.pd_lsn.encode());
-        buf.extend(self.pd_checksum.encode());
-        buf.extend(self.pd_flags.encode());
-        buf.extend(self.pd_lower.encode());
-        buf.extend(self.pd_upper.encode());
-        buf.extend(self.pd_special.encode());
-        buf.extend(self.pd_pagesize_version.encode());
-        buf.extend(self.pd_prune_xid.encode());
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
         buf
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.pd_lsn.encode_into(buf);
+        self.pd_checksum.encode_into(buf);
+        self.pd_flags.encode_into(buf);
+        self.pd_lower.encode_into(buf);
+        self.pd_upper.encode_into(buf);
+        self.pd_special.encode_into(buf);
+        self.pd_pagesize_version.encode_into(buf);
+        self.pd_prune_xid.encode_into(buf);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
-        let mut reader = std::io::Cursor::new(bytes);
+        let field = |start: usize, end: usize, context: &'static str| bytes.get_byte_slice(start, end).at(context);
         Ok(Self {
-            pd_lsn: PageXLogRecPtr::decode_from_reader(&mut reader)?,
-            pd_checksum: u16::decode_from_reader(&mut reader)?,
-            pd_flags: u16::decode_from_reader(&mut reader)?,
-            pd_lower: u16::decode_from_reader(&mut reader)?,
-            pd_upper: u16::decode_from_reader(&mut reader)?,
-            pd_special: u16::decode_from_reader(&mut reader)?,
-            pd_pagesize_version: u16::decode_from_reader(&mut reader)?,
-            pd_prune_xid: u32::decode_from_reader(&mut reader)?,
+            pd_lsn: PageXLogRecPtr::decode(field(0, 8, "PageHeaderData.pd_lsn")?)?,
+            pd_checksum: u16::decode(field(8, 10, "PageHeaderData.pd_checksum")?)?,
+            pd_flags: u16::decode(field(10, 12, "PageHeaderData.pd_flags")?)?,
+            pd_lower: u16::decode(field(12, 14, "PageHeaderData.pd_lower")?)?,
+            pd_upper: u16::decode(field(14, 16, "PageHeaderData.pd_upper")?)?,
+            pd_special: u16::decode(field(16, 18, "PageHeaderData.pd_special")?)?,
+            pd_pagesize_version: u16::decode(field(18, 20, "PageHeaderData.pd_pagesize_version")?)?,
+            pd_prune_xid: u32::decode(field(20, 24, "PageHeaderData.pd_prune_xid")?)?,
         })
     }
 
@@ -111,6 +116,29 @@ impl ByteEncoded for PageHeaderData {
 }
 
 impl PageHeaderData {
+    /// Decodes a header written with the given byte order.
+    pub fn decode_with_endianness(bytes: &[u8], endianness: Endianness) -> ByteEncodeResult<Self> {
+        let header = Self::decode(bytes)?;
+        Ok(match endianness {
+            Endianness::Little => header,
+            Endianness::Big => header.swap_bytes(),
+        })
+    }
+
+    /// The same header with the byte order of every field reversed.
+    pub fn swap_bytes(&self) -> Self {
+        PageHeaderData {
+            pd_lsn: self.pd_lsn.swap_bytes(),
+            pd_checksum: self.pd_checksum.swap_bytes(),
+            pd_flags: self.pd_flags.swap_bytes(),
+            pd_lower: self.pd_lower.swap_bytes(),
+            pd_upper: self.pd_upper.swap_bytes(),
+            pd_special: self.pd_special.swap_bytes(),
+            pd_pagesize_version: self.pd_pagesize_version.swap_bytes(),
+            pd_prune_xid: self.pd_prune_xid.swap_bytes(),
+        }
+    }
+
     pub fn page_size(&self) -> usize {
         (self.pd_pagesize_version & 0xFF00) as usize
     }
@@ -118,4 +146,331 @@ impl PageHeaderData {
     pub fn page_version(&self) -> u16 {
         self.pd_pagesize_version & 0x00FF
     }
-}
\ No newline at end of file
+
+    /// The `pd_flags` bits, including any outside [`PD_VALID_FLAG_BITS`].
+    pub fn flags(&self) -> PageFlags {
+        PageFlags(self.pd_flags)
+    }
+
+    /// Whether the header is all zeros, as on a block the relation was extended by but that
+    /// was never initialized (PageIsNew).
+    pub fn is_new_page(&self) -> bool {
+        self.pd_lsn.xlogid == 0
+            && self.pd_lsn.xrecoff == 0
+            && self.pd_checksum == 0
+            && self.pd_flags == 0
+            && self.pd_lower == 0
+            && self.pd_upper == 0
+            && self.pd_special == 0
+            && self.pd_pagesize_version == 0
+            && self.pd_prune_xid == 0
+    }
+
+    /// Whether PD_HAS_FREE_LINES is set: there may be unused line pointers to reuse.
+    pub fn has_free_line_pointers(&self) -> bool {
+        self.flags().contains(PageFlags::HAS_FREE_LINES)
+    }
+
+    /// Whether PD_PAGE_FULL is set: a recent update found no room for the new tuple.
+    pub fn page_full(&self) -> bool {
+        self.flags().contains(PageFlags::PAGE_FULL)
+    }
+
+    /// Whether PD_ALL_VISIBLE is set: all tuples on the page are visible to everyone.
+    pub fn all_visible(&self) -> bool {
+        self.flags().contains(PageFlags::ALL_VISIBLE)
+    }
+
+    /// Whether the page uses the layout version that carries `pd_checksum` (version 4,
+    /// PostgreSQL 9.3 and later). Older layouts stored the timeline there instead.
+    pub fn is_checksum_version(&self) -> bool {
+        self.page_version() == PG_PAGE_LAYOUT_VERSION
+    }
+
+    /// Whether `page_size` can be recorded in `pd_pagesize_version`: a multiple of 256, which
+    /// leaves the low byte for the layout version, from 64 bytes up to 32 kB.
+    pub fn is_valid_page_size(page_size: usize) -> bool {
+        page_size.is_multiple_of(256) && (64..=32768).contains(&page_size)
+    }
+
+    /// Checks that `page_size` is a valid block size and that the header offsets are in
+    /// order within it, `header size <= pd_lower <= pd_upper <= pd_special <= page_size`,
+    /// so slicing the page by them can't overrun.
+    pub fn validate(&self, page_size: usize) -> Result<(), Error> {
+        if !Self::is_valid_page_size(page_size) {
+            // sizes past u16 saturate rather than wrapping to a plausible-looking value
+            return Err(Error::InvalidPageSize(u16::try_from(page_size).unwrap_or(u16::MAX)));
+        }
+        if self.pd_special < Self::byte_size() || self.pd_special as usize > page_size {
+            return Err(Error::InvalidPageHeaderSpecialOffset(self.pd_special));
+        }
+        if self.pd_lower < Self::byte_size() || self.pd_lower > self.pd_upper {
+            return Err(Error::InvalidPageHeaderLowerBound(self.pd_lower));
+        }
+        // tuple storage running into the special space
+        if self.pd_upper > self.pd_special {
+            return Err(Error::InvalidPageHeaderSpecialBound(self.pd_special));
+        }
+        Ok(())
+    }
+
+    /// Decodes a header and validates it against the page size it records, for callers that
+    /// would rather reject a corrupt header up front than trip over its offsets later.
+    pub fn decode_validated(bytes: &[u8]) -> Result<Self, Error> {
+        let header = Self::decode(bytes)?;
+        header.validate(header.page_size())?;
+        Ok(header)
+    }
+}
+
+impl std::fmt::Display for PageHeaderData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PageHeader(lsn={:X}/{:X},checksum={:#06x},flags={:#06x},lower={},upper={},special={},size={},version={},prune_xid={})",
+            self.pd_lsn.xlogid,
+            self.pd_lsn.xrecoff,
+            self.pd_checksum,
+            self.pd_flags,
+            self.pd_lower,
+            self.pd_upper,
+            self.pd_special,
+            self.page_size(),
+            self.page_version(),
+            self.pd_prune_xid
+        )
+    }
+}
+
+/// current page layout version number
+pub const PG_PAGE_LAYOUT_VERSION: u16 = 4;
+
+/// are there any unused line pointers?
+pub const PD_HAS_FREE_LINES: u16 = 0x0001;
+/// not enough free space for new tuple?
+pub const PD_PAGE_FULL: u16 = 0x0002;
+/// all tuples on page are visible to everyone
+pub const PD_ALL_VISIBLE: u16 = 0x0004;
+/// OR of all valid pd_flags bits
+pub const PD_VALID_FLAG_BITS: u16 = 0x0007;
+
+/// The `pd_flags` bits of a page header.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Hash)]
+pub struct PageFlags(pub u16);
+
+impl PageFlags {
+    pub const HAS_FREE_LINES: PageFlags = PageFlags(PD_HAS_FREE_LINES);
+    pub const PAGE_FULL: PageFlags = PageFlags(PD_PAGE_FULL);
+    pub const ALL_VISIBLE: PageFlags = PageFlags(PD_ALL_VISIBLE);
+
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn contains(&self, other: PageFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether bits outside PD_VALID_FLAG_BITS are set, which only happens on corrupt pages.
+    pub fn has_invalid_bits(&self) -> bool {
+        self.0 & !PD_VALID_FLAG_BITS != 0
+    }
+}
+
+impl std::ops::BitOr for PageFlags {
+    type Output = PageFlags;
+
+    fn bitor(self, rhs: PageFlags) -> PageFlags {
+        PageFlags(self.0 | rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::ByteEncodeError;
+
+    fn header_with_flags(pd_flags: u16) -> PageHeaderData {
+        PageHeaderData {
+            pd_lsn: PageXLogRecPtr {
+                xlogid: 0,
+                xrecoff: 0,
+            },
+            pd_checksum: 0,
+            pd_flags,
+            pd_lower: 24,
+            pd_upper: 8192,
+            pd_special: 8192,
+            pd_pagesize_version: 8192 | 4,
+            pd_prune_xid: 0,
+        }
+    }
+
+    #[test]
+    fn test_has_free_line_pointers() {
+        let header = header_with_flags(PD_HAS_FREE_LINES);
+        assert!(header.has_free_line_pointers());
+        assert!(!header.page_full());
+        assert!(!header.all_visible());
+    }
+
+    #[test]
+    fn test_page_full() {
+        let header = header_with_flags(PD_PAGE_FULL);
+        assert!(!header.has_free_line_pointers());
+        assert!(header.page_full());
+        assert!(!header.all_visible());
+    }
+
+    #[test]
+    fn test_all_visible() {
+        let header = header_with_flags(PD_ALL_VISIBLE);
+        assert!(!header.has_free_line_pointers());
+        assert!(!header.page_full());
+        assert!(header.all_visible());
+    }
+
+    #[test]
+    fn test_flags() {
+        let header = header_with_flags(PD_ALL_VISIBLE | PD_HAS_FREE_LINES);
+        assert_eq!(header.flags(), PageFlags::ALL_VISIBLE | PageFlags::HAS_FREE_LINES);
+        assert!(header.flags().contains(PageFlags::ALL_VISIBLE));
+        assert!(!header.flags().contains(PageFlags::ALL_VISIBLE | PageFlags::PAGE_FULL));
+        assert!(!header.flags().has_invalid_bits());
+        assert!(header_with_flags(0x0010).flags().has_invalid_bits());
+    }
+
+    #[test]
+    fn test_is_checksum_version() {
+        let mut header = header_with_flags(0);
+        assert!(header.is_checksum_version());
+        header.pd_pagesize_version = 8192 | 3;
+        assert!(!header.is_checksum_version());
+    }
+
+    #[test]
+    fn test_validate_upper_past_special() {
+        let mut header = header_with_flags(0);
+        assert!(header.validate(8192).is_ok());
+        header.pd_special = 8176;
+        assert!(matches!(
+            header.validate(8192),
+            Err(Error::InvalidPageHeaderSpecialBound(8176))
+        ));
+        header.pd_upper = 8176;
+        assert!(header.validate(8192).is_ok());
+    }
+
+    #[test]
+    fn test_validate_page_size() {
+        let header = header_with_flags(0);
+        assert!(matches!(header.validate(8000), Err(Error::InvalidPageSize(8000))));
+        assert!(matches!(header.validate(0), Err(Error::InvalidPageSize(0))));
+        assert!(matches!(header.validate(65536), Err(Error::InvalidPageSize(u16::MAX))));
+        // the header's offsets don't fit a smaller page
+        assert!(matches!(
+            header.validate(4096),
+            Err(Error::InvalidPageHeaderSpecialOffset(8192))
+        ));
+    }
+
+    #[test]
+    fn test_validate_lower_bound() {
+        let mut header = header_with_flags(0);
+        header.pd_lower = 20;
+        assert!(matches!(header.validate(8192), Err(Error::InvalidPageHeaderLowerBound(20))));
+        header.pd_lower = 4000;
+        header.pd_upper = 3000;
+        assert!(matches!(header.validate(8192), Err(Error::InvalidPageHeaderLowerBound(4000))));
+    }
+
+    #[test]
+    fn test_validate_special_offset() {
+        let mut header = header_with_flags(0);
+        header.pd_special = 16;
+        assert!(matches!(
+            header.validate(8192),
+            Err(Error::InvalidPageHeaderSpecialOffset(16))
+        ));
+    }
+
+    #[test]
+    fn test_decode_validated() {
+        let header = header_with_flags(0);
+        assert_eq!(PageHeaderData::decode_validated(&header.encode()).unwrap(), header);
+
+        let mut corrupt = header;
+        corrupt.pd_upper = 10;
+        assert!(matches!(
+            PageHeaderData::decode_validated(&corrupt.encode()),
+            Err(Error::InvalidPageHeaderLowerBound(24))
+        ));
+        assert!(matches!(
+            PageHeaderData::decode_validated(&header.encode()[..20]),
+            Err(Error::ByteEncoding(_))
+        ));
+        // decode itself still takes whatever it is given
+        assert_eq!(PageHeaderData::decode(&corrupt.encode()).unwrap(), corrupt);
+    }
+
+    #[test]
+    fn test_decode_with_endianness() {
+        let mut header = header_with_flags(PD_ALL_VISIBLE);
+        header.pd_lsn.xrecoff = 0x0100_0000;
+        header.pd_prune_xid = 733;
+        let little = header.encode();
+        let big = header.swap_bytes().encode();
+
+        assert_eq!(PageHeaderData::decode_with_endianness(&little, Endianness::Little).unwrap(), header);
+        assert_eq!(PageHeaderData::decode_with_endianness(&big, Endianness::Big).unwrap(), header);
+        assert_eq!(PageHeaderData::decode_with_endianness(&big, Endianness::Little).unwrap().page_size(), 0x0400);
+    }
+
+    #[test]
+    fn test_is_new_page() {
+        assert!(PageHeaderData::decode(&[0; 24]).unwrap().is_new_page());
+        assert!(!header_with_flags(0).is_new_page());
+    }
+
+    #[test]
+    fn test_decode_error_context() {
+        let bytes = header_with_flags(0).encode();
+        let err = PageHeaderData::decode(&bytes[..15]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding PageHeaderData.pd_upper: Not enough bytes to decode, expected 16 bytes, got 15 bytes"
+        );
+        assert!(matches!(
+            err.root_cause(),
+            ByteEncodeError::NotEnoughBytes { expected: 16, actual: 15 }
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut header = header_with_flags(PD_ALL_VISIBLE);
+        header.pd_lsn = PageXLogRecPtr {
+            xlogid: 1,
+            xrecoff: 0x17B_0450,
+        };
+        header.pd_checksum = 0x7F12;
+        header.pd_prune_xid = 735;
+        assert_eq!(
+            header.to_string(),
+            "PageHeader(lsn=1/17B0450,checksum=0x7f12,flags=0x0004,lower=24,upper=8192,special=8192,size=8192,version=4,prune_xid=735)"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut header = header_with_flags(PD_ALL_VISIBLE);
+        header.pd_lsn = PageXLogRecPtr {
+            xlogid: 1,
+            xrecoff: 0x17B_0450,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.starts_with(r#"{"pd_lsn":{"xlogid":1,"xrecoff":24839248},"pd_checksum":0,"pd_flags":4,"#));
+        assert_eq!(serde_json::from_str::<PageHeaderData>(&json).unwrap(), header);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{util::{ByteEncodeResult, ByteEncoded, GetByteSliceExt}, Error};
+use crate::{checksum::pg_checksum_page, util::{maxalign, ByteEncodeError, ByteEncodeResult, ByteEncoded, GetByteSliceExt}, Error};
 
 use super::{
     *
@@ -11,67 +11,786 @@ pub struct PageLazy {
 }
 
 impl PageLazy {
-    pub fn iter_tuples(&self) -> PageLazyTuplesIter {
+    /// A fresh, empty heap page of `page_size` bytes, as PageInit lays it out: no line
+    /// pointers, all space between the header and the end of the page free, no special
+    /// space, and a zero LSN, checksum and flags.
+    ///
+    /// Panics unless `page_size` is one [`PageHeaderData::validate`] accepts, as checked by
+    /// [`PageHeaderData::is_valid_page_size`].
+    pub fn new_empty(page_size: usize) -> Self {
+        assert!(
+            PageHeaderData::is_valid_page_size(page_size),
+            "invalid page size {}",
+            page_size
+        );
+        let header_size = PageHeaderData::byte_size();
+        PageLazy {
+            header_data: PageHeaderData {
+                pd_lsn: PageXLogRecPtr { xlogid: 0, xrecoff: 0 },
+                pd_checksum: 0,
+                pd_flags: 0,
+                pd_lower: header_size,
+                pd_upper: page_size as u16,
+                pd_special: page_size as u16,
+                pd_pagesize_version: page_size as u16 | PG_PAGE_LAYOUT_VERSION,
+                pd_prune_xid: 0,
+            },
+            data: vec![0; page_size - header_size as usize],
+        }
+    }
+
+    /// Builds a page from a header and the bytes following it, checking that `data` fills the
+    /// rest of the page the header describes and that the header offsets are in order.
+    pub fn from_parts(header_data: PageHeaderData, data: Vec<u8>) -> Result<Self, Error> {
+        let header_size = PageHeaderData::byte_size();
+        let page_size = header_data.page_size();
+        header_data.validate(page_size)?;
+        if data.len() != page_size - header_size as usize {
+            return Err(ByteEncodeError::InvalidSize {
+                expected: page_size - header_size as usize,
+                actual: data.len(),
+            }
+            .into());
+        }
+        Ok(PageLazy { header_data, data })
+    }
+
+    pub fn from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let header_size = PageHeaderData::byte_size() as usize;
+        let mut bytes = vec![0; header_size];
+        reader.read_exact(&mut bytes)?;
+        let header_data = PageHeaderData::decode(&bytes)?;
+        // an all-zero page, as found in heap, FSM and VM files, has a page size of 0
+        let data_size = header_data.page_size().checked_sub(header_size).ok_or(ByteEncodeError::InvalidSize {
+            expected: header_size,
+            actual: header_data.page_size(),
+        })?;
+        let mut data = vec![0; data_size];
+        reader.read_exact(&mut data)?;
+        Ok(PageLazy { header_data, data })
+    }
+
+    /// The on-disk image of the page: the encoded header followed by `data`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PageHeaderData::byte_size() as usize + self.data.len());
+        self.header_data.encode_into(&mut bytes);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        self.header_data.encode_into_writer(writer)?;
+        Ok(writer.write_all(&self.data)?)
+    }
+
+    /// Converts the line pointers of a page written by a big-endian server into the
+    /// little-endian layout the rest of the crate reads, along with the headers of the
+    /// tuples they point at on a heap page, one without special space. The page header is
+    /// expected to be converted already, as [`PageHeaderData::decode_with_endianness`] does.
+    ///
+    /// Column values and the items of index pages are left in the server's byte order, as
+    /// their layout isn't known from the page alone.
+    pub fn convert_from_big_endian(&mut self) -> ByteEncodeResult<()> {
+        let heap = self.is_heap_layout();
+        for offset in 1..=self.line_pointer_count() {
+            let start = (offset as usize - 1) * ItemIdData::byte_size() as usize;
+            let bytes = self.data.get_byte_slice_mut(start, start + ItemIdData::byte_size() as usize)?;
+            let item_id = ItemIdData::decode_big_endian(bytes)?;
+            bytes.copy_from_slice(&item_id.encode());
+            if heap && item_id.is_normal() {
+                self.swap_tuple_header(&item_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The on-disk image of the page as a big-endian server writes it, undoing
+    /// [`PageLazy::convert_from_big_endian`].
+    pub fn to_big_endian_bytes(&self) -> ByteEncodeResult<Vec<u8>> {
+        let mut page = self.clone();
+        let heap = page.is_heap_layout();
+        for offset in 1..=page.line_pointer_count() {
+            let start = (offset as usize - 1) * ItemIdData::byte_size() as usize;
+            let bytes = page.data.get_byte_slice_mut(start, start + ItemIdData::byte_size() as usize)?;
+            let item_id = ItemIdData::decode(bytes)?;
+            bytes.copy_from_slice(&item_id.encode_big_endian());
+            if heap && item_id.is_normal() {
+                page.swap_tuple_header(&item_id)?;
+            }
+        }
+        let mut bytes = page.header_data.swap_bytes().encode();
+        bytes.extend_from_slice(&page.data);
+        Ok(bytes)
+    }
+
+    /// Whether the page has no special space, as heap pages don't.
+    fn is_heap_layout(&self) -> bool {
+        self.header_data.pd_special as usize == self.header_data.page_size()
+    }
+
+    /// Reverses the byte order of each integer field in the header of the tuple behind
+    /// `item_id`; `t_hoff` and the null bitmap are single bytes and stay as they are.
+    fn swap_tuple_header(&mut self, item_id: &ItemIdData) -> ByteEncodeResult<()> {
+        if (item_id.lp_len() as usize) < SIZEOF_HEAP_TUPLE_HEADER {
+            return Err(ByteEncodeError::InvalidSize {
+                expected: SIZEOF_HEAP_TUPLE_HEADER,
+                actual: item_id.lp_len() as usize,
+            }
+            .at("HeapTupleHeaderData"));
+        }
+        let header_size = PageHeaderData::byte_size() as usize;
+        let start = (item_id.lp_off() as usize).checked_sub(header_size).ok_or(
+            ByteEncodeError::InvalidSize {
+                expected: header_size,
+                actual: item_id.lp_off() as usize,
+            }
+            .at("ItemIdData.lp_off"),
+        )?;
+        let header = self.data.get_byte_slice_mut(start, start + SIZEOF_HEAP_TUPLE_HEADER)?;
+        // t_xmin, t_xmax and t_field3, then t_ctid's block halves and position, then
+        // t_infomask2 and t_infomask
+        for (from, to) in [(0, 4), (4, 8), (8, 12), (12, 14), (14, 16), (16, 18), (18, 20), (20, 22)] {
+            header[from..to].reverse();
+        }
+        Ok(())
+    }
+
+    pub fn iter_tuples(&self) -> PageLazyTuplesIter<'_> {
         PageLazyTuplesIter {
             page: self,
             cursor: 0,
+            ended: false,
+        }
+    }
+
+    /// Every line pointer on the page in offset number order, whatever its flags.
+    pub fn iter_line_pointers(&self) -> impl Iterator<Item = Result<ItemIdData, Error>> + '_ {
+        (1..=self.line_pointer_count()).map(|offset| self.item_id(offset))
+    }
+
+    pub fn iter_tuple_refs(&self) -> PageLazyTupleRefsIter<'_> {
+        PageLazyTupleRefsIter {
+            inner: self.iter_tuples(),
+        }
+    }
+
+    /// The PostgreSQL data checksum of the page as stored in block `block_number`.
+    pub fn compute_checksum(&self, block_number: u32) -> u16 {
+        pg_checksum_page(&self.to_bytes(), block_number)
+    }
+
+    /// Whether `pd_checksum` matches the checksum computed for block `block_number`.
+    pub fn verify_checksum(&self, block_number: u32) -> bool {
+        self.header_data.pd_checksum == self.compute_checksum(block_number)
+    }
+
+    /// Whether this page looks like it was written with data checksums enabled, judged from
+    /// the page alone: its stored checksum matches the one computed for `block_number`.
+    ///
+    /// A computed checksum is never zero, so a zero `pd_checksum` is never plausible and
+    /// usually means checksums were off when the page was written.
+    pub fn checksum_is_plausible(&self, block_number: u32) -> bool {
+        self.header_data.pd_checksum != 0 && self.verify_checksum(block_number)
+    }
+
+    /// Whether the page carries a zero LSN, as every page of an unlogged or temporary
+    /// relation does since their changes are never WAL-logged. LSN based checks should
+    /// skip such pages rather than flag them.
+    ///
+    /// This is ambiguous: a page of a logged relation that was never modified through WAL,
+    /// such as one extended and written during a bulk load with wal_level=minimal, also has
+    /// a zero LSN.
+    pub fn is_unlogged_page(&self) -> bool {
+        self.header_data.pd_lsn.to_u64() == 0
+    }
+
+    /// Whether the page was last changed by a WAL record at or after `checkpoint_lsn`, so
+    /// replay starting from that checkpoint's redo point would touch it. Unlogged pages
+    /// never are, whatever their contents.
+    pub fn modified_after_checkpoint(&self, checkpoint_lsn: u64) -> bool {
+        !self.is_unlogged_page() && self.header_data.pd_lsn.to_u64() >= checkpoint_lsn
+    }
+
+    /// Stores the checksum computed for block `block_number` in `pd_checksum`.
+    pub fn set_checksum(&mut self, block_number: u32) {
+        self.header_data.pd_checksum = self.compute_checksum(block_number);
+    }
+
+    /// The special space at the end of the page, empty for heap pages. Only `pd_special` is
+    /// checked, so a page with corrupt `pd_lower` or `pd_upper` still has its special space read.
+    pub fn special_bytes(&self) -> Result<&[u8], Error> {
+        let header_size = PageHeaderData::byte_size() as usize;
+        let special = self.header_data.pd_special as usize;
+        let page_size = self.header_data.page_size();
+        if special < header_size || special > page_size {
+            return Err(Error::InvalidPageHeaderSpecialOffset(self.header_data.pd_special));
         }
+        Ok(self.data.get_byte_slice(special - header_size, page_size - header_size)?)
+    }
+
+    /// The last `len` bytes of the special space, where some access methods keep
+    /// integrity markers.
+    pub fn special_trailer(&self, len: usize) -> Result<&[u8], Error> {
+        let special = self.special_bytes()?;
+        let start = special.len().checked_sub(len).ok_or(ByteEncodeError::NotEnoughBytes {
+            expected: len,
+            actual: special.len(),
+        })?;
+        Ok(&special[start..])
+    }
+
+    /// Whether this is a sequence relation's page, identified by SEQ_MAGIC in its special space.
+    pub fn is_sequence(&self) -> bool {
+        self.special_bytes()
+            .ok()
+            .and_then(|special| special.get(0..4))
+            .is_some_and(|magic| u32::decode(magic).ok() == Some(SEQ_MAGIC))
     }
 
+    /// Whether every tuple on the page is frozen, the condition the visibility map's
+    /// all-frozen bit promises. Comparing the two finds visibility map corruption.
+    pub fn all_tuples_frozen(&self) -> Result<bool, Error> {
+        for res in self.iter_tuples() {
+            let (_, tuple) = res?;
+            if !tuple.is_frozen() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reserves a normal line pointer and MAXALIGN'd storage for a tuple with `data_size`
+    /// bytes after its 23 byte header, or returns `None` if they don't fit in the free space.
     pub fn reserve_tuple(&mut self, data_size: u16) -> Option<ItemIdData> {
-        let tuple_size = HeapTupleHeaderData::byte_size() + data_size;
-        // TODO: add logic for alignment and null bitmap
-        if self.header_data.pd_upper - self.header_data.pd_lower < tuple_size + ItemPointerData::byte_size() {
+        let tuple_size = (SIZEOF_HEAP_TUPLE_HEADER as u16).checked_add(data_size)?;
+        // TODO: add logic for null bitmap
+        let aligned_size = maxalign(tuple_size as usize);
+        if (self.free_space() as usize) < aligned_size + ItemIdData::byte_size() as usize {
             None
         } else {
-            let mut item_id = ItemIdData::default();
-            item_id.set_lp_off(self.header_data.pd_upper - tuple_size);
-            item_id.set_lp_len(tuple_size);
+            let aligned_size = aligned_size as u16;
+            let item_id = ItemIdData::new(self.header_data.pd_upper - aligned_size, tuple_size, LpFlags::Normal);
             let item_id_bytes = item_id.encode();
 
-            let new_pd_lower = self.header_data.pd_lower + ItemPointerData::byte_size();
+            let header_size = PageHeaderData::byte_size();
+            let lp_start = self.header_data.pd_lower.checked_sub(header_size)? as usize;
+            let new_pd_lower = self.header_data.pd_lower + ItemIdData::byte_size();
 
             // TODO: Handle error differently?
-            self.data.get_byte_slice_mut(self.header_data.pd_lower as usize, new_pd_lower as usize).ok()?.copy_from_slice(&item_id_bytes);
+            self.data.get_byte_slice_mut(lp_start, lp_start + ItemIdData::byte_size() as usize).ok()?.copy_from_slice(&item_id_bytes);
 
             self.header_data.pd_lower = new_pd_lower;
-            self.header_data.pd_upper -= tuple_size;
-            
-            assert!(self.header_data.pd_upper >= self.header_data.pd_lower);
+            self.header_data.pd_upper -= aligned_size;
 
             Some(item_id)
         }
     }
 
-    pub fn vacuum(&mut self) {
-        // let mut new_item_id_data = Vec::new();
-        // let mut new_items = Vec::new();
-        // for (item_id, item) in self.item_id_data.iter().zip(self.items.iter()) {
-        //     if !item.is_dead() {
-        //         new_item_id_data.push(*item_id);
-        //         new_items.push(*item);
-        //     }
-        // }
-        // self.item_id_data = new_item_id_data;
-        // self.items = new_items;
+    /// The contiguous free space between the line pointer array and tuple storage,
+    /// `pd_upper - pd_lower`, or 0 if the header has them crossed.
+    pub fn free_space(&self) -> u16 {
+        self.header_data.pd_upper.saturating_sub(self.header_data.pd_lower)
+    }
+
+    /// Percentage of a page of `page_size` bytes that is not free space: header, line
+    /// pointers, tuple storage and special space.
+    pub fn percent_full(&self, page_size: usize) -> f32 {
+        (page_size.saturating_sub(self.free_space() as usize) as f32 / page_size as f32) * 100.0
+    }
+
+    /// How many more tuples of `tuple_size` bytes, header included, `reserve_tuple` can fit
+    /// in the free space, each taking its MAXALIGN'd size plus a line pointer.
+    pub fn capacity_for(&self, tuple_size: u16) -> u16 {
+        let free = self.free_space() as usize;
+        (free / (maxalign(tuple_size as usize) + ItemIdData::byte_size() as usize)) as u16
+    }
+
+    /// Bytes of tuple storage, between `pd_upper` and `pd_special`, that no normal line
+    /// pointer uses: storage of dead tuples and gaps left by removed ones, which pruning
+    /// and compaction can reclaim, as opposed to the free space between `pd_lower` and
+    /// `pd_upper`. Live tuples are counted at their MAXALIGN'd length.
+    ///
+    /// This covers all of the tuple storage rather than only the span from `pd_upper` up to
+    /// the lowest live `lp_off`. That span misses gaps between live tuples, and since free
+    /// space lies below `pd_upper` there is nothing to subtract from it.
+    pub fn fragmentation_bytes(&self) -> ByteEncodeResult<u16> {
+        let lp_size = ItemIdData::byte_size() as usize;
+        let line_pointers = self.data.get_byte_slice(0, self.line_pointer_count() as usize * lp_size)?;
+        let mut live = 0;
+        for bytes in line_pointers.chunks_exact(lp_size) {
+            let item_id = ItemIdData::decode(bytes)?;
+            if item_id.is_normal() {
+                live += maxalign(item_id.lp_len() as usize);
+            }
+        }
+        let storage = self.header_data.pd_special.saturating_sub(self.header_data.pd_upper) as usize;
+        Ok(storage.saturating_sub(live) as u16)
+    }
+
+    /// Copies `tuple` into the storage reserved for `item_id`, as returned by `reserve_tuple`,
+    /// zero-padding it to the reserved length.
+    pub fn write_tuple(&mut self, item_id: &ItemIdData, tuple: &HeapTupleHeaderData) -> Result<(), Error> {
+        let bytes = tuple.encode_padded(item_id.lp_len())?;
+        let start = Self::data_offset(item_id)?;
+        self.data
+            .get_byte_slice_mut(start, start + bytes.len())?
+            .copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Number of line pointers on the page.
+    pub fn line_pointer_count(&self) -> u16 {
+        self.header_data.pd_lower.saturating_sub(PageHeaderData::byte_size()) / ItemIdData::byte_size()
+    }
+
+    /// The line pointer for a (1-based) offset number.
+    pub fn item_id(&self, offset_number: u16) -> Result<ItemIdData, Error> {
+        let start = Self::line_pointer_start(offset_number)?;
+        let bytes = self.data.get_byte_slice(start, start + ItemIdData::byte_size() as usize)?;
+        Ok(ItemIdData::decode(bytes)?)
+    }
+
+    pub fn set_item_id(&mut self, offset_number: u16, item_id: ItemIdData) -> Result<(), Error> {
+        let start = Self::line_pointer_start(offset_number)?;
+        self.data
+            .get_byte_slice_mut(start, start + ItemIdData::byte_size() as usize)?
+            .copy_from_slice(&item_id.encode());
+        Ok(())
+    }
+
+    /// Where the line pointer for a (1-based) offset number starts in `data`. Offset number
+    /// 0 is InvalidOffsetNumber and has no line pointer.
+    fn line_pointer_start(offset_number: u16) -> Result<usize, Error> {
+        if offset_number == 0 {
+            return Err(Error::InvalidByteEncoding("offset number 0 is invalid".to_string()));
+        }
+        Ok((offset_number as usize - 1) * ItemIdData::byte_size() as usize)
+    }
+
+    /// The raw bytes of the item stored behind a line pointer, whatever the access method.
+    pub fn item_bytes(&self, item_id: &ItemIdData) -> Result<&[u8], Error> {
+        let start = Self::data_offset(item_id)?;
+        Ok(self.data.get_byte_slice(start, start + item_id.lp_len() as usize)?)
+    }
+
+    /// Where the storage a line pointer points at starts in `data`, which begins after the
+    /// page header.
+    fn data_offset(item_id: &ItemIdData) -> Result<usize, Error> {
+        (item_id.lp_off() as usize)
+            .checked_sub(PageHeaderData::byte_size() as usize)
+            .ok_or_else(|| {
+                Error::InvalidByteEncoding(format!(
+                    "line pointer offset {} points into the page header",
+                    item_id.lp_off()
+                ))
+            })
+    }
+
+    /// Every index tuple on the page with its line pointer, in offset number order.
+    ///
+    /// Index pages keep their own data in the special space after `pd_special`, so an item
+    /// is only decoded if it lies between `pd_upper` and `pd_special`; one that doesn't is
+    /// reported as an error rather than read as an index tuple.
+    pub fn iter_index_tuples(&self) -> impl Iterator<Item = Result<(ItemIdData, IndexTupleData), Error>> + '_ {
+        self.iter_line_pointers()
+            .filter(|item_id| item_id.as_ref().map_or(true, |item_id| item_id.is_normal()))
+            .map(move |item_id| {
+                let item_id = item_id?;
+                let (off, len) = (item_id.lp_off(), item_id.lp_len());
+                let header = &self.header_data;
+                if off < header.pd_upper || off as usize + len as usize > header.pd_special as usize {
+                    return Err(Error::InvalidByteEncoding(format!(
+                        "index tuple at {} of length {} lies outside the tuple space {}..{}",
+                        off, len, header.pd_upper, header.pd_special
+                    )));
+                }
+                Ok((item_id, IndexTupleData::decode(self.item_bytes(&item_id)?)?))
+            })
+    }
+
+    /// Whether every tuple on the page encodes back to the exact bytes it was decoded from.
+    /// The tuples are re-encoded into a blank image at their original offsets and each
+    /// line pointer's region is compared with `data`; free space is not looked at.
+    pub fn verify_roundtrip(&self) -> Result<bool, Error> {
+        self.roundtrips_with(HeapTupleHeaderData::encode)
+    }
+
+    /// [`PageLazy::verify_roundtrip`] with the tuples re-encoded by `encode`.
+    fn roundtrips_with(&self, encode: impl Fn(&HeapTupleHeaderData) -> Vec<u8>) -> Result<bool, Error> {
+        let mut image = vec![0; self.data.len()];
+        let mut regions = Vec::new();
+        for res in self.iter_tuples() {
+            let (item_id, tuple) = res?;
+            let bytes = encode(&tuple);
+            if bytes.len() != item_id.lp_len() as usize {
+                return Ok(false);
+            }
+            let start = Self::data_offset(&item_id)?;
+            image.get_byte_slice_mut(start, start + bytes.len())?.copy_from_slice(&bytes);
+            regions.push(start..start + bytes.len());
+        }
+        Ok(regions.into_iter().all(|region| image[region.clone()] == self.data[region]))
+    }
+
+    /// A human-readable dump for debugging: the header fields, every line pointer and a
+    /// hex and ASCII view of the tuple storage between `pd_upper` and `pd_special`, with
+    /// offsets from the start of the page. Bounds that don't fit the page are reported
+    /// rather than followed, so corrupt pages can be dumped too.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.write_dump(&mut out).expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_dump(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        let header = &self.header_data;
+        let header_size = PageHeaderData::byte_size() as usize;
+        writeln!(out, "Header")?;
+        writeln!(out, "  pd_lsn: {:X}/{:X}", header.pd_lsn.xlogid, header.pd_lsn.xrecoff)?;
+        writeln!(out, "  pd_checksum: {:#06x}", header.pd_checksum)?;
+        writeln!(out, "  pd_flags: {:#06x}", header.pd_flags)?;
+        writeln!(out, "  pd_lower: {}", header.pd_lower)?;
+        writeln!(out, "  pd_upper: {}", header.pd_upper)?;
+        writeln!(out, "  pd_special: {}", header.pd_special)?;
+        writeln!(
+            out,
+            "  pd_pagesize_version: {} (size {}, version {})",
+            header.pd_pagesize_version,
+            header.page_size(),
+            header.page_version()
+        )?;
+        writeln!(out, "  pd_prune_xid: {}", header.pd_prune_xid)?;
+
+        writeln!(out, "Line pointers: {}", self.line_pointer_count())?;
+        for offset in 1..=self.line_pointer_count() {
+            match self.item_id(offset) {
+                Ok(item_id) => writeln!(
+                    out,
+                    "  {:>4}: off {:>5} len {:>5} {:?}",
+                    offset,
+                    item_id.lp_off(),
+                    item_id.lp_len(),
+                    item_id.flags()
+                )?,
+                Err(err) => writeln!(out, "  {:>4}: {}", offset, err)?,
+            }
+        }
+
+        let (upper, special) = (header.pd_upper as usize, header.pd_special as usize);
+        writeln!(out, "Tuple storage: {}..{}", upper, special)?;
+        if upper < header_size || upper > special || special > header_size + self.data.len() {
+            return writeln!(out, "  bounds outside the page");
+        }
+        for (i, chunk) in self.data[upper - header_size..special - header_size].chunks(16).enumerate() {
+            write!(out, "  {:04x}:", upper + i * 16)?;
+            for (j, byte) in chunk.iter().enumerate() {
+                write!(out, "{}{:02x}", if j == 8 { "  " } else { " " }, byte)?;
+            }
+            let pad = (16 - chunk.len()) * 3 + usize::from(chunk.len() <= 8);
+            write!(out, "{:pad$}  |", "", pad = pad)?;
+            for byte in chunk {
+                out.push(if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' });
+            }
+            writeln!(out, "|")?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the tuple stored behind a line pointer.
+    pub fn tuple(&self, item_id: &ItemIdData) -> Result<HeapTupleHeaderData, Error> {
+        Ok(HeapTupleHeaderData::decode(self.item_bytes(item_id)?)?)
+    }
+
+    /// Collects the versions of a HOT chain on this page, stored as block `block_number`,
+    /// starting at offset number `start` and following Redirect line pointers and `t_ctid`
+    /// links.
+    ///
+    /// Links are followed only from tuples marked HEAP_HOT_UPDATED, whose successor is
+    /// always on the same page, and only while `t_ctid` points at `block_number`. The walk
+    /// also stops at a tuple pointing to itself, a non-normal line pointer, or a successor
+    /// whose xmin doesn't match the previous version's xmax.
+    pub fn follow_hot_chain(
+        &self,
+        block_number: u32,
+        start: u16,
+    ) -> Result<Vec<(ItemIdData, HeapTupleHeaderData)>, Error> {
+        let nline = self.line_pointer_count();
+        let mut chain = Vec::new();
+        let mut visited = Vec::new();
+        let mut prior_xmax = None;
+        let mut next = start;
+        while next >= 1 && next <= nline && !visited.contains(&next) {
+            visited.push(next);
+            let item_id = self.item_id(next)?;
+            if item_id.is_redirect() {
+                next = item_id.lp_off();
+                continue;
+            }
+            if !item_id.is_normal() {
+                break;
+            }
+            let tuple = self.tuple(&item_id)?;
+            if prior_xmax.is_some_and(|xmax| xmax != tuple.t_xmin) {
+                break;
+            }
+            let ctid = tuple.t_ctid;
+            let hot_updated = tuple.is_hot_updated();
+            prior_xmax = Some(tuple.t_xmax);
+            chain.push((item_id, tuple));
+
+            if !hot_updated || ctid.ip_posid == next || ctid.block_number() != block_number {
+                break;
+            }
+            next = ctid.ip_posid;
+        }
+        Ok(chain)
+    }
+
+    /// Clears the commit hint bits of every tuple and the page checksum, which changes along
+    /// with them, so pages differing only in hint bits compare equal.
+    pub fn clear_hint_bits(&mut self) -> Result<(), Error> {
+        self.header_data.pd_checksum = 0;
+        for offset in 1..=self.line_pointer_count() {
+            let item_id = self.item_id(offset)?;
+            if !item_id.is_normal() {
+                continue;
+            }
+            // t_infomask sits 20 bytes into the tuple header
+            let start = Self::data_offset(&item_id)? + 20;
+            let infomask = self.data.get_byte_slice_mut(start, start + 2)?;
+            let cleared = u16::decode(infomask)? & !HEAP_HINT_BITS;
+            infomask.copy_from_slice(&cleared.encode());
+        }
+        Ok(())
+    }
+
+    /// Whether `other` differs from this page only in tuple hint bits, `pd_lsn` and
+    /// `pd_checksum`, as when a page is dirtied by setting hint bits without any logical
+    /// change. Identical pages count as well. Pages whose line pointers can't be read
+    /// never do.
+    pub fn only_hint_bits_changed(&self, other: &PageLazy) -> bool {
+        let normalize = |page: &PageLazy| {
+            let mut page = page.clone();
+            page.header_data.pd_lsn = PageXLogRecPtr { xlogid: 0, xrecoff: 0 };
+            page.clear_hint_bits().ok().map(|_| page)
+        };
+        match (normalize(self), normalize(other)) {
+            (Some(page), Some(other)) => page == other,
+            _ => false,
+        }
+    }
+
+    /// Prunes HOT chains like heap_page_prune: dead tuples at the start of each chain are
+    /// removed, the chain's root line pointer is redirected to the first surviving version
+    /// (or marked dead if none survive), and the freed storage is compacted.
+    ///
+    /// Deadness is judged by [`HeapTupleHeaderData::is_dead`]. Returns the bytes reclaimed.
+    pub fn prune(&mut self, oldest_xid: u32) -> Result<u16, Error> {
+        let nline = self.line_pointer_count();
+        let mut processed = vec![false; nline as usize + 1];
+
+        for root in 1..=nline {
+            let root_id = self.item_id(root)?;
+            let mut next = match root_id.flags() {
+                LpFlags::Normal => {
+                    if self.tuple(&root_id)?.is_heap_only() {
+                        // heap-only tuples are reached through their chain's root
+                        continue;
+                    }
+                    root
+                }
+                LpFlags::Redirect => root_id.lp_off(),
+                LpFlags::Unused | LpFlags::Dead => continue,
+            };
+
+            let mut chain = Vec::new();
+            let mut latest_dead = None;
+            let mut prior_xmax = None;
+            while next >= 1 && next <= nline && !chain.contains(&next) {
+                let item_id = self.item_id(next)?;
+                if !item_id.is_normal() {
+                    break;
+                }
+                let tuple = self.tuple(&item_id)?;
+                if prior_xmax.is_some_and(|xmax| xmax != tuple.t_xmin) {
+                    break;
+                }
+                chain.push(next);
+                if tuple.is_dead(oldest_xid) {
+                    latest_dead = Some(chain.len() - 1);
+                }
+                if !tuple.is_hot_updated() {
+                    break;
+                }
+                prior_xmax = Some(tuple.t_xmax);
+                next = tuple.t_ctid.ip_posid;
+            }
+            for offset in &chain {
+                processed[*offset as usize] = true;
+            }
+
+            let root_target = match latest_dead {
+                Some(latest) => {
+                    for offset in chain.iter().take(latest + 1).filter(|o| **o != root) {
+                        self.set_item_id(*offset, ItemIdData::default())?;
+                    }
+                    chain.get(latest + 1).copied()
+                }
+                None if chain.is_empty() => None,
+                None => continue,
+            };
+            let mut root_id = ItemIdData::default();
+            match root_target {
+                Some(target) if target != root => {
+                    root_id.set_flags(LpFlags::Redirect);
+                    root_id.set_lp_off(target);
+                }
+                Some(_) => continue,
+                None => root_id.set_flags(LpFlags::Dead),
+            }
+            self.set_item_id(root, root_id)?;
+        }
+
+        // heap-only tuples no chain leads to anymore
+        for offset in 1..=nline {
+            let item_id = self.item_id(offset)?;
+            if processed[offset as usize] || !item_id.is_normal() {
+                continue;
+            }
+            let tuple = self.tuple(&item_id)?;
+            if tuple.is_heap_only() && tuple.is_dead(oldest_xid) {
+                self.set_item_id(offset, ItemIdData::default())?;
+            }
+        }
+
+        let old_free = self.free_space();
+        self.repair_fragmentation()?;
+        Ok(self.free_space().saturating_sub(old_free))
+    }
+
+    /// Compacts the storage of all line pointers that still have some towards the special
+    /// space, like PageRepairFragmentation, and updates pd_upper.
+    ///
+    /// Each tuple is MAXALIGN'd on the way, so a page whose tuples were not aligned can end
+    /// up with less free space than before.
+    fn repair_fragmentation(&mut self) -> Result<(), Error> {
+        let header_size = PageHeaderData::byte_size() as usize;
+        let mut items = Vec::new();
+        let mut has_unused = false;
+        for offset in 1..=self.line_pointer_count() {
+            let item_id = self.item_id(offset)?;
+            if item_id.is_unused() {
+                has_unused = true;
+            }
+            if item_id.lp_len() > 0 && (item_id.is_normal() || item_id.is_dead()) {
+                items.push((offset, item_id));
+            }
+        }
+        // keep the tuples in their physical order, highest first
+        items.sort_by_key(|(_, item_id)| std::cmp::Reverse(item_id.lp_off()));
+
+        // like compactify_tuples, lay the tuples out in a scratch copy of the storage, since
+        // moving them in place can overwrite a tuple before it has been moved
+        let special = self.header_data.pd_special as usize;
+        let mut scratch = vec![0; special.saturating_sub(header_size)];
+        let mut upper = special;
+        for (offset, mut item_id) in items {
+            let start = Self::data_offset(&item_id)?;
+            let len = item_id.lp_len() as usize;
+            let tuple = self.data.get_byte_slice(start, start + len)?;
+            // MAXALIGN'd, as Postgres lays out tuple storage
+            upper = upper
+                .checked_sub(maxalign(len))
+                .filter(|upper| *upper >= self.header_data.pd_lower as usize)
+                .ok_or_else(|| {
+                    Error::InvalidByteEncoding("tuple storage does not fit between pd_lower and pd_special".to_string())
+                })?;
+            scratch[upper - header_size..upper - header_size + len].copy_from_slice(tuple);
+            item_id.set_lp_off(upper as u16);
+            self.set_item_id(offset, item_id)?;
+        }
+        self.data
+            .get_byte_slice_mut(upper - header_size, special - header_size)?
+            .copy_from_slice(&scratch[upper - header_size..]);
+
+        self.header_data.pd_upper = upper as u16;
+        if has_unused {
+            self.header_data.pd_flags |= PD_HAS_FREE_LINES;
+        } else {
+            self.header_data.pd_flags &= !PD_HAS_FREE_LINES;
+        }
+        Ok(())
+    }
+
+    /// Vacuums the page like lazy vacuum's second heap pass: dead line pointers become
+    /// unused, their storage is compacted away, and unused line pointers at the end of the
+    /// array are truncated. Earlier unused pointers stay in place so the offset numbers of
+    /// the remaining tuples don't change. Redirects keep their line pointer.
+    ///
+    /// Returns the bytes reclaimed from tuple storage and the line pointer array.
+    pub fn vacuum(&mut self) -> Result<u16, Error> {
+        let old_free = self.free_space();
+
+        let mut nline = self.line_pointer_count();
+        for offset in 1..=nline {
+            if self.item_id(offset)?.is_dead() {
+                self.set_item_id(offset, ItemIdData::default())?;
+            }
+        }
+        while nline > 0 && self.item_id(nline)?.is_unused() {
+            nline -= 1;
+        }
+        self.header_data.pd_lower = PageHeaderData::byte_size() + nline * ItemIdData::byte_size();
+
+        self.repair_fragmentation()?;
+        Ok(self.free_space().saturating_sub(old_free))
     }
 }
 
+/// Smallest storage a heap tuple can take: a MAXALIGN'd bare header.
+pub const MINIMAL_TUPLE_SIZE: u16 = maxalign(SIZEOF_HEAP_TUPLE_HEADER) as u16;
+
+/// MaxHeapTuplesPerPage: an upper bound on the line pointers of a heap page, assuming every
+/// tuple is at least a MAXALIGN'd bare header.
+pub fn max_heap_tuples_per_page(page_size: u16) -> u16 {
+    page_size.saturating_sub(PageHeaderData::byte_size()) / (ItemIdData::byte_size() + MINIMAL_TUPLE_SIZE)
+}
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct PageLazyTuplesIter<'a> {
     page: &'a PageLazy,
     cursor: u16,
+    ended: bool,
 }
 
-impl Iterator for PageLazyTuplesIter<'_> {
-    type Item = Result<(ItemIdData, HeapTupleHeaderData), Error>;
+impl<'a> PageLazyTuplesIter<'a> {
+    /// Offset number (1-based line pointer index) of the last tuple returned by `next`.
+    pub fn offset_number(&self) -> u16 {
+        self.cursor / ItemIdData::byte_size()
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor >= (self.page.header_data.pd_lower - PageHeaderData::byte_size()) {
-            None
-        } else {
-            let item_id_bytes = match self.page.data.get_byte_slice(self.cursor as usize, (self.cursor + ItemIdData::byte_size()) as usize) {
+    /// Advances to the next normal line pointer and returns it with the bytes it points to.
+    fn next_item(&mut self) -> Option<Result<(ItemIdData, &'a [u8]), Error>> {
+        if self.ended {
+            return None;
+        }
+        let page = self.page;
+        let header = &page.header_data;
+        if header.pd_lower < PageHeaderData::byte_size()
+            || header.pd_lower as usize > header.page_size()
+            || page.line_pointer_count() > max_heap_tuples_per_page(header.page_size() as u16)
+        {
+            self.ended = true;
+            return Some(Err(Error::InvalidPageHeaderLowerBound(header.pd_lower)));
+        }
+
+        loop {
+            if self.cursor >= (header.pd_lower - PageHeaderData::byte_size()) {
+                return None;
+            }
+            let item_id_bytes = match page.data.get_byte_slice(self.cursor as usize, (self.cursor + ItemIdData::byte_size()) as usize) {
                 Ok(item_id) => item_id,
                 Err(err) => return Some(Err(err.into())),
             };
@@ -79,24 +798,779 @@ impl Iterator for PageLazyTuplesIter<'_> {
                 Ok(item_id) => item_id,
                 Err(err) => return Some(Err(err.into())),
             };
+            self.cursor += ItemIdData::byte_size();
 
-            // TODO: Handle redirect and dead items
+            // only normal line pointers have tuple storage; see iter_line_pointers for the rest
             if !item_id.is_normal() {
-                self.cursor += ItemIdData::byte_size();
-                // TODO: Avoid recursion
-                return self.next();
+                continue;
             }
-            let real_offset = item_id.lp_off() - PageHeaderData::byte_size();
-            let item_bytes = match self.page.data.get_byte_slice(real_offset as usize, (real_offset + item_id.lp_len()) as usize) {
-                Ok(item) => item,
-                Err(err) => return Some(Err(err.into())),
-            };
-            let item = match HeapTupleHeaderData::decode(item_bytes) {
-                Ok(item) => item,
-                Err(err) => return Some(Err(err.into())),
-            };
-            self.cursor += ItemIdData::byte_size();
-            Some(Ok((item_id, item)))
+            return Some(page.item_bytes(&item_id).map(|item| (item_id, item)));
         }
     }
-}
\ No newline at end of file
+}
+
+impl Iterator for PageLazyTuplesIter<'_> {
+    type Item = Result<(ItemIdData, HeapTupleHeaderData), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_item().map(|item| {
+            let (item_id, bytes) = item?;
+            Ok((item_id, HeapTupleHeaderData::decode(bytes)?))
+        })
+    }
+}
+
+/// Like [`PageLazyTuplesIter`], but the tuples borrow their payload from the page.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct PageLazyTupleRefsIter<'a> {
+    inner: PageLazyTuplesIter<'a>,
+}
+
+impl PageLazyTupleRefsIter<'_> {
+    /// Offset number (1-based line pointer index) of the last tuple returned by `next`.
+    pub fn offset_number(&self) -> u16 {
+        self.inner.offset_number()
+    }
+}
+
+impl<'a> Iterator for PageLazyTupleRefsIter<'a> {
+    type Item = Result<(ItemIdData, HeapTupleHeaderRef<'a>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next_item().map(|item| {
+            let (item_id, bytes) = item?;
+            Ok((item_id, HeapTupleHeaderRef::decode(bytes)?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, page_lazy, tuple, BTREE_LEAF_PAGE, HEAP_PAGE};
+
+    fn hot_tuple(t_xmin: u32, t_xmax: u32, next: u16, t_infomask2: u16, t_infomask: u16) -> HeapTupleHeaderData {
+        let mut t = tuple(t_xmin, vec![0]);
+        t.t_xmax = t_xmax;
+        t.t_ctid.ip_posid = next;
+        t.t_infomask2 = t_infomask2 | 1;
+        t.t_infomask = HEAP_XMIN_COMMITTED | t_infomask;
+        t
+    }
+
+    #[test]
+    fn test_prune_hot_chain() {
+        let committed = HEAP_XMAX_COMMITTED;
+        let mut page = page_lazy(&page_bytes(&[
+            hot_tuple(5, 10, 2, HEAP_HOT_UPDATED, committed),
+            hot_tuple(10, 11, 3, HEAP_HOT_UPDATED | HEAP_ONLY_TUPLE, committed),
+            hot_tuple(11, 0, 3, HEAP_ONLY_TUPLE, HEAP_XMAX_INVALID),
+        ]));
+        let old_upper = page.header_data.pd_upper;
+
+        let freed = page.prune(100).unwrap();
+
+        assert_eq!(freed, 48);
+        assert_eq!(page.header_data.pd_upper, old_upper + 48);
+        let root = page.item_id(1).unwrap();
+        assert!(root.is_redirect());
+        assert_eq!(root.lp_off(), 3);
+        assert!(page.item_id(2).unwrap().is_unused());
+        assert_eq!(page.header_data.pd_flags & PD_HAS_FREE_LINES, PD_HAS_FREE_LINES);
+
+        let live = page.item_id(3).unwrap();
+        assert!(live.is_normal());
+        assert_eq!(live.lp_off(), 8192 - 24);
+        assert_eq!(page.tuple(&live).unwrap().t_xmin, 11);
+        let tuples = page.iter_tuples().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(tuples.len(), 1);
+    }
+
+    #[test]
+    fn test_prune_keeps_recent_versions() {
+        let committed = HEAP_XMAX_COMMITTED;
+        let mut page = page_lazy(&page_bytes(&[
+            hot_tuple(5, 10, 2, HEAP_HOT_UPDATED, committed),
+            hot_tuple(10, 0, 2, HEAP_ONLY_TUPLE, HEAP_XMAX_INVALID),
+        ]));
+        let before = page.clone();
+
+        assert_eq!(page.prune(10).unwrap(), 0);
+        assert!(page.item_id(1).unwrap().is_normal());
+        assert_eq!(page.item_id(1).unwrap(), before.item_id(1).unwrap());
+    }
+
+    #[test]
+    fn test_prune_dead_chain_marks_root_dead() {
+        let mut page = page_lazy(&page_bytes(&[
+            hot_tuple(5, 10, 1, 0, HEAP_XMAX_COMMITTED),
+            hot_tuple(6, 0, 2, 0, HEAP_XMAX_INVALID),
+        ]));
+
+        assert_eq!(page.prune(100).unwrap(), 24);
+        let root = page.item_id(1).unwrap();
+        assert!(root.is_dead());
+        assert_eq!(root.lp_len(), 0);
+        assert_eq!(page.tuple(&page.item_id(2).unwrap()).unwrap().t_xmin, 6);
+    }
+
+    #[test]
+    fn test_prune_unaligned_live_tuples() {
+        // 25 byte tuples, which page_bytes packs without MAXALIGN padding
+        let tuples = vec![tuple(1, vec![1, 2]), tuple(2, vec![3, 4])];
+        let mut page = page_lazy(&page_bytes(&tuples));
+        assert_eq!(page.header_data.pd_upper, 8192 - 50);
+
+        // realigning the storage costs free space rather than reclaiming any
+        assert_eq!(page.prune(100).unwrap(), 0);
+        assert_eq!(page.header_data.pd_upper, 8192 - 64);
+        let live: Vec<_> = page.iter_tuples().map(Result::unwrap).collect();
+        assert_eq!((live[0].0.lp_off(), live[1].0.lp_off()), (8192 - 32, 8192 - 64));
+        assert_eq!(live.into_iter().map(|(_, tuple)| tuple).collect::<Vec<_>>(), tuples);
+    }
+
+    #[test]
+    fn test_prune_line_pointer_into_header() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![1])]));
+        page.set_item_id(1, ItemIdData::new(8, 24, LpFlags::Dead)).unwrap();
+        assert!(matches!(page.prune(100), Err(Error::InvalidByteEncoding(_))));
+    }
+
+    #[test]
+    fn test_offset_number_zero() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![1])]));
+        assert!(matches!(page.item_id(0), Err(Error::InvalidByteEncoding(_))));
+        assert!(matches!(
+            page.set_item_id(0, ItemIdData::default()),
+            Err(Error::InvalidByteEncoding(_))
+        ));
+        assert!(page.item_id(1).unwrap().is_normal());
+    }
+
+    #[test]
+    fn test_iter_tuples_impossible_line_pointer_count() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![1])]));
+        // 400 line pointers can't fit on an 8KB heap page
+        page.header_data.pd_lower = 24 + 400 * 4;
+        page.header_data.pd_upper = 8192 - 24;
+
+        let mut iter = page.iter_tuples();
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::InvalidPageHeaderLowerBound(1624)))
+        ));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_tuples_lower_beyond_page() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![1])]));
+        page.header_data.pd_pagesize_version = 1024 | 4;
+        page.header_data.pd_lower = 2000;
+
+        let mut iter = page.iter_tuples();
+        assert!(matches!(iter.next(), Some(Err(Error::InvalidPageHeaderLowerBound(2000)))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_checksum_known_answer() {
+        let page = page_lazy(HEAP_PAGE);
+        assert_eq!(page.header_data.pd_checksum, 0x7F12);
+        assert_eq!(page.compute_checksum(0), 0x7F12);
+        assert!(page.verify_checksum(0));
+        assert!(!page.verify_checksum(1));
+    }
+
+    #[test]
+    fn test_set_checksum() {
+        let mut page = page_lazy(HEAP_PAGE);
+        page.data[1000] ^= 0xFF;
+        assert!(!page.verify_checksum(0));
+        page.set_checksum(0);
+        assert!(page.verify_checksum(0));
+        assert_ne!(page.header_data.pd_checksum, 0x7F12);
+
+        page.set_checksum(7);
+        assert!(page.verify_checksum(7));
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut page = page_lazy(&page_bytes(&[tuple(7, b"hello, page!".to_vec()), tuple(8, vec![0xFF; 3])]));
+        page.header_data.pd_lsn = PageXLogRecPtr {
+            xlogid: 1,
+            xrecoff: 0x17B_0450,
+        };
+        let dump = page.dump();
+        assert!(dump.starts_with(
+            "Header
+  pd_lsn: 1/17B0450
+  pd_checksum: 0x0000
+  pd_flags: 0x0000
+  pd_lower: 32
+  pd_upper: 8131
+  pd_special: 8192
+  pd_pagesize_version: 8196 (size 8192, version 4)
+  pd_prune_xid: 0
+Line pointers: 2
+     1: off  8157 len    35 Normal
+     2: off  8131 len    26 Normal
+Tuple storage: 8131..8192
+"
+        ));
+        assert!(dump.contains("\n  1fd3: 00 00 01 00 00 00 18 ff  ff ff 07 00 00 00 00 00  |................|\n"));
+        assert!(dump.ends_with("\n  1ff3: 18 68 65 6c 6c 6f 2c 20  70 61 67 65 21           |.hello, page!|\n"));
+    }
+
+    #[test]
+    fn test_dump_corrupt_bounds() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        page.header_data.pd_upper = 9000;
+        assert!(page.dump().ends_with("Tuple storage: 9000..8192\n  bounds outside the page\n"));
+    }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        assert!(page_lazy(HEAP_PAGE).verify_roundtrip().unwrap());
+        let built = page_lazy(&page_bytes(&[tuple(1, vec![1, 2, 3]), tuple(2, vec![4; 40])]));
+        assert!(built.verify_roundtrip().unwrap());
+        assert!(page_lazy(&page_bytes(&[])).verify_roundtrip().unwrap());
+    }
+
+    #[test]
+    fn test_verify_roundtrip_asymmetric_encoding() {
+        let page = page_lazy(&page_bytes(&[tuple(1, vec![1, 2, 3]), tuple(2, vec![4; 40])]));
+        // an encoder that gets t_ctid wrong, as the old ItemPointerData encoding did
+        let bad_ctid = |tuple: &HeapTupleHeaderData| {
+            let mut tuple = tuple.clone();
+            tuple.t_ctid.ip_blkid.bi_lo += 1;
+            tuple.encode()
+        };
+        assert!(!page.roundtrips_with(bad_ctid).unwrap());
+        // one that drops a byte, so the tuple no longer fills its line pointer
+        let short = |tuple: &HeapTupleHeaderData| {
+            let mut bytes = tuple.encode();
+            bytes.pop();
+            bytes
+        };
+        assert!(!page.roundtrips_with(short).unwrap());
+        assert!(page.roundtrips_with(HeapTupleHeaderData::encode).unwrap());
+    }
+
+    #[test]
+    fn test_verify_roundtrip_line_pointer_into_header() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![1, 2, 3])]));
+        page.set_item_id(1, ItemIdData::new(8, 26, LpFlags::Normal)).unwrap();
+        assert!(matches!(page.verify_roundtrip(), Err(Error::InvalidByteEncoding(_))));
+    }
+
+    #[test]
+    fn test_is_unlogged_page() {
+        assert!(page_lazy(&page_bytes(&[tuple(1, vec![1])])).is_unlogged_page());
+        let page = page_lazy(HEAP_PAGE);
+        assert_eq!(page.header_data.pd_lsn.to_u64(), 0x17B_0450);
+        assert!(!page.is_unlogged_page());
+    }
+
+    #[test]
+    fn test_modified_after_checkpoint() {
+        let page = page_lazy(HEAP_PAGE);
+        assert!(page.modified_after_checkpoint(0x17B_0450));
+        assert!(page.modified_after_checkpoint(0x100_0000));
+        assert!(!page.modified_after_checkpoint(0x17B_0451));
+        assert!(!page_lazy(&page_bytes(&[])).modified_after_checkpoint(0));
+    }
+
+    #[test]
+    fn test_checksum_is_plausible() {
+        let page = page_lazy(HEAP_PAGE);
+        assert!(page.checksum_is_plausible(0));
+        assert!(!page.checksum_is_plausible(1));
+
+        let mut corrupted = page.clone();
+        corrupted.data[1000] ^= 0xFF;
+        assert!(!corrupted.checksum_is_plausible(0));
+
+        let mut unchecksummed = page_lazy(&page_bytes(&[tuple(1, vec![1])]));
+        assert_eq!(unchecksummed.header_data.pd_checksum, 0);
+        assert_ne!(unchecksummed.compute_checksum(0), 0);
+        assert!(!unchecksummed.checksum_is_plausible(0));
+        unchecksummed.set_checksum(0);
+        assert!(unchecksummed.checksum_is_plausible(0));
+    }
+
+    #[test]
+    fn test_special_bytes() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        assert!(page.special_bytes().unwrap().is_empty());
+
+        page.header_data.pd_upper = 8192 - 16;
+        page.header_data.pd_special = 8192 - 16;
+        page.data[8192 - 16 - 24..].copy_from_slice(&[7; 16]);
+        assert_eq!(page.special_bytes().unwrap(), &[7; 16]);
+    }
+
+    #[test]
+    fn test_special_beyond_page_size() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        page.header_data.pd_pagesize_version = 4096 | 4;
+        assert!(matches!(
+            page.header_data.validate(page.header_data.page_size()),
+            Err(Error::InvalidPageHeaderSpecialOffset(8192))
+        ));
+        assert!(matches!(
+            page.special_bytes(),
+            Err(Error::InvalidPageHeaderSpecialOffset(8192))
+        ));
+    }
+
+    #[test]
+    fn test_special_bytes_ignores_lower_and_upper() {
+        let mut page = page_lazy(BTREE_LEAF_PAGE);
+        page.header_data.pd_lower = page.header_data.pd_upper + 8;
+        assert!(page.header_data.validate(8192).is_err());
+        assert_eq!(page.special_bytes().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip() {
+        let page = PageLazy::from_reader(&mut std::io::Cursor::new(HEAP_PAGE)).unwrap();
+        assert_eq!(page.to_bytes(), HEAP_PAGE);
+
+        let mut written = vec![];
+        page.encode_into_writer(&mut written).unwrap();
+        assert_eq!(written, HEAP_PAGE);
+    }
+
+    #[test]
+    fn test_from_reader_zero_page() {
+        assert!(matches!(
+            PageLazy::from_reader(&mut std::io::Cursor::new(vec![0; 8192])),
+            Err(ByteEncodeError::InvalidSize { expected: 24, actual: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_vacuum() {
+        // 23 byte header + 9 bytes of data keeps every tuple MAXALIGN'd
+        let tuples: Vec<_> = (1..=5).map(|xmin| tuple(xmin, vec![xmin as u8; 9])).collect();
+        let mut page = page_lazy(&page_bytes(&tuples));
+        assert_eq!(page.header_data.pd_upper, 8192 - 5 * 32);
+
+        for offset in [2, 5] {
+            let mut item_id = page.item_id(offset).unwrap();
+            item_id.set_flags(LpFlags::Dead);
+            page.set_item_id(offset, item_id).unwrap();
+        }
+        let mut redirect = ItemIdData::default();
+        redirect.set_flags(LpFlags::Redirect);
+        redirect.set_lp_off(3);
+        page.set_item_id(4, redirect).unwrap();
+
+        // two dead tuples and the redirected one lose their storage, one trailing pointer goes
+        assert_eq!(page.vacuum().unwrap(), 3 * 32 + 4);
+        assert_eq!(page.line_pointer_count(), 4);
+        assert_eq!(page.header_data.pd_lower, 24 + 4 * 4);
+        assert_eq!(page.header_data.pd_upper, 8192 - 2 * 32);
+        assert!(page.header_data.has_free_line_pointers());
+
+        assert!(page.item_id(2).unwrap().is_unused());
+        assert_eq!(page.item_id(4).unwrap(), redirect);
+        let live: Vec<_> = [1, 3]
+            .iter()
+            .map(|offset| page.item_id(*offset).unwrap())
+            .collect();
+        assert_eq!(live[0].lp_off(), 8192 - 32);
+        assert_eq!(live[1].lp_off(), 8192 - 64);
+        assert_eq!(page.tuple(&live[0]).unwrap(), tuples[0]);
+        assert_eq!(page.tuple(&live[1]).unwrap(), tuples[2]);
+    }
+
+    #[test]
+    fn test_vacuum_keeps_surviving_tuples() {
+        // 25 and 26 byte tuples, packed unaligned, so compaction has to move every survivor
+        let tuples: Vec<_> = (1..=6)
+            .map(|xmin| tuple(xmin, vec![xmin as u8 * 10; 2 + xmin as usize % 2]))
+            .collect();
+        let mut page = page_lazy(&page_bytes(&tuples));
+        for offset in [3, 4] {
+            let mut item_id = page.item_id(offset).unwrap();
+            item_id.set_flags(LpFlags::Dead);
+            page.set_item_id(offset, item_id).unwrap();
+        }
+
+        page.vacuum().unwrap();
+        assert!(page.item_id(3).unwrap().is_unused());
+        assert!(page.item_id(4).unwrap().is_unused());
+        for offset in [1, 2, 5, 6] {
+            let item_id = page.item_id(offset).unwrap();
+            assert_eq!(item_id.lp_off() % 8, 0);
+            assert_eq!(page.item_bytes(&item_id).unwrap(), tuples[offset as usize - 1].encode());
+        }
+        assert_eq!(page.header_data.pd_upper, 8192 - 4 * 32);
+        assert_eq!(page.fragmentation_bytes().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_follow_hot_chain() {
+        let committed = HEAP_XMAX_COMMITTED;
+        let mut page = page_lazy(&page_bytes(&[
+            hot_tuple(5, 10, 2, HEAP_HOT_UPDATED, committed),
+            hot_tuple(10, 0, 2, HEAP_ONLY_TUPLE, HEAP_XMAX_INVALID),
+            hot_tuple(7, 0, 3, 0, HEAP_XMAX_INVALID),
+        ]));
+
+        let chain = page.follow_hot_chain(0, 1).unwrap();
+        let xmins: Vec<_> = chain.iter().map(|(_, tuple)| tuple.t_xmin).collect();
+        assert_eq!(xmins, vec![5, 10]);
+        assert_eq!(chain[1].0, page.item_id(2).unwrap());
+
+        assert_eq!(page.follow_hot_chain(0, 3).unwrap().len(), 1);
+        assert!(page.follow_hot_chain(0, 4).unwrap().is_empty());
+
+        // a redirected root leads straight to the surviving version
+        let mut redirect = ItemIdData::default();
+        redirect.set_flags(LpFlags::Redirect);
+        redirect.set_lp_off(2);
+        page.set_item_id(1, redirect).unwrap();
+        let chain = page.follow_hot_chain(0, 1).unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].1.t_xmin, 10);
+    }
+
+    #[test]
+    fn test_follow_hot_chain_other_block() {
+        let mut first = hot_tuple(5, 10, 2, HEAP_HOT_UPDATED, HEAP_XMAX_COMMITTED);
+        first.t_ctid.ip_blkid.bi_lo = 1;
+        let mut second = hot_tuple(10, 11, 3, HEAP_HOT_UPDATED | HEAP_ONLY_TUPLE, HEAP_XMAX_COMMITTED);
+        second.t_ctid.ip_blkid.bi_lo = 2;
+        let third = hot_tuple(11, 0, 3, HEAP_ONLY_TUPLE, HEAP_XMAX_INVALID);
+        let page = page_lazy(&page_bytes(&[first, second, third]));
+
+        // stored as block 1, the chain leaves the page after the second version
+        assert_eq!(page.follow_hot_chain(1, 1).unwrap().len(), 2);
+        // stored as block 0, even the first link points elsewhere
+        assert_eq!(page.follow_hot_chain(0, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_iter_tuple_refs() {
+        let page = page_lazy(HEAP_PAGE);
+        let owned: Vec<_> = page.iter_tuples().map(Result::unwrap).collect();
+        let refs: Vec<_> = page.iter_tuple_refs().map(Result::unwrap).collect();
+        assert_eq!(owned.len(), 40);
+        assert_eq!(refs.len(), owned.len());
+        for ((item_id, tuple), (ref_item_id, tuple_ref)) in owned.iter().zip(&refs) {
+            assert_eq!(item_id, ref_item_id);
+            assert_eq!(&tuple_ref.to_header_data(), tuple);
+        }
+    }
+
+    #[test]
+    fn test_iter_tuples_skips_non_normal() {
+        let tuples: Vec<_> = (1..=5).map(|xmin| tuple(xmin, vec![0])).collect();
+        let mut page = page_lazy(&page_bytes(&tuples));
+        let mut dead = page.item_id(2).unwrap();
+        dead.set_flags(LpFlags::Dead);
+        page.set_item_id(2, dead).unwrap();
+        let mut redirect = ItemIdData::default();
+        redirect.set_flags(LpFlags::Redirect);
+        redirect.set_lp_off(5);
+        page.set_item_id(3, redirect).unwrap();
+        page.set_item_id(4, ItemIdData::default()).unwrap();
+
+        let mut iter = page.iter_tuples();
+        let (_, first) = iter.next().unwrap().unwrap();
+        assert_eq!((first.t_xmin, iter.offset_number()), (1, 1));
+        let (_, last) = iter.next().unwrap().unwrap();
+        assert_eq!((last.t_xmin, iter.offset_number()), (5, 5));
+        assert!(iter.next().is_none());
+
+        let flags: Vec<_> = page.iter_line_pointers().map(|item_id| item_id.unwrap().flags()).collect();
+        assert_eq!(
+            flags,
+            vec![LpFlags::Normal, LpFlags::Dead, LpFlags::Redirect, LpFlags::Unused, LpFlags::Normal]
+        );
+    }
+
+    #[test]
+    fn test_reserve_tuple_normal() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![0])]));
+        let item_id = page.reserve_tuple(32).unwrap();
+        assert_eq!(item_id.flags(), LpFlags::Normal);
+        assert_eq!(page.line_pointer_count(), 2);
+        assert_eq!(page.item_id(2).unwrap(), item_id);
+        assert_eq!(page.header_data.pd_upper, item_id.lp_off());
+    }
+
+    #[test]
+    fn test_max_heap_tuples_per_page() {
+        assert_eq!(max_heap_tuples_per_page(8192), 291);
+        assert_eq!(max_heap_tuples_per_page(32768), 1169);
+        assert_eq!(max_heap_tuples_per_page(0), 0);
+    }
+
+    #[test]
+    fn test_all_tuples_frozen() {
+        let frozen = |xmin| {
+            let mut tuple = tuple(xmin, vec![1]);
+            tuple.t_infomask = HEAP_XMIN_FROZEN | HEAP_XMAX_INVALID;
+            tuple
+        };
+        assert!(page_lazy(&page_bytes(&[frozen(10), frozen(11)])).all_tuples_frozen().unwrap());
+        assert!(!page_lazy(&page_bytes(&[frozen(10), tuple(11, vec![1])])).all_tuples_frozen().unwrap());
+        assert!(page_lazy(&page_bytes(&[])).all_tuples_frozen().unwrap());
+    }
+
+    #[test]
+    fn test_capacity_for() {
+        for data_size in [0, 4, 5, 37, 1977] {
+            let tuple_size = 23 + data_size;
+            let mut page = page_lazy(&page_bytes(&[tuple(1, vec![0; 5])]));
+            let capacity = page.capacity_for(tuple_size);
+            for _ in 0..capacity {
+                assert!(page.reserve_tuple(data_size).is_some());
+            }
+            assert!(page.reserve_tuple(data_size).is_none());
+            assert_eq!(page.capacity_for(tuple_size), 0);
+        }
+        // 8192 - 24 - 4 - 28 = 8136 bytes free, 36 per tuple of 28 bytes
+        assert_eq!(page_lazy(&page_bytes(&[tuple(1, vec![0; 5])])).capacity_for(28), 226);
+    }
+
+    #[test]
+    fn test_write_tuple() {
+        let mut page = page_lazy(&page_bytes(&[tuple(1, vec![0])]));
+        let new_tuple = tuple(2, vec![0, 1, 2, 3, 4]);
+        let item_id = page.reserve_tuple(new_tuple.data.len() as u16).unwrap();
+        assert_eq!(item_id.lp_len() as usize, new_tuple.encode().len());
+        page.write_tuple(&item_id, &new_tuple).unwrap();
+        assert_eq!(page.item_bytes(&item_id).unwrap(), new_tuple.encode());
+
+        let tuples: Vec<_> = page.iter_tuples().map(Result::unwrap).collect();
+        assert_eq!(tuples.len(), 2);
+        assert_eq!(tuples[1], (item_id, new_tuple));
+    }
+
+    #[test]
+    fn test_free_space() {
+        let empty = PageLazy::new_empty(8192);
+        assert_eq!(empty.free_space(), 8192 - 24);
+        assert_eq!(empty.percent_full(8192), 24.0 / 8192.0 * 100.0);
+
+        // four 32 byte tuples and their line pointers
+        let tuples: Vec<_> = (1..=4).map(|xmin| tuple(xmin, vec![0; 9])).collect();
+        let page = page_lazy(&page_bytes(&tuples));
+        assert_eq!(page.free_space(), 8192 - 24 - 4 * 4 - 4 * 32);
+        assert_eq!(page.percent_full(8192), 168.0 / 8192.0 * 100.0);
+
+        let mut crossed = page;
+        crossed.header_data.pd_lower = crossed.header_data.pd_upper + 4;
+        assert_eq!(crossed.free_space(), 0);
+        assert_eq!(crossed.percent_full(8192), 100.0);
+    }
+
+    #[test]
+    fn test_fragmentation_bytes() {
+        // 23 + 9 bytes each, 32 once aligned
+        let tuples: Vec<_> = (1..=4).map(|xmin| tuple(xmin, vec![0; 9])).collect();
+        let mut page = page_lazy(&page_bytes(&tuples));
+        assert_eq!(page.fragmentation_bytes().unwrap(), 0);
+
+        // a deleted tuple whose line pointer was removed leaves a gap
+        page.set_item_id(2, ItemIdData::default()).unwrap();
+        assert_eq!(page.fragmentation_bytes().unwrap(), 32);
+        // a dead line pointer that still has storage counts as well
+        let mut dead = page.item_id(3).unwrap();
+        dead.set_flags(LpFlags::Dead);
+        page.set_item_id(3, dead).unwrap();
+        assert_eq!(page.fragmentation_bytes().unwrap(), 64);
+
+        let free = page.free_space();
+        page.vacuum().unwrap();
+        assert_eq!(page.fragmentation_bytes().unwrap(), 0);
+        assert_eq!(page.free_space(), free + 64);
+        assert_eq!(PageLazy::new_empty(8192).fragmentation_bytes().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_new_empty() {
+        let mut page = PageLazy::new_empty(8192);
+        assert_eq!(page.to_bytes(), page_bytes(&[]));
+        assert_eq!(page.header_data.page_size(), 8192);
+        assert_eq!(page.header_data.page_version(), PG_PAGE_LAYOUT_VERSION);
+        assert_eq!(page.line_pointer_count(), 0);
+
+        let new_tuple = tuple(2, vec![1, 2, 3, 4, 5]);
+        let item_id = page.reserve_tuple(new_tuple.data.len() as u16).unwrap();
+        page.write_tuple(&item_id, &new_tuple).unwrap();
+        // 23 byte header + 5 bytes of data in a 32 byte slot
+        assert_eq!((item_id.lp_off(), item_id.lp_len()), (8192 - 32, 28));
+        assert_eq!(page.header_data.pd_lower, 24 + 4);
+        let tuples: Vec<_> = page.iter_tuples().map(Result::unwrap).collect();
+        assert_eq!(tuples, vec![(item_id, new_tuple)]);
+
+        let small = PageLazy::new_empty(1024);
+        assert_eq!((small.header_data.pd_upper, small.data.len()), (1024, 1000));
+        // every size validate accepts can be built, including ones that aren't powers of two
+        for page_size in [256, 768, 32768] {
+            let page = PageLazy::new_empty(page_size);
+            assert!(page.header_data.validate(page_size).is_ok());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid page size 8000")]
+    fn test_new_empty_invalid_size() {
+        PageLazy::new_empty(8000);
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let page = page_lazy(HEAP_PAGE);
+        let rebuilt = PageLazy::from_parts(page.header_data, page.data.clone()).unwrap();
+        assert_eq!(rebuilt.to_bytes(), HEAP_PAGE);
+    }
+
+    #[test]
+    fn test_from_parts_wrong_length() {
+        let page = page_lazy(HEAP_PAGE);
+        assert!(matches!(
+            PageLazy::from_parts(page.header_data, vec![0; 100]),
+            Err(Error::ByteEncoding(ByteEncodeError::InvalidSize { expected: 8168, actual: 100 }))
+        ));
+        assert!(matches!(
+            PageLazy::from_parts(page.header_data, vec![0; 8192]),
+            Err(Error::ByteEncoding(ByteEncodeError::InvalidSize { expected: 8168, actual: 8192 }))
+        ));
+    }
+
+    #[test]
+    fn test_from_parts_bad_header() {
+        let page = page_lazy(HEAP_PAGE);
+        let mut header = page.header_data;
+        header.pd_lower = header.pd_upper + 8;
+        assert!(matches!(
+            PageLazy::from_parts(header, page.data.clone()),
+            Err(Error::InvalidPageHeaderLowerBound(_))
+        ));
+
+        let mut header = page.header_data;
+        header.pd_special = header.pd_upper - 8;
+        assert!(matches!(
+            PageLazy::from_parts(header, page.data.clone()),
+            Err(Error::InvalidPageHeaderSpecialBound(special)) if special == header.pd_special
+        ));
+    }
+
+    #[test]
+    fn test_write_tuple_too_long() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        let item_id = page.reserve_tuple(1).unwrap();
+        assert!(matches!(
+            page.write_tuple(&item_id, &tuple(2, vec![0; 30])),
+            Err(Error::ByteEncoding(ByteEncodeError::TooManyBytes { expected: 24, actual: 53 }))
+        ));
+    }
+
+    #[test]
+    fn test_write_tuple_padded() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        let item_id = page.reserve_tuple(8).unwrap();
+        page.write_tuple(&item_id, &tuple(2, vec![7, 7])).unwrap();
+        let (_, written) = page.iter_tuples().next().unwrap().unwrap();
+        assert_eq!(written.data, vec![7, 7, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_iter_index_tuples() {
+        let page = page_lazy(BTREE_LEAF_PAGE);
+        let keys: Vec<_> = page
+            .iter_index_tuples()
+            .map(|res| i32::decode(&res.unwrap().1.key_payload()[..4]).unwrap())
+            .collect();
+        assert_eq!(keys, (1..=40).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_iter_index_tuples_special_space() {
+        let mut page = page_lazy(BTREE_LEAF_PAGE);
+        let special = page.header_data.pd_special;
+        // one item inside the special space, one running into it
+        page.set_item_id(1, ItemIdData::new(special, 16, LpFlags::Normal)).unwrap();
+        page.set_item_id(2, ItemIdData::new(special - 8, 16, LpFlags::Normal)).unwrap();
+
+        let items: Vec<_> = page.iter_index_tuples().collect();
+        assert_eq!(items.len(), 40);
+        assert!(matches!(items[0], Err(Error::InvalidByteEncoding(_))));
+        assert!(matches!(items[1], Err(Error::InvalidByteEncoding(_))));
+        assert!(items[2..].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_special_trailer() {
+        let page = page_lazy(BTREE_LEAF_PAGE);
+        // BTPageOpaqueData ends with btpo_flags (BTP_LEAF | BTP_ROOT) and btpo_cycleid
+        assert_eq!(page.special_trailer(4).unwrap(), &[3, 0, 0, 0]);
+        assert_eq!(page.special_trailer(16).unwrap().len(), 16);
+        assert_eq!(page.special_trailer(0).unwrap(), &[] as &[u8]);
+        assert!(matches!(
+            page.special_trailer(17),
+            Err(Error::ByteEncoding(ByteEncodeError::NotEnoughBytes { expected: 17, actual: 16 }))
+        ));
+    }
+
+    #[test]
+    fn test_reserve_tuple_maxaligned() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        // 23 byte header + 5 bytes of data in a 32 byte slot
+        let first = page.reserve_tuple(5).unwrap();
+        let second = page.reserve_tuple(5).unwrap();
+        assert_eq!((first.lp_off(), first.lp_len()), (8192 - 32, 28));
+        assert_eq!((second.lp_off(), second.lp_len()), (8192 - 64, 28));
+        assert_eq!(page.header_data.pd_upper, 8192 - 64);
+    }
+
+    #[test]
+    fn test_reserve_tuple_full_page() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        // 8192 - 24 bytes free can't hold an 8168 byte slot and its line pointer
+        assert!(page.reserve_tuple(8168 - 23).is_none());
+        assert!(page.reserve_tuple(u16::MAX).is_none());
+        assert!(page.reserve_tuple(8160 - 23).is_some());
+        assert_eq!(page.free_space(), 4);
+        assert!(page.reserve_tuple(0).is_none());
+
+        let mut crossed = page_lazy(&page_bytes(&[]));
+        crossed.header_data.pd_lower = crossed.header_data.pd_upper + 4;
+        assert!(crossed.reserve_tuple(0).is_none());
+    }
+
+    #[test]
+    fn test_only_hint_bits_changed() {
+        let page = page_lazy(HEAP_PAGE);
+        let mut hinted = page.clone();
+        let item_id = hinted.item_id(1).unwrap();
+        // t_infomask of the first tuple
+        let start = item_id.lp_off() as usize + 20 - 24;
+        let toggle = |page: &mut PageLazy, bits: u16| {
+            let infomask = u16::decode(&page.data[start..start + 2]).unwrap() ^ bits;
+            page.data[start..start + 2].copy_from_slice(&infomask.encode());
+        };
+        toggle(&mut hinted, HEAP_XMAX_INVALID);
+        hinted.header_data.pd_lsn.xrecoff += 8;
+        hinted.set_checksum(0);
+        assert_ne!(page, hinted);
+        assert!(page.only_hint_bits_changed(&hinted));
+        assert!(page.only_hint_bits_changed(&page));
+
+        let mut changed = hinted.clone();
+        toggle(&mut changed, HEAP_HASNULL);
+        assert!(!page.only_hint_bits_changed(&changed));
+
+        let mut flagged = page.clone();
+        flagged.header_data.pd_flags |= PD_ALL_VISIBLE;
+        assert!(!page.only_hint_bits_changed(&flagged));
+    }
+}
@@ -1,19 +1,39 @@
 use crate::util::{ByteEncodeResult, ByteEncoded};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PageXLogRecPtr {
     pub xlogid: u32,
     pub xrecoff: u32,
 }
 
+impl PageXLogRecPtr {
+    /// The pointer as a single 64-bit LSN, as PageXLogRecPtrGet does.
+    pub fn to_u64(&self) -> u64 {
+        (self.xlogid as u64) << 32 | self.xrecoff as u64
+    }
+
+    /// The same pointer with the byte order of both halves reversed.
+    pub fn swap_bytes(&self) -> Self {
+        PageXLogRecPtr {
+            xlogid: self.xlogid.swap_bytes(),
+            xrecoff: self.xrecoff.swap_bytes(),
+        }
+    }
+}
+
 impl ByteEncoded for PageXLogRecPtr {
     fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.xlogid.encode());
-        buf.extend(self.xrecoff.encode());
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
         buf
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.xlogid.encode_into(buf);
+        self.xrecoff.encode_into(buf);
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let xlogid = u32::decode(&bytes[0..4])?;
         let xrecoff = u32::decode(&bytes[4..8])?;
@@ -0,0 +1,36 @@
+use super::heap_tuple_header_data::transaction_id_precedes;
+
+///
+/// The parts of an MVCC snapshot needed to decide tuple visibility, as in
+/// `txid_current_snapshot()`.
+///
+/// Transactions are judged relative to the snapshot: every xid before `xmin`
+/// had finished when the snapshot was taken, every xid from `xmax` on had not
+/// started yet, and the ones in between had finished unless listed in `xip`.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Default)]
+pub struct Snapshot {
+    /// all xids < xmin are finished
+    pub xmin: u32,
+    /// all xids >= xmax are not yet started
+    pub xmax: u32,
+    /// xids in progress when the snapshot was taken, xmin <= xid < xmax
+    pub xip: Vec<u32>,
+}
+
+impl Snapshot {
+    pub fn new(xmin: u32, xmax: u32, xip: Vec<u32>) -> Self {
+        Snapshot { xmin, xmax, xip }
+    }
+
+    /// Whether `xid` must be treated as still running for this snapshot, like XidInMVCCSnapshot.
+    pub fn is_in_progress(&self, xid: u32) -> bool {
+        if transaction_id_precedes(xid, self.xmin) {
+            return false;
+        }
+        if !transaction_id_precedes(xid, self.xmax) {
+            return true;
+        }
+        self.xip.contains(&xid)
+    }
+}
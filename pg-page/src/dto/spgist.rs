@@ -0,0 +1,142 @@
+use crate::{
+    util::{read_u16, ByteEncodeResult, ByteEncoded, GetByteSliceExt},
+    Error,
+};
+
+use super::page_lazy::PageLazy;
+
+/// the metapage
+pub const SPGIST_META: u16 = 1 << 0;
+/// the page has been deleted
+pub const SPGIST_DELETED: u16 = 1 << 1;
+/// leaf page, otherwise an inner page
+pub const SPGIST_LEAF: u16 = 1 << 2;
+/// page belongs to the tree of null entries
+pub const SPGIST_NULLS: u16 = 1 << 3;
+
+/// Identifies SP-GiST pages, kept in the last two bytes of the special space.
+pub const SPGIST_PAGE_ID: u16 = 0xFF82;
+
+///
+/// The special space of an SP-GiST page (SpGistPageOpaqueData).
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct SpGistPageOpaque {
+    /// SPGIST_* flag bits
+    pub flags: u16,
+    /// number of redirection tuples on the page
+    pub n_redirection: u16,
+    /// number of placeholder tuples on the page
+    pub n_placeholder: u16,
+    /// should contain SPGIST_PAGE_ID
+    pub spgist_page_id: u16,
+}
+
+impl SpGistPageOpaque {
+    pub fn is_meta(&self) -> bool {
+        self.flags & SPGIST_META != 0
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.flags & SPGIST_DELETED != 0
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.flags & SPGIST_LEAF != 0
+    }
+
+    pub fn stores_nulls(&self) -> bool {
+        self.flags & SPGIST_NULLS != 0
+    }
+}
+
+impl ByteEncoded for SpGistPageOpaque {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.flags.encode_into(buf);
+        self.n_redirection.encode_into(buf);
+        self.n_placeholder.encode_into(buf);
+        self.spgist_page_id.encode_into(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let bytes = bytes.get_byte_slice(0, Self::byte_size() as usize)?;
+        Ok(SpGistPageOpaque {
+            flags: read_u16(&bytes[0..2]),
+            n_redirection: read_u16(&bytes[2..4]),
+            n_placeholder: read_u16(&bytes[4..6]),
+            spgist_page_id: read_u16(&bytes[6..8]),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        8
+    }
+}
+
+impl PageLazy {
+    /// The SP-GiST opaque data kept in the special space of an index page.
+    /// Fails unless `spgist_page_id` is SPGIST_PAGE_ID.
+    pub fn spgist_opaque(&self) -> Result<SpGistPageOpaque, Error> {
+        let opaque = SpGistPageOpaque::decode(self.special_bytes()?)?;
+        if opaque.spgist_page_id != SPGIST_PAGE_ID {
+            return Err(Error::InvalidByteEncoding(format!(
+                "SP-GiST page id {:#x}, expected {:#x}",
+                opaque.spgist_page_id, SPGIST_PAGE_ID
+            )));
+        }
+        Ok(opaque)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::page_with_special;
+
+    #[test]
+    fn test_spgist_opaque() {
+        // special spaces of the metapage and the nulls leaf page of an SP-GiST index on text
+        let meta = page_with_special(&[0x01, 0, 0, 0, 0, 0, 0x82, 0xFF]).spgist_opaque().unwrap();
+        assert!(meta.is_meta() && !meta.is_leaf());
+
+        let special = [0x0C, 0, 0, 0, 0, 0, 0x82, 0xFF];
+        let nulls = page_with_special(&special).spgist_opaque().unwrap();
+        assert_eq!(
+            nulls,
+            SpGistPageOpaque {
+                flags: SPGIST_LEAF | SPGIST_NULLS,
+                n_redirection: 0,
+                n_placeholder: 0,
+                spgist_page_id: SPGIST_PAGE_ID,
+            }
+        );
+        assert!(nulls.is_leaf() && nulls.stores_nulls() && !nulls.is_deleted());
+        assert_eq!(nulls.encode(), special);
+    }
+
+    #[test]
+    fn test_spgist_opaque_counts() {
+        let opaque = SpGistPageOpaque::decode(&[0x04, 0, 3, 0, 2, 0, 0x82, 0xFF]).unwrap();
+        assert_eq!((opaque.n_redirection, opaque.n_placeholder), (3, 2));
+
+        // a GiST special space is twice the size and carries another page id
+        let gist = [0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x81, 0xFF];
+        assert!(matches!(page_with_special(&gist).spgist_opaque(), Err(Error::InvalidByteEncoding(_))));
+    }
+}
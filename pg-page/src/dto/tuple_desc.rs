@@ -0,0 +1,497 @@
+use crate::util::{maxalign, varlena::VarlenaHeader, ByteEncodeError, ByteEncodeResult, GetByteSliceExt};
+use crate::Error;
+
+use super::{block_id_data::BlockIdData, heap_tuple_header_data::*, index_tuple_data::IndexTupleData, item_pointer_data::ItemPointerData};
+
+///
+/// The subset of a pg_attribute row needed to locate an attribute's value
+/// inside a tuple.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct AttrDesc {
+    /// length of the type in bytes, -1 for varlena, -2 for cstring
+    pub attlen: i16,
+    /// alignment of the type: b'c', b's', b'i' or b'd'
+    pub attalign: u8,
+    /// whether the value is passed by value rather than by reference
+    pub attbyval: bool,
+    /// type OID
+    pub typoid: u32,
+}
+
+impl AttrDesc {
+    /// Alignment requirement in bytes.
+    pub fn alignment(&self) -> usize {
+        match self.attalign {
+            b's' => 2,
+            b'i' => 4,
+            b'd' => 8,
+            _ => 1,
+        }
+    }
+}
+
+///
+/// Describes the columns of a relation, in attribute number order.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Default)]
+pub struct TupleDesc {
+    pub attrs: Vec<AttrDesc>,
+}
+
+impl TupleDesc {
+    pub fn new(attrs: Vec<AttrDesc>) -> Self {
+        TupleDesc { attrs }
+    }
+}
+
+/// An attribute value as stored on disk. Varlena values keep their length header.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum Datum {
+    Null,
+    Value(Vec<u8>),
+}
+
+impl Datum {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Datum::Null)
+    }
+}
+
+pub fn att_align(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+/// Where one attribute's value sits within a tuple's user data.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum AttrSlot {
+    Null,
+    Value {
+        /// alignment padding skipped before the value
+        padding: usize,
+        start: usize,
+        len: usize,
+    },
+}
+
+/// Walks the attributes of a tuple following the descriptor, the same way
+/// heap_deform_tuple does: nulls take no space, fixed-width values are aligned
+/// to their attalign, and varlenas with a short header are not aligned at all.
+pub(crate) struct AttrWalker<'a> {
+    data: &'a [u8],
+    bitmap: Option<&'a [u8]>,
+    desc: &'a TupleDesc,
+    natts: usize,
+    attnum: usize,
+    offset: usize,
+}
+
+impl<'a> AttrWalker<'a> {
+    pub(crate) fn new(tuple: &'a HeapTupleHeaderData, desc: &'a TupleDesc) -> ByteEncodeResult<Self> {
+        let natts = tuple.natts() as usize;
+        let bitmap = if tuple.t_infomask & HEAP_HASNULL != 0 {
+            Some(tuple.data.get_byte_slice(0, natts.div_ceil(8))?)
+        } else {
+            None
+        };
+        Ok(AttrWalker {
+            data: tuple.user_data()?,
+            bitmap,
+            desc,
+            natts,
+            attnum: 0,
+            offset: 0,
+        })
+    }
+
+    /// Index tuples don't record their attribute count, so every attribute of the
+    /// descriptor is walked.
+    pub(crate) fn for_index(tuple: &'a IndexTupleData, desc: &'a TupleDesc) -> ByteEncodeResult<Self> {
+        Ok(AttrWalker {
+            data: tuple.key_payload(),
+            bitmap: tuple.null_bitmap()?,
+            desc,
+            natts: desc.attrs.len(),
+            attnum: 0,
+            offset: 0,
+        })
+    }
+
+    fn is_null(&self, attnum: usize) -> bool {
+        match self.bitmap {
+            Some(bitmap) => bitmap[attnum / 8] & (1 << (attnum % 8)) == 0,
+            None => false,
+        }
+    }
+
+    fn next_slot(&mut self, attr: AttrDesc) -> ByteEncodeResult<AttrSlot> {
+        let attnum = self.attnum;
+        self.attnum += 1;
+        // attributes added after the tuple was written read as null
+        if attnum >= self.natts || self.is_null(attnum) {
+            return Ok(AttrSlot::Null);
+        }
+
+        let start = match attr.attlen {
+            -1 if self.data.get(self.offset).is_some_and(|b| *b != 0) => self.offset,
+            _ => att_align(self.offset, attr.alignment()),
+        };
+        let len = match attr.attlen {
+            -1 => varsize_any(self.data.get_byte_slice(start, self.data.len())?)?,
+            -2 => {
+                let rest = self.data.get_byte_slice(start, self.data.len())?;
+                rest.iter().position(|b| *b == 0).ok_or(ByteEncodeError::NotEnoughBytes {
+                    expected: rest.len() + 1,
+                    actual: rest.len(),
+                })? + 1
+            }
+            len => len as usize,
+        };
+        self.data.get_byte_slice(start, start + len)?;
+
+        let padding = start - self.offset;
+        self.offset = start + len;
+        Ok(AttrSlot::Value {
+            padding,
+            start,
+            len,
+        })
+    }
+}
+
+impl Iterator for AttrWalker<'_> {
+    type Item = ByteEncodeResult<AttrSlot>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let attr = *self.desc.attrs.get(self.attnum)?;
+        Some(self.next_slot(attr))
+    }
+}
+
+/// Splits the user data of a tuple into one datum per attribute of the descriptor.
+pub fn deserialize_attrs(tuple: &HeapTupleHeaderData, desc: &TupleDesc) -> ByteEncodeResult<Vec<Datum>> {
+    let data = tuple.user_data()?;
+    AttrWalker::new(tuple, desc)?
+        .map(|slot| {
+            Ok(match slot? {
+                AttrSlot::Null => Datum::Null,
+                AttrSlot::Value { start, len, .. } => Datum::Value(data[start..start + len].to_vec()),
+            })
+        })
+        .collect()
+}
+
+impl HeapTupleHeaderData {
+    /// Splits the tuple into one raw value per attribute of `desc`, like heap_deform_tuple,
+    /// with `Datum::Null` for nulls. Values keep their on-disk form; varlenas keep their header.
+    pub fn deform(&self, desc: &TupleDesc) -> Result<Vec<Datum>, Error> {
+        Ok(deserialize_attrs(self, desc)?)
+    }
+
+    /// The value of attribute `attnum` (0-based), walking only the attributes before it.
+    pub fn get_attr(&self, attnum: usize, desc: &TupleDesc) -> ByteEncodeResult<Datum> {
+        let data = self.user_data()?;
+        match AttrWalker::new(self, desc)?.nth(attnum) {
+            Some(slot) => Ok(match slot? {
+                AttrSlot::Null => Datum::Null,
+                AttrSlot::Value { start, len, .. } => Datum::Value(data[start..start + len].to_vec()),
+            }),
+            None => Err(ByteEncodeError::InvalidSize {
+                expected: desc.attrs.len(),
+                actual: attnum,
+            }),
+        }
+    }
+
+    /// `(attnum, on-disk length)` of every non-null varlena attribute, attnum 0-based. The
+    /// length is what the value takes in the tuple including its header: the compressed
+    /// size for inline compressed values and the TOAST pointer for external ones.
+    pub fn varlena_lengths(&self, desc: &TupleDesc) -> ByteEncodeResult<Vec<(usize, usize)>> {
+        let mut lengths = Vec::new();
+        for (attnum, slot) in AttrWalker::new(self, desc)?.enumerate() {
+            if let AttrSlot::Value { len, .. } = slot? {
+                if desc.attrs[attnum].attlen == -1 {
+                    lengths.push((attnum, len));
+                }
+            }
+        }
+        Ok(lengths)
+    }
+}
+
+/// Splits the key of an index tuple into one datum per attribute of the index's descriptor.
+pub fn deserialize_index_attrs(tuple: &IndexTupleData, desc: &TupleDesc) -> ByteEncodeResult<Vec<Datum>> {
+    let data = tuple.key_payload();
+    AttrWalker::for_index(tuple, desc)?
+        .map(|slot| {
+            Ok(match slot? {
+                AttrSlot::Null => Datum::Null,
+                AttrSlot::Value { start, len, .. } => Datum::Value(data[start..start + len].to_vec()),
+            })
+        })
+        .collect()
+}
+
+/// Forms a heap tuple from attribute values, like heap_form_tuple: writes the null bitmap,
+/// pads the header to a MAXALIGN'd `t_hoff`, aligns each value and sets the infomask bits
+/// describing the contents. The inverse of [`deserialize_attrs`].
+///
+/// Values must already be in their on-disk form; varlenas with a 1-byte header are stored
+/// unaligned, as Postgres does. The tuple's `t_ctid` is left zeroed.
+pub fn build_tuple(values: &[Datum], desc: &TupleDesc, xmin: u32, xmax: u32) -> ByteEncodeResult<HeapTupleHeaderData> {
+    if values.len() > desc.attrs.len() || values.len() > HEAP_NATTS_MASK as usize {
+        return Err(ByteEncodeError::InvalidSize {
+            expected: desc.attrs.len(),
+            actual: values.len(),
+        });
+    }
+
+    let has_nulls = values.iter().any(Datum::is_null);
+    let mut t_infomask = if xmax == INVALID_TRANSACTION_ID { HEAP_XMAX_INVALID } else { 0 };
+    let mut data = Vec::new();
+    if has_nulls {
+        t_infomask |= HEAP_HASNULL;
+        data.resize(values.len().div_ceil(8), 0);
+        for (i, value) in values.iter().enumerate() {
+            if !value.is_null() {
+                data[i / 8] |= 1 << (i % 8);
+            }
+        }
+    }
+    let t_hoff = maxalign(SIZEOF_HEAP_TUPLE_HEADER + data.len());
+    data.resize(t_hoff - SIZEOF_HEAP_TUPLE_HEADER, 0);
+
+    let user_start = data.len();
+    for (value, attr) in values.iter().zip(&desc.attrs) {
+        let Datum::Value(bytes) = value else {
+            continue;
+        };
+        let offset = data.len() - user_start;
+        let start = match attr.attlen {
+            -1 if bytes.first().is_some_and(|b| b & 0x01 == 0x01) => offset,
+            _ => att_align(offset, attr.alignment()),
+        };
+        if attr.attlen > 0 && bytes.len() != attr.attlen as usize {
+            return Err(ByteEncodeError::InvalidSize {
+                expected: attr.attlen as usize,
+                actual: bytes.len(),
+            });
+        }
+        if attr.attlen < 0 {
+            t_infomask |= HEAP_HASVARWIDTH;
+        }
+        if attr.attlen == -1 && bytes.first() == Some(&0x01) {
+            t_infomask |= HEAP_HASEXTERNAL;
+        }
+        data.resize(user_start + start, 0);
+        data.extend_from_slice(bytes);
+    }
+
+    Ok(HeapTupleHeaderData {
+        t_xmin: xmin,
+        t_xmax: xmax,
+        t_field3: 0,
+        t_ctid: ItemPointerData {
+            ip_blkid: BlockIdData { bi_hi: 0, bi_lo: 0 },
+            ip_posid: 0,
+        },
+        t_infomask2: values.len() as u16,
+        t_infomask,
+        t_hoff: t_hoff as u8,
+        data,
+    })
+}
+
+/// Total on-disk size of the varlena starting at `bytes`, header included.
+fn varsize_any(bytes: &[u8]) -> ByteEncodeResult<usize> {
+    Ok(VarlenaHeader::parse(bytes)?.total_len())
+}
+
+/// Bytes of a tuple spent purely on alignment padding: after the fixed header
+/// (up to `t_hoff`) and between attributes. Summed over a relation this shows
+/// how much a different column order would save.
+pub fn tuple_padding_waste(tuple: &HeapTupleHeaderData, desc: &TupleDesc) -> ByteEncodeResult<u16> {
+    let natts = tuple.natts() as usize;
+    let mut used = SIZEOF_HEAP_TUPLE_HEADER;
+    if tuple.t_infomask & HEAP_HASNULL != 0 {
+        used += natts.div_ceil(8);
+    }
+    if tuple.t_infomask & HEAP_HASOID_OLD != 0 {
+        used += 4;
+    }
+    let mut waste = (tuple.t_hoff as usize).saturating_sub(used);
+
+    for slot in AttrWalker::new(tuple, desc)? {
+        if let AttrSlot::Value { padding, .. } = slot? {
+            waste += padding;
+        }
+    }
+
+    Ok(waste as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{page_lazy, tuple, HEAP_PAGE, INT2, INT4, INT8, TEXT},
+        util::ByteEncoded,
+    };
+
+    #[test]
+    fn test_build_tuple_round_trip() {
+        let desc = TupleDesc::new(vec![INT2, TEXT, INT8, TEXT, INT4]);
+        let values = vec![
+            Datum::Value(vec![1, 0]),
+            Datum::Value(vec![(3 << 1) | 1, b'h', b'i']),
+            Datum::Null,
+            // 4-byte header varlena, which gets aligned
+            Datum::Value(vec![6 << 2, 0, 0, 0, b'y', b'o']),
+            Datum::Value(vec![4, 0, 0, 0]),
+        ];
+
+        let built = build_tuple(&values, &desc, 10, 0).unwrap();
+        assert_eq!(built.t_infomask2, 5);
+        assert_eq!(built.t_infomask, HEAP_HASNULL | HEAP_HASVARWIDTH | HEAP_XMAX_INVALID);
+        assert_eq!(built.t_hoff, 24);
+        assert_eq!(built.data[0], 0b11011);
+        // int2, short text right after it, padding, long text, int4
+        assert_eq!(
+            built.user_data().unwrap(),
+            &[1, 0, 7, b'h', b'i', 0, 0, 0, 24, 0, 0, 0, b'y', b'o', 0, 0, 4, 0, 0, 0]
+        );
+
+        let decoded = HeapTupleHeaderData::decode(&built.encode()).unwrap();
+        assert_eq!(decoded, built);
+        assert_eq!(deserialize_attrs(&decoded, &desc).unwrap(), values);
+    }
+
+    #[test]
+    fn test_build_tuple_without_nulls() {
+        let desc = TupleDesc::new(vec![INT4, INT8]);
+        let values = vec![Datum::Value(vec![1, 0, 0, 0]), Datum::Value(vec![2, 0, 0, 0, 0, 0, 0, 0])];
+
+        let built = build_tuple(&values, &desc, 10, 12).unwrap();
+        assert_eq!(built.t_infomask, 0);
+        assert_eq!(built.t_hoff, 24);
+        assert_eq!(built.data.len(), 1 + 16);
+        assert_eq!(deserialize_attrs(&built, &desc).unwrap(), values);
+    }
+
+    #[test]
+    fn test_build_tuple_wrong_width() {
+        let desc = TupleDesc::new(vec![INT4]);
+        assert!(build_tuple(&[Datum::Value(vec![1, 0])], &desc, 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_padding_waste_misordered_columns() {
+        let desc = TupleDesc::new(vec![INT4, INT8, INT4]);
+        let mut t = tuple(1, vec![0; 21]);
+        t.t_infomask2 = 3;
+        // int4 at 0, padding to 8, int8 at 8, int4 at 16
+        assert_eq!(tuple_padding_waste(&t, &desc).unwrap(), 1 + 4);
+
+        let desc = TupleDesc::new(vec![INT8, INT4, INT4]);
+        let mut t = tuple(1, vec![0; 17]);
+        t.t_infomask2 = 3;
+        assert_eq!(tuple_padding_waste(&t, &desc).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_padding_waste_with_nulls() {
+        let desc = TupleDesc::new(vec![INT4, INT8, INT4]);
+        let mut t = tuple(1, vec![0b101, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        t.t_infomask2 = 3;
+        t.t_infomask = HEAP_HASNULL;
+        // the int8 is null, so the two int4s sit back to back
+        assert_eq!(tuple_padding_waste(&t, &desc).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_deform_fixed_and_varlena() {
+        let page = page_lazy(HEAP_PAGE);
+        let desc = TupleDesc::new(vec![INT4, TEXT, INT8]);
+        let tuple = page.tuple(&page.item_id(2).unwrap()).unwrap();
+
+        let values = tuple.deform(&desc).unwrap();
+        let mut name = vec![(6 << 1) | 1];
+        name.extend_from_slice(b"row 2");
+        assert_eq!(
+            values,
+            vec![
+                Datum::Value(2_i32.to_le_bytes().to_vec()),
+                Datum::Value(name),
+                Datum::Value(200_i64.to_le_bytes().to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deform_nulls() {
+        let desc = TupleDesc::new(vec![INT4, TEXT, INT8]);
+        let values = [
+            Datum::Null,
+            Datum::Value(vec![(3 << 1) | 1, b'h', b'i']),
+            Datum::Value(7_i64.to_le_bytes().to_vec()),
+        ];
+        let tuple = build_tuple(&values, &desc, 2, 0).unwrap();
+        assert_eq!(tuple.deform(&desc).unwrap(), values);
+    }
+
+    #[test]
+    fn test_get_attr_third_column() {
+        let page = page_lazy(HEAP_PAGE);
+        let desc = TupleDesc::new(vec![INT4, TEXT, INT8]);
+        let tuple = page.tuple(&page.item_id(3).unwrap()).unwrap();
+        assert_eq!(
+            tuple.get_attr(2, &desc).unwrap(),
+            Datum::Value(300_i64.to_le_bytes().to_vec())
+        );
+
+        let values = [
+            Datum::Null,
+            Datum::Value(vec![(4 << 1) | 1, b'a', b'b', b'c']),
+            Datum::Value(9_i64.to_le_bytes().to_vec()),
+        ];
+        let built = build_tuple(&values, &desc, 2, 0).unwrap();
+        assert_eq!(built.get_attr(0, &desc).unwrap(), Datum::Null);
+        assert_eq!(built.get_attr(2, &desc).unwrap(), values[2]);
+        assert!(built.get_attr(3, &desc).is_err());
+    }
+
+    #[test]
+    fn test_get_attr_stops_early() {
+        let desc = TupleDesc::new(vec![INT4, INT8]);
+        let values = [
+            Datum::Value(5_i32.to_le_bytes().to_vec()),
+            Datum::Value(6_i64.to_le_bytes().to_vec()),
+        ];
+        let mut tuple = build_tuple(&values, &desc, 2, 0).unwrap();
+        // cut the second attribute short; the first is still readable
+        tuple.data.truncate(tuple.data.len() - 4);
+        assert_eq!(tuple.get_attr(0, &desc).unwrap(), values[0]);
+        assert!(tuple.get_attr(1, &desc).is_err());
+        assert!(tuple.deform(&desc).is_err());
+    }
+
+    #[test]
+    fn test_varlena_lengths() {
+        let desc = TupleDesc::new(vec![TEXT, INT4, TEXT, TEXT, TEXT]);
+        // 4 byte header with the compressed bit, va_tcinfo (raw size 100, lz4), 6 bytes of data
+        let mut compressed = (((8 + 6) << 2) | 0x02_u32).to_le_bytes().to_vec();
+        compressed.extend((100_u32 | 1 << 30).to_le_bytes());
+        compressed.extend([1, 2, 3, 4, 5, 6]);
+        let mut external = vec![0x01, 18];
+        external.extend([0; 16]);
+        let values = [
+            Datum::Value(vec![(3 << 1) | 1, b'h', b'i']),
+            Datum::Value(1_i32.to_le_bytes().to_vec()),
+            Datum::Value(compressed),
+            Datum::Null,
+            Datum::Value(external),
+        ];
+        let tuple = build_tuple(&values, &desc, 2, 0).unwrap();
+        assert_eq!(tuple.varlena_lengths(&desc).unwrap(), vec![(0, 3), (2, 14), (4, 18)]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{Read, Seek};
+
+use crate::{page_reader::PageReader, util::ByteEncodeResult};
+
+use super::page_lazy::PageLazy;
+
+/// Bits kept per heap block
+pub const BITS_PER_HEAPBLOCK: usize = 2;
+/// Heap blocks covered by one byte of the map
+pub const HEAPBLOCKS_PER_BYTE: usize = 8 / BITS_PER_HEAPBLOCK;
+/// every tuple on the heap page is visible to all transactions
+pub const VISIBILITYMAP_ALL_VISIBLE: u8 = 0x01;
+/// every tuple on the heap page is frozen
+pub const VISIBILITYMAP_ALL_FROZEN: u8 = 0x02;
+
+///
+/// The visibility map fork (`<filenode>_vm`) of a heap relation: two bits per heap block,
+/// packed four blocks to a byte into the space after each page's header.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct VisibilityMap {
+    pub pages: Vec<PageLazy>,
+}
+
+impl VisibilityMap {
+    pub fn new(pages: Vec<PageLazy>) -> Self {
+        VisibilityMap { pages }
+    }
+
+    /// Reads every page of a visibility map fork.
+    pub fn from_reader<R: Read + Seek>(reader: PageReader<R>) -> ByteEncodeResult<Self> {
+        Ok(VisibilityMap {
+            pages: reader.into_iter().collect::<ByteEncodeResult<_>>()?,
+        })
+    }
+
+    /// Heap blocks covered by each map page, from the size of the first page. With 8 kB
+    /// pages that is (8192 - 24) * 4 = 32672.
+    pub fn heap_blocks_per_page(&self) -> usize {
+        self.pages.first().map_or(0, |page| page.data.len() * HEAPBLOCKS_PER_BYTE)
+    }
+
+    /// The VISIBILITYMAP_* bits of `heap_block`. Blocks past the end of the map have
+    /// no bits set, as Postgres treats them.
+    pub fn bits(&self, heap_block: u32) -> u8 {
+        let per_page = self.heap_blocks_per_page();
+        if per_page == 0 {
+            return 0;
+        }
+        let heap_block = heap_block as usize;
+        let map_byte = (heap_block % per_page) / HEAPBLOCKS_PER_BYTE;
+        let map_offset = (heap_block % HEAPBLOCKS_PER_BYTE) * BITS_PER_HEAPBLOCK;
+        self.pages
+            .get(heap_block / per_page)
+            .and_then(|page| page.data.get(map_byte))
+            .map_or(0, |byte| (byte >> map_offset) & (VISIBILITYMAP_ALL_VISIBLE | VISIBILITYMAP_ALL_FROZEN))
+    }
+
+    /// Whether `heap_block` is marked (all-visible, all-frozen).
+    pub fn status(&self, heap_block: u32) -> (bool, bool) {
+        let bits = self.bits(heap_block);
+        (bits & VISIBILITYMAP_ALL_VISIBLE != 0, bits & VISIBILITYMAP_ALL_FROZEN != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, page_lazy, relation_reader};
+
+    #[test]
+    fn test_status() {
+        let mut first = page_bytes(&[]);
+        // heap blocks 0 (visible, frozen), 1 (visible) and 3 (visible, frozen)
+        first[24] = 0b1100_0111;
+        // the last byte of the page covers heap blocks 32668..=32671; mark 32671 all-visible
+        first[8191] = 0b0100_0000;
+        let mut second = page_bytes(&[]);
+        // heap block 32672 + 5, the second block of the second byte
+        second[25] = 0b0000_0100;
+        let map = VisibilityMap::from_reader(relation_reader(&[first, second])).unwrap();
+
+        assert_eq!(map.heap_blocks_per_page(), 32672);
+        assert_eq!(map.status(0), (true, true));
+        assert_eq!(map.status(1), (true, false));
+        assert_eq!(map.status(2), (false, false));
+        assert_eq!(map.status(3), (true, true));
+        assert_eq!(map.status(32671), (true, false));
+        assert_eq!(map.status(32672 + 5), (true, false));
+        assert_eq!(map.status(32672 + 4), (false, false));
+        assert_eq!(map.bits(32672 + 5), VISIBILITYMAP_ALL_VISIBLE);
+    }
+
+    #[test]
+    fn test_status_past_end() {
+        let map = VisibilityMap::new(vec![page_lazy(&page_bytes(&[]))]);
+        assert_eq!(map.status(32672 * 3), (false, false));
+        assert_eq!(VisibilityMap::default().status(0), (false, false));
+    }
+}
@@ -1,9 +1,18 @@
 use thiserror::Error;
 
+#[cfg(feature = "basebackup")]
+pub mod basebackup;
+pub mod checksum;
 pub mod compile_constants;
 pub mod util;
 pub mod dto;
+#[cfg(feature = "memmap2")]
+pub mod mmap;
 pub mod page_reader;
+pub mod relation;
+pub mod scan;
+#[cfg(test)]
+mod test_util;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -1,17 +1,21 @@
-use pg_page::dto::HeapTupleHeaderData;
 use pg_page::page_reader::PageReader;
+use pg_page::scan::verify_relation_full;
 use pg_page::{
     dto::{Page, PageLazy},
     util::{ByteEncodeError, ByteEncodeResult},
 };
-use std::io::{Read, Write, Seek};
+use std::io::Seek;
 use std::time::Instant;
 use std::{fs::File, io::BufReader};
 
 fn main() {
-    let table_file_name = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let table_file_name = args.next().unwrap();
+    if table_file_name == "verify" {
+        verify(&args.next().unwrap(), args.next().map_or(0, |block| block.parse().unwrap()));
+    }
 
-    let (pages, elapsed) = bench_func(|| {
+    let (_pages, elapsed) = bench_func(|| {
         let mut table_file = File::open(&table_file_name).unwrap();
         let mut reader = BufReader::new(&mut table_file);
         read_pages_lazy(&mut reader).unwrap()
@@ -45,11 +49,20 @@ fn main() {
     // }
 }
 
+/// `verify <file> [first block number]`: prints a verification report for a relation
+/// segment and exits nonzero if anything failed.
+fn verify(file_name: &str, block_start: u64) -> ! {
+    let reader = PageReader::new(File::open(file_name).unwrap());
+    let report = verify_relation_full(reader, block_start).unwrap();
+    print!("{}", report);
+    std::process::exit(if report.has_failures() { 1 } else { 0 })
+}
+
 fn read_pages_lazy(reader: &mut (impl std::io::Read + Seek)) -> ByteEncodeResult<Vec<PageLazy>> {
     let mut pages = Vec::new();
     for page in PageReader::new(reader).into_iter() {
         let page = page?;
-        let tuples = page.iter_tuples().map(Result::unwrap).collect::<Vec<_>>();
+        let _tuples = page.iter_tuples().map(Result::unwrap).collect::<Vec<_>>();
         pages.push(page);
     }
 
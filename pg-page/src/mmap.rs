@@ -0,0 +1,130 @@
+//! Zero-copy, read-only access to a relation file through a memory map, for analysing
+//! large relations without copying every page into its own buffer.
+
+use std::{fs::File, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    compile_constants::BLCKSZ,
+    dto::PageHeaderData,
+    util::{ByteEncodeResult, ByteEncoded, GetByteSliceExt},
+};
+
+/// A relation segment file mapped into memory, split into pages of its block size.
+pub struct MmapRelation {
+    mmap: Mmap,
+    block_size: usize,
+}
+
+impl MmapRelation {
+    /// Maps the file at `path`, taking its blocks to be `BLCKSZ` bytes.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this process or
+    /// any other, such as a running server. The pages handed out borrow the mapping
+    /// directly, so a change would alter bytes behind a shared reference, and a truncation
+    /// makes reading them fault.
+    pub unsafe fn open(path: impl AsRef<Path>) -> ByteEncodeResult<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the caller guarantees the file stays unchanged while mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(MmapRelation {
+            mmap,
+            block_size: BLCKSZ as usize,
+        })
+    }
+
+    /// Splits the file into blocks of `block_size` bytes instead of `BLCKSZ`.
+    ///
+    /// Panics if `block_size` is 0.
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        assert!(block_size > 0, "block size must be positive");
+        self.block_size = block_size;
+        self
+    }
+
+    /// Number of complete blocks in the file. A torn final block is left out.
+    pub fn block_count(&self) -> u32 {
+        (self.mmap.len() / self.block_size) as u32
+    }
+
+    /// Block `block_number`, or `None` past the end of the file.
+    pub fn page(&self, block_number: u32) -> Option<PageView<'_>> {
+        let start = block_number as usize * self.block_size;
+        self.mmap
+            .get(start..start + self.block_size)
+            .map(|bytes| PageView { bytes })
+    }
+
+    /// Every complete block in order.
+    pub fn pages(&self) -> impl Iterator<Item = PageView<'_>> {
+        self.mmap
+            .chunks_exact(self.block_size)
+            .map(|bytes| PageView { bytes })
+    }
+}
+
+/// A page borrowed from the mapped file.
+#[derive(Debug, Clone, Copy)]
+pub struct PageView<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PageView<'a> {
+    /// The whole page image, header included.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn header(&self) -> ByteEncodeResult<PageHeaderData> {
+        PageHeaderData::decode(self.bytes.get_byte_slice(0, PageHeaderData::byte_size() as usize)?)
+    }
+
+    /// The `len` bytes at page offset `offset`, as given by a line pointer's lp_off and lp_len.
+    pub fn tuple_bytes(&self, offset: u16, len: u16) -> ByteEncodeResult<&'a [u8]> {
+        self.bytes
+            .get_byte_slice(offset as usize, offset as usize + len as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dto::{HeapTupleHeaderData, PageLazy},
+        test_util::{page_bytes, tuple},
+    };
+
+    #[test]
+    fn test_mmap_relation() {
+        let pages: Vec<_> = (1..=3).map(|xmin| page_bytes(&[tuple(xmin, vec![xmin as u8])])).collect();
+        let path = std::env::temp_dir().join(format!("pg-page-mmap-{}", std::process::id()));
+        // a torn final block is not counted
+        std::fs::write(&path, [pages.concat(), vec![0; 100]].concat()).unwrap();
+
+        // SAFETY: nothing else touches the file until it is removed below
+        let relation = unsafe { MmapRelation::open(&path) }.unwrap();
+        assert_eq!(relation.block_count(), 3);
+        assert_eq!(relation.pages().count(), 3);
+        assert!(relation.page(3).is_none());
+
+        let view = relation.page(1).unwrap();
+        let page = PageLazy::from_reader(&mut pages[1].as_slice()).unwrap();
+        assert_eq!(view.header().unwrap(), page.header_data);
+        assert_eq!(view.bytes(), &pages[1][..]);
+
+        let item_id = page.item_id(1).unwrap();
+        let bytes = view.tuple_bytes(item_id.lp_off(), item_id.lp_len()).unwrap();
+        assert_eq!(HeapTupleHeaderData::decode(bytes).unwrap(), tuple(2, vec![2]));
+        assert!(view.tuple_bytes(8190, 4).is_err());
+
+        let halves = relation.with_block_size(4096);
+        assert_eq!(halves.block_count(), 6);
+        assert_eq!(halves.page(3).unwrap().bytes(), &pages[1][4096..]);
+
+        drop(halves);
+        std::fs::remove_file(path).unwrap();
+    }
+}
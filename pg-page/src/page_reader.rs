@@ -1,6 +1,20 @@
-use std::{io::{BufReader, Read, Seek}};
+use std::io::{BufReader, Cursor, Read, Seek};
 
-use crate::{dto::{PageHeaderData, PageLazy}, util::{ByteEncodeResult, ByteEncoded, read_exact_with_eof}};
+use crate::{compile_constants::BLCKSZ, dto::{PageHeaderData, PageLazy}, util::{ByteEncodeError, ByteEncodeResult, ByteEncoded, Endianness, read_exact_with_eof, read_until_full}};
+
+#[cfg(feature = "tokio")]
+pub mod async_page_reader;
+
+/// How much work a `PageReader` has done through `read_next_page` and its variants.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ReaderStats {
+    /// Pages decoded and returned
+    pub pages_read: u64,
+    /// Pages passed over by a filter or `skip_empty_pages`
+    pub pages_skipped: u64,
+    /// Bytes read from the file; only the header of a skipped page is read
+    pub bytes_read: u64,
+}
 
 // TODO: handle locked pages
 
@@ -8,6 +22,12 @@ pub struct PageReader<R: Read + Seek> {
     reader: BufReader<R>,
     cursor: u64,
     ended: bool,
+    endianness: Endianness,
+    skip_empty_pages: bool,
+    block_size: usize,
+    stats: ReaderStats,
+    /// Block number of the last page returned
+    block: u64,
 }
 
 impl<R: Read + Seek> PageReader<R> {
@@ -17,66 +37,328 @@ impl<R: Read + Seek> PageReader<R> {
             reader,
             cursor: 0,
             ended: false,
+            endianness: Endianness::default(),
+            skip_empty_pages: false,
+            block_size: BLCKSZ as usize,
+            stats: ReaderStats::default(),
+            block: 0,
         }
     }
 
+    /// Skips the all-zero pages PostgreSQL leaves behind when a relation is extended but
+    /// the new blocks are never written. Otherwise they are returned as pages of the block
+    /// size with an all-zero header. Only sequential reads skip them; reading a block by its
+    /// number always returns that block.
+    pub fn skip_empty_pages(mut self, skip: bool) -> Self {
+        self.skip_empty_pages = skip;
+        self
+    }
+
+    /// Sets the block size of the relation, `BLCKSZ` by default. It places blocks read by
+    /// number and sizes all-zero pages, which record no size of their own; other pages are
+    /// read at the size their header gives.
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Reads pages written in the given byte order instead of little-endian. Big-endian
+    /// pages are converted as by [`PageLazy::convert_from_big_endian`]: the header, line
+    /// pointers and heap tuple headers, but not column values.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Reads the page starting at byte `offset`, leaving the cursor just past it. An all-zero
+    /// page there is returned even with `skip_empty_pages`, never the page after it.
     pub fn read_page_at(&mut self, offset: u64) -> ByteEncodeResult<Option<PageLazy>> {
-        self.reader.seek(std::io::SeekFrom::Start(offset))?;
-        self.read_next_page()
+        self.seek(offset)?;
+        self.ended = false;
+        self.read_page(|_| true, false)
+    }
+
+    /// Reads block `block_number` of a relation whose pages are `page_size` bytes. Nothing
+    /// on disk says how big a block is before it has been read, so the page size must be
+    /// known up front, usually `BLCKSZ` or the `page_size()` of block 0.
+    pub fn read_block(&mut self, block_number: u32, page_size: usize) -> ByteEncodeResult<Option<PageLazy>> {
+        self.read_page_at(block_number as u64 * page_size as u64)
+    }
+
+    /// Reads blocks 0, `stride`, 2 * `stride`, ... seeking over the rest, for estimates
+    /// over a huge relation without a full scan. Blocks are [`PageReader::block_size`] bytes.
+    /// Stops at the end of the file or after the first error.
+    ///
+    /// Panics if `stride` is 0.
+    pub fn sample_pages(&mut self, stride: u64) -> impl Iterator<Item = ByteEncodeResult<(u64, PageLazy)>> + '_ {
+        assert!(stride > 0, "sample stride must be positive");
+        let mut failed = false;
+        (0..).step_by(stride as usize).map_while(move |block: u64| {
+            if failed {
+                return None;
+            }
+            let page = BlockSource::read_block(self, block).transpose()?;
+            failed = page.is_err();
+            Some(page.map(|page| (block, page)))
+        })
     }
 
     pub fn cursor(&self) -> u64 {
         self.cursor
     }
 
+    pub fn stats(&self) -> ReaderStats {
+        self.stats
+    }
+
     pub fn seek(&mut self, offset: u64) -> ByteEncodeResult<()> {
         self.reader.seek(std::io::SeekFrom::Start(offset))?;
         self.cursor = offset;
         Ok(())
     }
 
+    /// Goes back to the start of the file so the reader can make another pass.
+    pub fn rewind(&mut self) -> ByteEncodeResult<()> {
+        self.seek(0)?;
+        self.ended = false;
+        Ok(())
+    }
+
     pub fn seek_relative(&mut self, offset: i64) -> ByteEncodeResult<()> {
         self.reader.seek_relative(offset)?;
         self.cursor = (self.cursor as i64 + offset) as u64;
         Ok(())
     }
 
+    /// Reads the next `block_size` bytes without interpreting them, for callers that must
+    /// cope with pages whose header can't be trusted. A block cut short by the end of the
+    /// file is returned as is, so it comes back shorter than `block_size`.
+    pub fn read_raw_block(&mut self, block_size: usize) -> ByteEncodeResult<Option<Vec<u8>>> {
+        let mut bytes = vec![0; block_size];
+        let filled = read_until_full(&mut bytes, &mut self.reader)?;
+        self.cursor += filled as u64;
+        if filled == 0 {
+            self.ended = true;
+            return Ok(None);
+        }
+        bytes.truncate(filled);
+        Ok(Some(bytes))
+    }
+
     pub fn read_next_page(&mut self) -> ByteEncodeResult<Option<PageLazy>> {
         self.read_next_page_filtered(|_| true)
     }
 
+    /// Like `read_next_page`, but also returns the page's block number, worked out from
+    /// where it starts in the file. Unlike a count of the pages returned, it stays right when
+    /// `skip_empty_pages` passes pages over.
+    pub fn read_next_block(&mut self) -> ByteEncodeResult<Option<(u64, PageLazy)>> {
+        Ok(self.read_next_page()?.map(|page| (self.block, page)))
+    }
+
+    /// Turns the reader into an iterator over its pages and their block numbers, as
+    /// returned by `read_next_block`.
+    pub fn into_blocks(mut self) -> impl Iterator<Item = ByteEncodeResult<(u64, PageLazy)>> {
+        std::iter::from_fn(move || self.read_next_block().transpose())
+    }
+
     pub fn read_next_page_filtered(&mut self, filter: impl Fn(&PageHeaderData) -> bool) -> ByteEncodeResult<Option<PageLazy>> {
+        self.read_page(filter, self.skip_empty_pages)
+    }
+
+    fn read_page(&mut self, filter: impl Fn(&PageHeaderData) -> bool, skip_empty_pages: bool) -> ByteEncodeResult<Option<PageLazy>> {
         if self.ended {
             return Ok(None);
         }
 
         let header_size = PageHeaderData::byte_size() as usize;
         let mut bytes = vec![0; header_size];
-        if read_exact_with_eof(&mut bytes, &mut self.reader)?.is_none() {
-            self.ended = true;
-            return Ok(None);
-        }
+        let (header_data, page_size) = loop {
+            if read_exact_with_eof(&mut bytes, &mut self.reader)?.is_none() {
+                self.ended = true;
+                return Ok(None);
+            }
 
-        let header_data = PageHeaderData::decode(&bytes)?;
-        let page_size = header_data.page_size();
+            self.stats.bytes_read += header_size as u64;
 
-        if !filter(&header_data) {
+            let header_data = PageHeaderData::decode_with_endianness(&bytes, self.endianness)?;
+            let page_size = page_size(&header_data, self.block_size)?;
+            if filter(&header_data) && !(skip_empty_pages && header_data.is_new_page()) {
+                break (header_data, page_size);
+            }
             self.reader.seek_relative((page_size - header_size) as i64)?;
             self.cursor += page_size as u64;
-            return self.read_next_page_filtered(filter);
-        }
+            self.stats.pages_skipped += 1;
+        };
 
         let mut data = vec![0; page_size - header_size];
+        // past the header the page must be complete
         if read_exact_with_eof(&mut data, &mut self.reader)?.is_none() {
-            self.ended = true;
-            return Ok(None);
+            return Err(ByteEncodeError::NotEnoughBytes {
+                expected: data.len(),
+                actual: 0,
+            });
         }
+        self.block = self.cursor / page_size as u64;
         self.cursor += page_size as u64;
+        self.stats.bytes_read += data.len() as u64;
+        self.stats.pages_read += 1;
 
-        Ok(Some(PageLazy {
+        let mut page = PageLazy {
             header_data,
             data,
-        }))
+        };
+        if self.endianness == Endianness::Big {
+            page.convert_from_big_endian()?;
+        }
+        Ok(Some(page))
+    }
+}
+
+impl PageReader<Cursor<Vec<u8>>> {
+    /// Reads pages from an owned in-memory image of a relation.
+    ///
+    /// ```
+    /// use pg_page::{
+    ///     dto::{PageHeaderData, PageXLogRecPtr},
+    ///     page_reader::PageReader,
+    ///     util::ByteEncoded,
+    /// };
+    ///
+    /// let header = PageHeaderData {
+    ///     pd_lsn: PageXLogRecPtr { xlogid: 0, xrecoff: 0 },
+    ///     pd_checksum: 0,
+    ///     pd_flags: 0,
+    ///     pd_lower: 24,
+    ///     pd_upper: 8192,
+    ///     pd_special: 8192,
+    ///     pd_pagesize_version: 8192 | 4,
+    ///     pd_prune_xid: 0,
+    /// };
+    /// let mut page = header.encode();
+    /// page.resize(8192, 0);
+    ///
+    /// let reader = PageReader::from_bytes([page.clone(), page].concat());
+    /// assert_eq!(reader.into_iter().count(), 2);
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        PageReader::new(Cursor::new(bytes))
+    }
+}
+
+impl<'a> PageReader<Cursor<&'a [u8]>> {
+    /// Reads pages from a borrowed in-memory image of a relation.
+    pub fn from_slice(bytes: &'a [u8]) -> Self {
+        PageReader::new(Cursor::new(bytes))
+    }
+}
+
+/// Size of the page a header starts. New pages have no size recorded and take `block_size`.
+fn page_size(header: &PageHeaderData, block_size: usize) -> ByteEncodeResult<usize> {
+    if header.is_new_page() {
+        return Ok(block_size);
+    }
+    let page_size = header.page_size();
+    if page_size < PageHeaderData::byte_size() as usize {
+        return Err(ByteEncodeError::InvalidSize {
+            expected: PageHeaderData::byte_size() as usize,
+            actual: page_size,
+        });
+    }
+    Ok(page_size)
+}
+
+/// Anything that can hand out a relation's pages by block number.
+pub trait BlockSource {
+    /// Reads block `block`, or `None` if it lies past the end of the relation.
+    fn read_block(&mut self, block: u64) -> ByteEncodeResult<Option<PageLazy>>;
+}
+
+impl<R: Read + Seek> BlockSource for PageReader<R> {
+    /// Blocks are [`PageReader::block_size`] bytes apart.
+    fn read_block(&mut self, block: u64) -> ByteEncodeResult<Option<PageLazy>> {
+        self.read_page_at(block * self.block_size as u64)
+    }
+}
+
+impl<R: Read + Seek> PageReader<R> {
+    /// Turns the reader into an iterator that knows how many pages remain.
+    ///
+    /// The page size is taken from the next page's header and the block count from the
+    /// remaining file length. A torn final block is not counted.
+    pub fn exact_iter(mut self) -> ByteEncodeResult<ExactPageIter<R>> {
+        let start = self.reader.stream_position()?;
+        let end = self.reader.seek(std::io::SeekFrom::End(0))?;
+        self.reader.seek(std::io::SeekFrom::Start(start))?;
+
+        let mut bytes = vec![0; PageHeaderData::byte_size() as usize];
+        let remaining = match read_exact_with_eof(&mut bytes, &mut self.reader)? {
+            Some(()) => {
+                let header = PageHeaderData::decode_with_endianness(&bytes, self.endianness)?;
+                let page_size = page_size(&header, self.block_size)? as u64;
+                ((end - start).checked_div(page_size).unwrap_or(0)) as usize
+            }
+            None => 0,
+        };
+        self.reader.seek(std::io::SeekFrom::Start(start))?;
+
+        Ok(ExactPageIter {
+            reader: self,
+            remaining,
+        })
+    }
+}
+
+pub struct ExactPageIter<R: Read + Seek> {
+    reader: PageReader<R>,
+    remaining: usize,
+}
+
+impl<R: Read + Seek> Iterator for ExactPageIter<R> {
+    type Item = ByteEncodeResult<PageLazy>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let page = self.reader.read_next_page().transpose();
+        if page.is_none() {
+            self.remaining = 0;
+        }
+        page
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<R: Read + Seek> ExactSizeIterator for ExactPageIter<R> {}
+
+impl<R: Read + Seek> PageReader<R> {
+    /// Iterates the pages that pass `filter`, calling `prerun` with the cursor before each read.
+    pub fn into_iter_filtered(
+        self,
+        filter: impl Fn(&PageHeaderData) -> bool + 'static,
+        prerun: impl Fn(u64) + 'static,
+    ) -> PageReaderIter<R> {
+        self.pages().with_filter(filter).with_prerun(prerun).into_iter()
+    }
+
+    /// Starts configuring an iteration over the pages; the settings are fixed once it is
+    /// turned into an iterator.
+    pub fn pages(self) -> PageReaderIterBuilder<R> {
+        PageReaderIterBuilder {
+            reader: self,
+            filter: Box::new(|_| true),
+            prerun: Box::new(|_| {}),
+            remaining: None,
+        }
     }
 }
 
@@ -89,19 +371,59 @@ impl<R: Read + Seek> IntoIterator for PageReader<R> {
     }
 }
 
+/// Configures a [`PageReaderIter`] before iteration starts.
+pub struct PageReaderIterBuilder<R: Read + Seek> {
+    reader: PageReader<R>,
+    filter: Box<dyn Fn(&PageHeaderData) -> bool>,
+    prerun: Box<dyn Fn(u64)>,
+    remaining: Option<usize>,
+}
+
+impl<R: Read + Seek> PageReaderIterBuilder<R> {
+    pub fn with_prerun(self, prerun: impl Fn(u64) + 'static) -> Self {
+        Self { prerun: Box::new(prerun), ..self }
+    }
+
+    pub fn with_filter(self, filter: impl Fn(&PageHeaderData) -> bool + 'static) -> Self {
+        Self { filter: Box::new(filter), ..self }
+    }
+
+    /// Ends the iteration once `n` pages have been returned, without reading past the last
+    /// of them. Pages rejected by the filter or skipped as empty don't count towards `n`,
+    /// so with either of those the first `n` pages returned can span more than `n` blocks.
+    pub fn take_pages(self, n: usize) -> Self {
+        Self { remaining: Some(n), ..self }
+    }
+}
+
+impl<R: Read + Seek> IntoIterator for PageReaderIterBuilder<R> {
+    type Item = ByteEncodeResult<PageLazy>;
+    type IntoIter = PageReaderIter<R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        PageReaderIter {
+            reader: self.reader,
+            filter: self.filter,
+            prerun: self.prerun,
+            remaining: self.remaining,
+        }
+    }
+}
+
 pub struct PageReaderIter<R: Read + Seek> {
     reader: PageReader<R>,
     filter: Box<dyn Fn(&PageHeaderData) -> bool>,
     prerun: Box<dyn Fn(u64)>,
+    remaining: Option<usize>,
 }
 
 impl<R: Read + Seek> PageReaderIter<R> {
     pub fn new(reader: PageReader<R>) -> Self {
-        PageReaderIter {
-            reader,
-            filter: Box::new(|_| true),
-            prerun: Box::new(|_| {}),
-        }
+        reader.pages().into_iter()
+    }
+
+    pub fn stats(&self) -> ReaderStats {
+        self.reader.stats()
     }
 }
 
@@ -109,17 +431,376 @@ impl<R: Read + Seek> Iterator for PageReaderIter<R> {
     type Item = ByteEncodeResult<PageLazy>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(remaining) = &mut self.remaining {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
         (self.prerun)(self.reader.cursor);
         self.reader.read_next_page_filtered(&self.filter).transpose()
     }
 }
 
-impl<R: Read + Seek> PageReaderIter<R> {
-    pub fn with_prerun(self, prerun: impl Fn(u64) + 'static) -> Self {
-        Self { prerun: Box::new(prerun), ..self }
+#[cfg(test)]
+mod tests {
+    use super::BlockSource;
+    use crate::{
+        dto::{ItemIdData, PageHeaderData, PageLazy},
+        test_util::{page_bytes, page_lazy, relation_reader, tuple},
+        util::{ByteEncodeError, ByteEncoded, Endianness},
+    };
+
+    #[test]
+    fn test_exact_iter_len() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let reader = relation_reader(&[page.clone(), page.clone(), page]);
+        let iter = reader.exact_iter().unwrap();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.map(Result::unwrap).count(), 3);
     }
 
-    pub fn with_filter(self, filter: impl Fn(&PageHeaderData) -> bool + 'static) -> Self {
-        Self { filter: Box::new(filter), ..self }
+    #[test]
+    fn test_exact_iter_torn_tail() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let reader = relation_reader(&[page.clone(), page.clone(), page[..100].to_vec()]);
+        let mut iter = reader.exact_iter().unwrap();
+        assert_eq!(iter.len(), 2);
+        assert!(iter.next().unwrap().is_ok());
+        assert_eq!(iter.len(), 1);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_read_next_page_eof() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        assert!(relation_reader(&[]).read_next_page().unwrap().is_none());
+
+        let mut reader = relation_reader(std::slice::from_ref(&page));
+        assert!(reader.read_next_page().unwrap().is_some());
+        assert!(reader.read_next_page().unwrap().is_none());
+
+        let mut reader = relation_reader(&[page.clone(), page[..page.len() - 1].to_vec()]);
+        assert!(reader.read_next_page().unwrap().is_some());
+        assert!(matches!(
+            reader.read_next_page(),
+            Err(ByteEncodeError::NotEnoughBytes { expected: 8168, actual: 8167 })
+        ));
+
+        let mut reader = relation_reader(&[page[..24].to_vec()]);
+        assert!(matches!(
+            reader.read_next_page(),
+            Err(ByteEncodeError::NotEnoughBytes { expected: 8168, actual: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_skip_empty_pages() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            vec![0; 8192],
+            page_bytes(&[tuple(2, vec![2])]),
+        ];
+        let xmins = |reader: super::PageReader<_>| {
+            reader
+                .into_iter()
+                .map(|page| page.unwrap().iter_tuples().next().map(|t| t.unwrap().1.t_xmin))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(xmins(relation_reader(&pages).skip_empty_pages(true)), vec![Some(1), Some(2)]);
+
+        let mut reader = relation_reader(&pages);
+        reader.read_next_page().unwrap();
+        let empty = reader.read_next_page().unwrap().unwrap();
+        assert!(empty.header_data.is_new_page());
+        assert_eq!(empty.to_bytes(), vec![0; 8192]);
+        assert_eq!(reader.cursor(), 2 * 8192);
+        assert_eq!(relation_reader(&pages).exact_iter().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_block_numbers_skipping_empty_pages() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            vec![0; 8192],
+            vec![0; 8192],
+            page_bytes(&[tuple(2, vec![2])]),
+        ];
+        let blocks: Vec<_> = relation_reader(&pages)
+            .skip_empty_pages(true)
+            .into_blocks()
+            .map(|res| {
+                let (block, page) = res.unwrap();
+                (block, page.iter_tuples().next().unwrap().unwrap().1.t_xmin)
+            })
+            .collect();
+        assert_eq!(blocks, vec![(0, 1), (3, 2)]);
+
+        let blocks: Vec<_> = relation_reader(&pages).into_blocks().map(|res| res.unwrap().0).collect();
+        assert_eq!(blocks, vec![0, 1, 2, 3]);
+
+        // reading a zero block by number returns it rather than the next page
+        let mut reader = relation_reader(&pages).skip_empty_pages(true);
+        assert!(BlockSource::read_block(&mut reader, 1).unwrap().unwrap().header_data.is_new_page());
+        assert_eq!(reader.cursor(), 2 * 8192);
+        assert!(reader.read_page_at(2 * 8192).unwrap().unwrap().header_data.is_new_page());
+    }
+
+    #[test]
+    fn test_undersized_page() {
+        let mut page = page_bytes(&[]);
+        page[18] = 0x10;
+        page[19] = 0x00;
+        assert!(matches!(
+            relation_reader(&[page]).read_next_page(),
+            Err(ByteEncodeError::InvalidSize { expected: 24, actual: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let pages = [page_bytes(&[tuple(1, vec![1])]), page_bytes(&[tuple(2, vec![2])])].concat();
+        let mut reader = super::PageReader::from_slice(&pages);
+        assert_eq!(reader.read_next_page().unwrap().unwrap().to_bytes(), pages[..8192]);
+        assert_eq!(reader.read_next_page().unwrap().unwrap().to_bytes(), pages[8192..]);
+        assert!(reader.read_next_page().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_take_pages() {
+        use std::{
+            cell::Cell,
+            io::{Cursor, Read, Seek, SeekFrom},
+            rc::Rc,
+        };
+
+        /// Counts the bytes pulled from the underlying reader.
+        struct CountingReader {
+            inner: Cursor<Vec<u8>>,
+            read: Rc<Cell<usize>>,
+        }
+        impl Read for CountingReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.inner.read(buf)?;
+                self.read.set(self.read.get() + n);
+                Ok(n)
+            }
+        }
+        impl Seek for CountingReader {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let read = Rc::new(Cell::new(0));
+        let reader = super::PageReader::new(CountingReader {
+            inner: Cursor::new(page.repeat(100)),
+            read: read.clone(),
+        });
+        let reads = Rc::new(Cell::new(0));
+        let counter = reads.clone();
+        let pages = reader
+            .pages()
+            .with_prerun(move |_| counter.set(counter.get() + 1))
+            .take_pages(5)
+            .into_iter()
+            .map(Result::unwrap)
+            .count();
+
+        assert_eq!(pages, 5);
+        assert_eq!(reads.get(), 5);
+        assert_eq!(read.get(), 5 * 8192);
+    }
+
+    #[test]
+    fn test_stats() {
+        let pages: Vec<_> = (0..10_u32)
+            .map(|i| {
+                let mut page = page_bytes(&[tuple(i, vec![1])]);
+                // pd_prune_xid
+                page[20..24].copy_from_slice(&(i % 2).to_le_bytes());
+                page
+            })
+            .collect();
+        let mut iter = relation_reader(&pages)
+            .pages()
+            .with_filter(|header| header.pd_prune_xid == 0)
+            .into_iter();
+        assert_eq!(iter.by_ref().map(Result::unwrap).count(), 5);
+        assert_eq!(
+            iter.stats(),
+            super::ReaderStats {
+                pages_read: 5,
+                pages_skipped: 5,
+                bytes_read: 5 * 8192 + 5 * 24,
+            }
+        );
+    }
+
+    #[test]
+    fn test_with_filter() {
+        // pages with an even xmin hold a second tuple, so their pd_lower is 32
+        let pages: Vec<_> = (1..=4)
+            .map(|xmin| page_bytes(&vec![tuple(xmin, vec![1]); 1 + (xmin % 2 == 0) as usize]))
+            .collect();
+        let xmin = |page: PageLazy| page.iter_tuples().next().unwrap().unwrap().1.t_xmin;
+
+        let filtered: Vec<_> = relation_reader(&pages)
+            .pages()
+            .with_filter(|header| header.pd_lower == 28)
+            .into_iter()
+            .map(|page| xmin(page.unwrap()))
+            .collect();
+        assert_eq!(filtered, vec![1, 3]);
+
+        let cursors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = cursors.clone();
+        let even: Vec<_> = relation_reader(&pages)
+            .into_iter_filtered(|header| header.pd_lower == 32, move |cursor| seen.borrow_mut().push(cursor))
+            .map(|page| xmin(page.unwrap()))
+            .collect();
+        assert_eq!(even, vec![2, 4]);
+        // one call per next(), each starting where the previous returned page ended
+        assert_eq!(*cursors.borrow(), vec![0, 2 * 8192, 4 * 8192]);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            page_bytes(&[tuple(2, vec![2])]),
+        ];
+        let mut reader = relation_reader(&pages);
+        let first = reader.read_next_page().unwrap().unwrap();
+        reader.read_next_page().unwrap().unwrap();
+        assert!(reader.read_next_page().unwrap().is_none());
+
+        reader.rewind().unwrap();
+        assert_eq!(reader.cursor(), 0);
+        assert_eq!(reader.read_next_page().unwrap().unwrap(), first);
+    }
+
+    #[test]
+    fn test_read_block_by_number() {
+        let pages: Vec<_> = (1..=4).map(|xmin| page_bytes(&[tuple(xmin, vec![1])])).collect();
+        let sequential: Vec<_> = relation_reader(&pages).into_iter().map(Result::unwrap).collect();
+
+        let mut reader = relation_reader(&pages);
+        assert_eq!(reader.read_block(2, 8192).unwrap().unwrap(), sequential[2]);
+        assert_eq!(reader.cursor(), 3 * 8192);
+        assert_eq!(reader.read_next_page().unwrap().unwrap(), sequential[3]);
+        assert!(reader.read_block(4, 8192).unwrap().is_none());
+        assert_eq!(reader.read_block(0, 8192).unwrap().unwrap(), sequential[0]);
+    }
+
+    #[test]
+    fn test_exact_iter_empty() {
+        let reader = relation_reader(&[]);
+        assert_eq!(reader.exact_iter().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_read_block() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            page_bytes(&[tuple(2, vec![2])]),
+        ];
+        let mut reader = relation_reader(&pages);
+        let page = BlockSource::read_block(&mut reader, 1).unwrap().unwrap();
+        assert_eq!(page.iter_tuples().next().unwrap().unwrap().1.t_xmin, 2);
+        let page = BlockSource::read_block(&mut reader, 0).unwrap().unwrap();
+        assert_eq!(page.iter_tuples().next().unwrap().unwrap().1.t_xmin, 1);
+        assert!(BlockSource::read_block(&mut reader, 2).unwrap().is_none());
+        assert!(BlockSource::read_block(&mut reader, 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_read_block_with_block_size() {
+        let page = |xmin| {
+            let mut page = page_lazy(&page_bytes(&[]));
+            page.header_data.pd_pagesize_version = 4096 | 4;
+            page.header_data.pd_upper = 4096;
+            page.header_data.pd_special = 4096;
+            page.data.truncate(4096 - 24);
+            let item_id = page.reserve_tuple(1).unwrap();
+            page.write_tuple(&item_id, &tuple(xmin, vec![1])).unwrap();
+            page.to_bytes()
+        };
+        let mut reader = relation_reader(&[page(1), vec![0; 4096], page(3)]).with_block_size(4096);
+        assert_eq!(reader.block_size(), 4096);
+        let page = BlockSource::read_block(&mut reader, 2).unwrap().unwrap();
+        assert_eq!(page.iter_tuples().next().unwrap().unwrap().1.t_xmin, 3);
+        // an all-zero block has no size of its own and takes the reader's
+        assert_eq!(BlockSource::read_block(&mut reader, 1).unwrap().unwrap().to_bytes(), vec![0; 4096]);
+        assert!(BlockSource::read_block(&mut reader, 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_raw_block() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let mut reader = relation_reader(&[vec![0; 8192], page[..100].to_vec()]);
+        assert_eq!(reader.read_raw_block(8192).unwrap().unwrap(), vec![0; 8192]);
+        assert_eq!(reader.read_raw_block(8192).unwrap().unwrap(), page[..100]);
+        assert!(reader.read_raw_block(8192).unwrap().is_none());
+        assert_eq!(reader.cursor(), 8292);
+    }
+
+    #[test]
+    fn test_big_endian_page() {
+        let mut updated = tuple(0x0102_0304, vec![4]);
+        updated.t_xmax = 0x0506_0708;
+        updated.t_ctid.ip_blkid.bi_lo = 0x0203;
+        updated.t_ctid.ip_posid = 2;
+        updated.t_infomask = 0x0900;
+        let tuples = [tuple(1, vec![1, 2, 3]), updated];
+        let page = page_bytes(&tuples);
+        let header = PageHeaderData::decode(&page).unwrap();
+
+        // lay the page out by hand as a big-endian server writes it
+        let mut big = page.clone();
+        big[..24].copy_from_slice(&header.swap_bytes().encode());
+        for offset in 0..tuples.len() {
+            let lp = 24 + offset * 4;
+            let item_id = ItemIdData::decode(&page[lp..lp + 4]).unwrap();
+            let word = (item_id.lp_off() as u32) << 17 | 1 << 15 | item_id.lp_len() as u32;
+            big[lp..lp + 4].copy_from_slice(&word.to_be_bytes());
+            let off = item_id.lp_off() as usize;
+            for (from, to) in [(0, 4), (4, 8), (8, 12), (12, 14), (14, 16), (16, 18), (18, 20), (20, 22)] {
+                big[off + from..off + to].reverse();
+            }
+        }
+        assert_ne!(big, page);
+
+        let mut reader = relation_reader(&[big.clone()]).with_endianness(Endianness::Big);
+        let read = reader.read_next_page().unwrap().unwrap();
+        assert_eq!(read.header_data, header);
+        assert_eq!(read.to_bytes(), page);
+        let read_tuples: Vec<_> = read.iter_tuples().map(|res| res.unwrap().1).collect();
+        assert_eq!(read_tuples, tuples);
+        assert_eq!(read.to_big_endian_bytes().unwrap(), big);
+        assert!(reader.read_next_page().unwrap().is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sample_pages() {
+        let pages: Vec<_> = (0..100).map(|xmin| page_bytes(&[tuple(xmin, vec![1])])).collect();
+        let mut reader = relation_reader(&pages);
+        let sampled: Vec<_> = reader
+            .sample_pages(10)
+            .map(|res| {
+                let (block, page) = res.unwrap();
+                (block, page.iter_tuples().next().unwrap().unwrap().1.t_xmin)
+            })
+            .collect();
+
+        assert_eq!(sampled.len(), 10);
+        assert_eq!(sampled, (0..100).step_by(10).map(|block| (block, block as u32)).collect::<Vec<_>>());
+        assert_eq!(reader.stats().pages_read, 10);
+
+        assert_eq!(reader.sample_pages(30).count(), 4);
+        assert_eq!(reader.sample_pages(1).count(), 100);
+    }
+}
@@ -0,0 +1,120 @@
+//! A non-blocking counterpart of [`PageReader`](super::PageReader) over tokio's async IO traits.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, BufReader};
+
+use crate::{
+    compile_constants::BLCKSZ,
+    dto::{PageHeaderData, PageLazy},
+    util::{check_filled, ByteEncodeError, ByteEncodeResult, ByteEncoded},
+};
+
+use super::page_size;
+
+pub struct AsyncPageReader<R: AsyncRead + AsyncSeek + Unpin> {
+    reader: BufReader<R>,
+    cursor: u64,
+    ended: bool,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncPageReader<R> {
+    pub fn new(reader: R) -> Self {
+        AsyncPageReader {
+            reader: BufReader::new(reader),
+            cursor: 0,
+            ended: false,
+        }
+    }
+
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Reads the page starting at byte `offset`, leaving the cursor just past it.
+    pub async fn read_page_at(&mut self, offset: u64) -> ByteEncodeResult<Option<PageLazy>> {
+        self.reader.seek(std::io::SeekFrom::Start(offset)).await?;
+        self.cursor = offset;
+        self.ended = false;
+        self.read_next_page().await
+    }
+
+    pub async fn read_next_page(&mut self) -> ByteEncodeResult<Option<PageLazy>> {
+        if self.ended {
+            return Ok(None);
+        }
+
+        let header_size = PageHeaderData::byte_size() as usize;
+        let mut bytes = vec![0; header_size];
+        if read_exact_with_eof(&mut bytes, &mut self.reader).await?.is_none() {
+            self.ended = true;
+            return Ok(None);
+        }
+        let header_data = PageHeaderData::decode(&bytes)?;
+        let page_size = page_size(&header_data, BLCKSZ as usize)?;
+
+        let mut data = vec![0; page_size - header_size];
+        // past the header the page must be complete
+        if read_exact_with_eof(&mut data, &mut self.reader).await?.is_none() {
+            return Err(ByteEncodeError::NotEnoughBytes {
+                expected: data.len(),
+                actual: 0,
+            });
+        }
+        self.cursor += page_size as u64;
+
+        Ok(Some(PageLazy { header_data, data }))
+    }
+}
+
+/// Async version of [`crate::util::read_exact_with_eof`]: `None` only if nothing was left to read.
+async fn read_exact_with_eof(
+    bytes: &mut [u8],
+    reader: &mut (impl AsyncRead + Unpin),
+) -> ByteEncodeResult<Option<()>> {
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match reader.read(&mut bytes[filled..]).await {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    check_filled(filled, bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, tuple};
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn test_read_pages() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            page_bytes(&[tuple(2, vec![2])]),
+        ];
+        let mut reader = AsyncPageReader::new(Cursor::new(pages.concat()));
+
+        let first = reader.read_next_page().await.unwrap().unwrap();
+        assert_eq!(first.to_bytes(), pages[0]);
+        let second = reader.read_next_page().await.unwrap().unwrap();
+        assert_eq!(second.to_bytes(), pages[1]);
+        assert_eq!(reader.cursor(), 2 * 8192);
+        assert!(reader.read_next_page().await.unwrap().is_none());
+
+        let again = reader.read_page_at(8192).await.unwrap().unwrap();
+        assert_eq!(again, second);
+        assert!(reader.read_next_page().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_torn_page() {
+        let page = page_bytes(&[tuple(1, vec![1])]);
+        let mut reader = AsyncPageReader::new(Cursor::new(page[..100].to_vec()));
+        assert!(matches!(
+            reader.read_next_page().await,
+            Err(ByteEncodeError::NotEnoughBytes { expected: 8168, actual: 76 })
+        ));
+    }
+}
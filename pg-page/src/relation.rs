@@ -0,0 +1,293 @@
+//! A relation as PostgreSQL stores it on disk: segment files `<filenode>`, `<filenode>.1`,
+//! `<filenode>.2`, ... of RELSEG_SIZE blocks each, the last one possibly shorter.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    compile_constants::{BLCKSZ, RELSEG_SIZE},
+    dto::PageLazy,
+    page_reader::{BlockSource, PageReader},
+    util::ByteEncodeResult,
+};
+
+/// Reads the blocks of a relation by block number, opening segment files as they are needed.
+pub struct Relation {
+    base_path: PathBuf,
+    segment_size: u32,
+    segments: HashMap<u32, PageReader<File>>,
+}
+
+impl Relation {
+    /// `base_path` is the first segment, such as `base/5/16384`.
+    pub fn new(base_path: impl AsRef<Path>) -> Self {
+        Relation {
+            base_path: base_path.as_ref().to_path_buf(),
+            segment_size: RELSEG_SIZE,
+            segments: HashMap::new(),
+        }
+    }
+
+    /// Sets the number of blocks per segment, for clusters built with a non-default RELSEG_SIZE.
+    pub fn with_segment_size(mut self, segment_size: u32) -> Self {
+        self.segment_size = segment_size;
+        self
+    }
+
+    /// Path of segment `segment`: the base path for the first, `<base>.<n>` after it.
+    pub fn segment_path(&self, segment: u32) -> PathBuf {
+        if segment == 0 {
+            return self.base_path.clone();
+        }
+        let mut path = self.base_path.clone().into_os_string();
+        path.push(format!(".{}", segment));
+        path.into()
+    }
+
+    /// Reads block `block_number`, or `None` if the relation ends before it.
+    pub fn read_block(&mut self, block_number: u32) -> ByteEncodeResult<Option<PageLazy>> {
+        let segment = block_number / self.segment_size;
+        if !self.segments.contains_key(&segment) {
+            match File::open(self.segment_path(segment)) {
+                Ok(file) => {
+                    self.segments.insert(segment, PageReader::new(file));
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let reader = self.segments.get_mut(&segment).expect("segment opened above");
+        reader.read_block(block_number % self.segment_size, BLCKSZ as usize)
+    }
+
+    /// Every block of the relation in order, across segments, up to the first missing one.
+    /// Stops after the first error.
+    pub fn blocks(&mut self) -> impl Iterator<Item = ByteEncodeResult<PageLazy>> + '_ {
+        let mut failed = false;
+        (0..=u32::MAX).map_while(move |block_number| {
+            if failed {
+                return None;
+            }
+            let page = self.read_block(block_number).transpose();
+            failed = matches!(page, Some(Err(_)));
+            page
+        })
+    }
+
+    /// Every block of the relation as one stream of `(block number, page)`, numbered
+    /// continuously across segment boundaries. Unlike [`Relation::blocks`], each segment
+    /// is read sequentially. The stream ends at the first missing segment, after a segment
+    /// shorter than the segment size, where Postgres considers the relation to end, and
+    /// after the first error.
+    pub fn iter_all_blocks(self) -> impl Iterator<Item = ByteEncodeResult<(u64, PageLazy)>> {
+        let mut segment = 0;
+        let mut pages = None;
+        let mut block_in_segment = 0_u32;
+        let mut done = false;
+        std::iter::from_fn(move || loop {
+            if done {
+                return None;
+            }
+            let segment_pages = match &mut pages {
+                Some(segment_pages) => segment_pages,
+                None => match File::open(self.segment_path(segment)) {
+                    Ok(file) => {
+                        block_in_segment = 0;
+                        pages.insert(PageReader::new(file).into_iter())
+                    }
+                    Err(err) => {
+                        done = true;
+                        return (err.kind() != ErrorKind::NotFound).then(|| Err(err.into()));
+                    }
+                },
+            };
+            match segment_pages.next() {
+                Some(Ok(page)) => {
+                    let block = segment as u64 * self.segment_size as u64 + block_in_segment as u64;
+                    block_in_segment += 1;
+                    return Some(Ok((block, page)));
+                }
+                Some(Err(err)) => {
+                    done = true;
+                    return Some(Err(err));
+                }
+                None if block_in_segment < self.segment_size => done = true,
+                None => {
+                    pages = None;
+                    segment += 1;
+                }
+            }
+        })
+    }
+}
+
+impl BlockSource for Relation {
+    /// Block numbers are 32 bits, so there is no block past `u32::MAX`.
+    fn read_block(&mut self, block: u64) -> ByteEncodeResult<Option<PageLazy>> {
+        match u32::try_from(block) {
+            Ok(block) => Relation::read_block(self, block),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Filenode and segment number of a main fork segment file name, `<filenode>[.<segno>]`.
+fn main_fork_segment(name: &str) -> Option<(u32, u32)> {
+    let (filenode, segno) = name.split_once('.').unwrap_or((name, "0"));
+    let parse = |s: &str| s.bytes().all(|b| b.is_ascii_digit()).then(|| s.parse().ok()).flatten();
+    Some((parse(filenode)?, parse(segno)?))
+}
+
+/// The main fork segment files of every relation in a database directory such as
+/// `base/5`, ordered by filenode then segment. Other forks and non-relation files are left out.
+pub fn main_fork_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let segment = entry.file_name().to_str().and_then(main_fork_segment);
+        if let Some(segment) = segment {
+            if entry.file_type()?.is_file() {
+                files.push((segment, entry.path()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Calls `f` with the segment file path, the block number within the relation and the
+/// page, for every page of every main fork file in `dir`. Each file is read in order;
+/// with the `rayon` feature files are spread across threads.
+///
+/// Stops at the first error, though with `rayon` other files may still be in progress.
+pub fn scan_directory(dir: &Path, f: impl Fn(&Path, u64, &PageLazy) + Sync) -> ByteEncodeResult<()> {
+    let scan_file = |path: &PathBuf| -> ByteEncodeResult<()> {
+        let (_, segno) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(main_fork_segment)
+            .expect("listed by main_fork_files");
+        let block_start = segno as u64 * RELSEG_SIZE as u64;
+        for (block, page) in PageReader::new(File::open(path)?).into_iter().enumerate() {
+            f(path, block_start + block as u64, &page?);
+        }
+        Ok(())
+    };
+
+    let files = main_fork_files(dir)?;
+    #[cfg(feature = "rayon")]
+    return files.par_iter().try_for_each(scan_file);
+    #[cfg(not(feature = "rayon"))]
+    return files.iter().try_for_each(scan_file);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page_bytes, tuple, write_segments};
+
+    fn xmin(page: &PageLazy) -> u32 {
+        page.iter_tuples().next().unwrap().unwrap().1.t_xmin
+    }
+
+    #[test]
+    fn test_segment_path() {
+        let relation = Relation::new("base/5/16384");
+        assert_eq!(relation.segment_path(0), PathBuf::from("base/5/16384"));
+        assert_eq!(relation.segment_path(2), PathBuf::from("base/5/16384.2"));
+    }
+
+    #[test]
+    fn test_read_block_across_segments() {
+        let page = |xmin| page_bytes(&[tuple(xmin, vec![1])]);
+        // a full segment of two blocks, then a short last segment
+        let base = write_segments("relation-segments", &[vec![page(1), page(2)], vec![page(3)]]);
+        let mut relation = Relation::new(&base).with_segment_size(2);
+
+        assert_eq!(xmin(&relation.read_block(2).unwrap().unwrap()), 3);
+        assert_eq!(xmin(&relation.read_block(1).unwrap().unwrap()), 2);
+        assert!(relation.read_block(3).unwrap().is_none());
+        assert!(relation.read_block(4).unwrap().is_none());
+        // would wrap around to block 2 if truncated to 32 bits
+        assert!(BlockSource::read_block(&mut relation, (1 << 32) + 2).unwrap().is_none());
+
+        let xmins: Vec<_> = relation.blocks().map(|page| xmin(&page.unwrap())).collect();
+        assert_eq!(xmins, vec![1, 2, 3]);
+
+        std::fs::remove_dir_all(base.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_iter_all_blocks() {
+        let page = |xmin| page_bytes(&[tuple(xmin, vec![1])]);
+        let base = write_segments(
+            "iter-all-blocks",
+            &[vec![page(1), page(2)], vec![page(3), page(4)], vec![page(5)]],
+        );
+
+        let blocks: Vec<_> = Relation::new(&base)
+            .with_segment_size(2)
+            .iter_all_blocks()
+            .map(|block| block.map(|(block, page)| (block, xmin(&page))).unwrap())
+            .collect();
+        assert_eq!(blocks, vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]);
+
+        // a short segment ends the relation, whatever follows it
+        let blocks: Vec<_> = Relation::new(&base).with_segment_size(3).iter_all_blocks().collect();
+        assert_eq!(blocks.len(), 2);
+
+        std::fs::remove_dir_all(base.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_main_fork_segment() {
+        assert_eq!(main_fork_segment("16384"), Some((16384, 0)));
+        assert_eq!(main_fork_segment("16384.2"), Some((16384, 2)));
+        assert_eq!(main_fork_segment("16384_fsm"), None);
+        assert_eq!(main_fork_segment("16384_vm.1"), None);
+        assert_eq!(main_fork_segment("PG_VERSION"), None);
+        assert_eq!(main_fork_segment("+1"), None);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let page = |xmin| page_bytes(&[tuple(xmin, vec![1])]);
+        let base = write_segments("scan-directory", &[vec![page(1), page(2)], vec![page(3)]]);
+        let dir = base.parent().unwrap();
+        std::fs::write(dir.join("16390"), page(4)).unwrap();
+        std::fs::write(dir.join("16384_fsm"), page(5)).unwrap();
+        std::fs::write(dir.join("PG_VERSION"), "16\n").unwrap();
+
+        assert_eq!(
+            main_fork_files(dir).unwrap(),
+            vec![dir.join("16384"), dir.join("16384.1"), dir.join("16390")]
+        );
+
+        let seen = std::sync::Mutex::new(Vec::new());
+        scan_directory(dir, |path, block, page| {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            seen.lock().unwrap().push((name, block, xmin(page)));
+        })
+        .unwrap();
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                ("16384".to_string(), 0, 1),
+                ("16384".to_string(), 1, 2),
+                ("16384.1".to_string(), RELSEG_SIZE as u64, 3),
+                ("16390".to_string(), 0, 4),
+            ]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
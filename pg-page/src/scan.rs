@@ -0,0 +1,1082 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Seek, Write},
+};
+
+use digest::{Digest, Output};
+
+use crate::{
+    checksum::pg_checksum_page,
+    dto::{
+        deserialize_attrs, AttrDesc, BlockIdData, Datum, HeapTupleHeaderData, ItemIdData, ItemPointerData,
+        PageHeaderData, PageLazy, TupleDesc, HEAP_XMAX_COMMITTED, HEAP_XMAX_IS_MULTI, HEAP_XMAX_LOCK_ONLY,
+        HEAP_XMIN_FROZEN, HEAP_XMIN_INVALID, PD_ALL_VISIBLE,
+    },
+    page_reader::{BlockSource, PageReader},
+    util::{
+        varlena::{read_varlena, VarlenaHeader},
+        ByteEncodeError, ByteEncodeResult, ByteEncoded, GetByteSliceExt,
+    },
+    Error,
+};
+
+/// Collects every normal tuple of a relation together with its TID, sorted in TID order
+/// (block number, then offset number), matching the order of `ctid` in Postgres.
+pub fn tuples_by_tid<R: Read + Seek>(
+    reader: PageReader<R>,
+) -> Result<Vec<(ItemPointerData, HeapTupleHeaderData)>, Error> {
+    let mut tuples = Vec::new();
+    for res in reader.into_blocks() {
+        let (block, page) = res?;
+        let block = u32::try_from(block)
+            .map_err(|_| Error::InvalidByteEncoding(format!("block number {} does not fit 32 bits", block)))?;
+        let mut iter = page.iter_tuples();
+        while let Some(res) = iter.next() {
+            let (_, tuple) = res?;
+            let tid = ItemPointerData {
+                ip_blkid: BlockIdData::from_block_number(block),
+                ip_posid: iter.offset_number(),
+            };
+            tuples.push((tid, tuple));
+        }
+    }
+    tuples.sort_by_key(|(tid, _)| *tid);
+
+    Ok(tuples)
+}
+
+/// Groups the tuples of a relation into update chains: each chain starts at the oldest
+/// version found and follows `t_ctid` links to the newest, fetching each successor by TID
+/// through `source`. With a [`Relation`](crate::relation::Relation) as the source, chains
+/// are followed across segment files.
+///
+/// A link is followed only if the target's xmin matches the previous version's xmax and
+/// the target has no more attributes than `desc`, so it can be a version of the same row.
+/// Chains end at a self-pointing tuple, a moved-partitions sentinel, a missing target, or a
+/// version already seen. Chains are returned in TID order of their first version.
+pub fn update_chains<S: BlockSource>(
+    source: &mut S,
+    desc: &TupleDesc,
+) -> ByteEncodeResult<Vec<Vec<(ItemPointerData, HeapTupleHeaderData)>>> {
+    let mut tuples = BTreeMap::new();
+    for block in 0..=u32::MAX {
+        let Some(page) = source.read_block(block as u64)? else {
+            break;
+        };
+        for offset in 1..=page.line_pointer_count() {
+            let tid = ItemPointerData {
+                ip_blkid: BlockIdData::from_block_number(block),
+                ip_posid: offset,
+            };
+            if let Some(tuple) = heap_tuple_at(&page, offset)? {
+                tuples.insert(tid, tuple);
+            }
+        }
+    }
+
+    let mut successors = BTreeMap::new();
+    for (tid, tuple) in &tuples {
+        let next = tuple.t_ctid;
+        if next == *tid || next.is_moved_partitions() {
+            continue;
+        }
+        let is_successor = deref_tid(source, &next)?.is_some_and(|next_tuple| {
+            next_tuple.t_xmin == tuple.t_xmax && next_tuple.natts() as usize <= desc.attrs.len()
+        });
+        if is_successor {
+            successors.insert(*tid, next);
+        }
+    }
+    let targets: BTreeSet<_> = successors.values().collect();
+    // versions no other version leads to come first, then anything left over in a cycle
+    let heads = tuples
+        .keys()
+        .filter(|tid| !targets.contains(tid))
+        .chain(tuples.keys().filter(|tid| targets.contains(tid)));
+
+    let mut visited = BTreeSet::new();
+    let mut chains = Vec::new();
+    for head in heads {
+        let mut chain = Vec::new();
+        let mut next = Some(*head);
+        while let Some(tid) = next {
+            if !visited.insert(tid) {
+                break;
+            }
+            chain.push((tid, tuples[&tid].clone()));
+            next = successors.get(&tid).copied();
+        }
+        if !chain.is_empty() {
+            chains.push(chain);
+        }
+    }
+
+    Ok(chains)
+}
+
+/// The heap tuple `tid` points at, read through `source`, or `None` if the block doesn't
+/// exist or the line pointer isn't a normal one.
+fn deref_tid<S: BlockSource>(source: &mut S, tid: &ItemPointerData) -> ByteEncodeResult<Option<HeapTupleHeaderData>> {
+    match source.read_block(tid.block_number() as u64)? {
+        Some(page) => heap_tuple_at(&page, tid.ip_posid),
+        None => Ok(None),
+    }
+}
+
+/// The tuple behind line pointer `offset` of `page`, if it is a normal one.
+fn heap_tuple_at(page: &PageLazy, offset: u16) -> ByteEncodeResult<Option<HeapTupleHeaderData>> {
+    if offset == 0 || offset > page.line_pointer_count() {
+        return Ok(None);
+    }
+    let lp_start = (offset as usize - 1) * ItemIdData::byte_size() as usize;
+    let item_id = ItemIdData::decode(page.data.get_byte_slice(lp_start, lp_start + ItemIdData::byte_size() as usize)?)?;
+    if !item_id.is_normal() {
+        return Ok(None);
+    }
+    let header_size = PageHeaderData::byte_size() as usize;
+    let start = (item_id.lp_off() as usize).checked_sub(header_size).ok_or(
+        ByteEncodeError::InvalidSize {
+            expected: header_size,
+            actual: item_id.lp_off() as usize,
+        }
+        .at("ItemIdData.lp_off"),
+    )?;
+    let bytes = page.data.get_byte_slice(start, start + item_id.lp_len() as usize)?;
+    HeapTupleHeaderData::decode(bytes).map(Some)
+}
+
+/// One step of `scan_events`: page boundaries with the tuples found between them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScanEvent {
+    PageStart { block: u64, header: PageHeaderData },
+    Tuple { block: u64, offset: u16, tuple: HeapTupleHeaderData },
+    PageEnd { block: u64 },
+}
+
+/// Streams a relation as a flat sequence of events, for exporters that need to know where
+/// pages begin and end. A page that fails to decode yields its error in place of the
+/// remaining events of that page; the scan then moves on to the next page.
+pub fn scan_events<R: Read + Seek>(reader: PageReader<R>) -> impl Iterator<Item = Result<ScanEvent, Error>> {
+    reader.into_blocks().flat_map(|res| {
+        let (block, page) = match res {
+            Ok(block_page) => block_page,
+            Err(err) => return vec![Err(err.into())],
+        };
+        let mut events = vec![Ok(ScanEvent::PageStart {
+            block,
+            header: page.header_data,
+        })];
+        let mut iter = page.iter_tuples();
+        while let Some(res) = iter.next() {
+            match res {
+                Ok((_, tuple)) => events.push(Ok(ScanEvent::Tuple {
+                    block,
+                    offset: iter.offset_number(),
+                    tuple,
+                })),
+                Err(err) => {
+                    events.push(Err(err));
+                    return events;
+                }
+            }
+        }
+        events.push(Ok(ScanEvent::PageEnd { block }));
+        events
+    })
+}
+
+/// Hashes the raw bytes of every page of a relation, so two copies can be compared cheaply.
+///
+/// With `normalize_hint_bits` the tuple hint bits, PD_ALL_VISIBLE and page checksums are
+/// zeroed before hashing, so relations that only differ in which hints have been set yet
+/// hash the same. A page whose line pointers can't be followed fails the digest.
+pub fn relation_digest<R: Read + Seek, D: Digest>(
+    reader: PageReader<R>,
+    mut hasher: D,
+    normalize_hint_bits: bool,
+) -> Result<Output<D>, Error> {
+    for page in reader {
+        let mut page = page?;
+        if normalize_hint_bits {
+            page.clear_hint_bits()?;
+            page.header_data.pd_flags &= !PD_ALL_VISIBLE;
+        }
+        hasher.update(page.header_data.encode());
+        hasher.update(&page.data);
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Block numbers of pages with line pointers free for reuse: the page has PD_HAS_FREE_LINES
+/// set, or an unused line pointer is found on it.
+pub fn pages_with_free_slots<R: Read + Seek>(reader: PageReader<R>) -> Result<Vec<u64>, Error> {
+    let mut blocks = Vec::new();
+    for res in reader.into_blocks() {
+        let (block, page) = res?;
+        let mut has_free_slot = page.header_data.has_free_line_pointers();
+        for offset in 1..=page.line_pointer_count() {
+            if has_free_slot {
+                break;
+            }
+            has_free_slot = page.item_id(offset)?.is_unused();
+        }
+        if has_free_slot {
+            blocks.push(block);
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Block numbers of pages whose `pd_lsn` is at or past `redo_lsn`: the pages crash recovery
+/// starting from that redo point would replay records into. Pages of unlogged relations,
+/// with a zero LSN, are never included.
+pub fn blocks_needing_replay<R: Read + Seek>(reader: PageReader<R>, redo_lsn: u64) -> Result<Vec<u64>, Error> {
+    let mut blocks = Vec::new();
+    for res in reader.into_blocks() {
+        let (block, page) = res?;
+        if page.modified_after_checkpoint(redo_lsn) {
+            blocks.push(block);
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Sum of `lp_len` over the tuples that are not dead as of `oldest_xid`: the logical size
+/// of the relation's data, without dead tuples, line pointers, page headers or free space.
+/// Against the file size this gives a bloat ratio. Dead and unused line pointers count for
+/// nothing, and tuples are classified as [`HeapTupleHeaderData::is_dead`] does.
+pub fn live_tuple_bytes<R: Read + Seek>(reader: PageReader<R>, oldest_xid: u32) -> Result<u64, Error> {
+    let mut bytes = 0;
+    for page in reader {
+        for tuple in page?.iter_tuples() {
+            let (item_id, tuple) = tuple?;
+            if !tuple.is_dead(oldest_xid) {
+                bytes += item_id.lp_len() as u64;
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// What `verify_relation_full` found wrong with a relation. Block numbers are absolute,
+/// counting from the `block_start` the scan was given.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct VerifyReport {
+    /// Number of complete blocks read
+    pub total_blocks: u64,
+    /// Blocks whose pd_checksum doesn't match their contents
+    pub checksum_failures: Vec<u64>,
+    /// Whether the file ends in a partial block
+    pub torn_tail: bool,
+    /// All-zero blocks, which PostgreSQL treats as new pages rather than corruption
+    pub zero_pages: Vec<u64>,
+    /// Blocks whose header is inconsistent, with the problem found
+    pub header_errors: Vec<(u64, String)>,
+}
+
+impl VerifyReport {
+    /// Whether any block failed verification. Zero pages don't count as failures.
+    pub fn has_failures(&self) -> bool {
+        !self.checksum_failures.is_empty() || self.torn_tail || !self.header_errors.is_empty()
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Blocks scanned: {}", self.total_blocks)?;
+        writeln!(f, "Zero pages: {}", self.zero_pages.len())?;
+        writeln!(f, "Checksum failures: {}", self.checksum_failures.len())?;
+        for block in &self.checksum_failures {
+            writeln!(f, "  block {}: checksum mismatch", block)?;
+        }
+        writeln!(f, "Header errors: {}", self.header_errors.len())?;
+        for (block, error) in &self.header_errors {
+            writeln!(f, "  block {}: {}", block, error)?;
+        }
+        if self.torn_tail {
+            writeln!(f, "File ends in a partial block")?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies every block of a relation segment like pg_checksums: headers are checked for
+/// consistency and checksums recomputed for block numbers starting at `block_start` (the
+/// segment number times RELSEG_SIZE for segments after the first). Blocks are the reader's
+/// [`PageReader::block_size`].
+///
+/// Checksums are only meaningful for clusters initialized with data checksums.
+pub fn verify_relation_full<R: Read + Seek>(mut reader: PageReader<R>, block_start: u64) -> Result<VerifyReport, Error> {
+    let block_size = reader.block_size();
+    let mut report = VerifyReport::default();
+    while let Some(bytes) = reader.read_raw_block(block_size)? {
+        if bytes.len() < block_size {
+            report.torn_tail = true;
+            break;
+        }
+        let block = block_start + report.total_blocks;
+        report.total_blocks += 1;
+
+        if bytes.iter().all(|b| *b == 0) {
+            report.zero_pages.push(block);
+            continue;
+        }
+        let header = PageHeaderData::decode(&bytes[..PageHeaderData::byte_size() as usize])?;
+        let header_error = if header.page_size() != block_size {
+            Some(Error::InvalidPageSize(header.page_size() as u16))
+        } else {
+            header.validate(block_size).err()
+        };
+        if let Some(error) = header_error {
+            report.header_errors.push((block, error.to_string()));
+            continue;
+        }
+        if pg_checksum_page(&bytes, block as u32) != header.pd_checksum {
+            report.checksum_failures.push(block);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Samples the values of column `col` (0-based) across a relation, keeping each row with
+/// probability `sample_rate`, as input for histograms or most-common-value lists.
+///
+/// Rows are picked by a generator seeded with `seed`, so the same relation and seed always
+/// yield the same sample. `sample_rate` must be in (0, 1].
+pub fn sample_column<R: Read + Seek>(
+    reader: PageReader<R>,
+    desc: &TupleDesc,
+    col: usize,
+    sample_rate: f64,
+    seed: u64,
+) -> Result<Vec<Datum>, Error> {
+    if col >= desc.attrs.len() {
+        return Err(Error::InvalidByteEncoding(format!(
+            "column {} out of range for {} attributes",
+            col,
+            desc.attrs.len()
+        )));
+    }
+    if !(sample_rate > 0.0 && sample_rate <= 1.0) {
+        return Err(Error::InvalidByteEncoding(format!(
+            "sample rate {} is not in (0, 1]",
+            sample_rate
+        )));
+    }
+
+    let mut state = seed;
+    let mut sample = Vec::new();
+    for page in reader {
+        let page = page?;
+        for res in page.iter_tuples() {
+            let (_, tuple) = res?;
+            // splitmix64, mapped onto [0, 1)
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            if ((z >> 11) as f64 / (1u64 << 53) as f64) >= sample_rate {
+                continue;
+            }
+            sample.push(deserialize_attrs(&tuple, desc)?.swap_remove(col));
+        }
+    }
+
+    Ok(sample)
+}
+
+/// On-disk widths of one column's values across a relation.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ColumnWidthStats {
+    /// Attribute number, 0-based
+    pub attnum: usize,
+    /// Number of non-null values
+    pub values: u64,
+    pub nulls: u64,
+    /// Narrowest value in bytes, including any varlena header
+    pub min: usize,
+    /// Widest value in bytes, including any varlena header
+    pub max: usize,
+    /// Sum of all value widths
+    pub total: u64,
+    /// Values stored out of line as TOAST pointers
+    pub external: u64,
+}
+
+impl ColumnWidthStats {
+    /// Average width of the non-null values.
+    pub fn avg(&self) -> f64 {
+        if self.values == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.values as f64
+        }
+    }
+}
+
+/// Profiles how wide each column of a relation is on disk, to find the columns that
+/// dominate row width. External values count with the size of their TOAST pointer.
+pub fn column_width_profile<R: Read + Seek>(
+    reader: PageReader<R>,
+    desc: &TupleDesc,
+) -> Result<Vec<ColumnWidthStats>, Error> {
+    let mut stats: Vec<_> = (0..desc.attrs.len())
+        .map(|attnum| ColumnWidthStats {
+            attnum,
+            ..Default::default()
+        })
+        .collect();
+    for page in reader {
+        let page = page?;
+        for res in page.iter_tuples() {
+            let (_, tuple) = res?;
+            for ((value, column), attr) in tuple.deform(desc)?.iter().zip(&mut stats).zip(&desc.attrs) {
+                let Datum::Value(bytes) = value else {
+                    column.nulls += 1;
+                    continue;
+                };
+                let width = bytes.len();
+                column.min = if column.values == 0 { width } else { column.min.min(width) };
+                column.max = column.max.max(width);
+                column.total += width as u64;
+                column.values += 1;
+                if attr.attlen == -1 && matches!(VarlenaHeader::parse(bytes)?, VarlenaHeader::External { .. }) {
+                    column.external += 1;
+                }
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Quotes a string as an SQL literal, doubling embedded quotes.
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Formats a float the way Postgres accepts it back, quoting the special values. Finite
+/// values are printed at their own precision, so a float4 keeps only the digits it stores.
+fn float_literal<F: Into<f64> + std::fmt::Display + Copy>(value: F) -> String {
+    let wide: f64 = value.into();
+    if wide.is_nan() {
+        "'NaN'".to_string()
+    } else if wide.is_infinite() {
+        if wide > 0.0 { "'Infinity'" } else { "'-Infinity'" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders one on-disk value as an SQL literal according to its type OID.
+fn sql_literal(bytes: &[u8], attr: &AttrDesc) -> Result<String, Error> {
+    let literal = match attr.typoid {
+        // bool
+        16 => bool::decode(bytes)?.to_string(),
+        // int8
+        20 => i64::decode(bytes)?.to_string(),
+        // int2
+        21 => i16::decode(bytes)?.to_string(),
+        // int4
+        23 => i32::decode(bytes)?.to_string(),
+        // oid
+        26 => u32::decode(bytes)?.to_string(),
+        // float4
+        700 => float_literal(f32::decode(bytes)?),
+        // float8
+        701 => float_literal(f64::decode(bytes)?),
+        // name, a NUL padded fixed width string
+        19 => {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            quote_literal(&String::from_utf8(bytes[..end].to_vec()).map_err(ByteEncodeError::from)?)
+        }
+        // text, bpchar, varchar
+        25 | 1042 | 1043 => {
+            if let header @ (VarlenaHeader::Compressed { .. } | VarlenaHeader::External { .. }) =
+                VarlenaHeader::parse(bytes)?
+            {
+                return Err(Error::InvalidByteEncoding(format!(
+                    "cannot export {:?} varlena without detoasting",
+                    header
+                )));
+            }
+            let (_, payload) = read_varlena(bytes)?;
+            quote_literal(&String::from_utf8(payload.to_vec()).map_err(ByteEncodeError::from)?)
+        }
+        typoid => {
+            return Err(Error::InvalidByteEncoding(format!(
+                "cannot export values of type {}",
+                typoid
+            )))
+        }
+    };
+    Ok(literal)
+}
+
+/// Whether the hint bits show the row is gone: its insert aborted, or a delete or update of
+/// it committed. Without the commit log, rows whose fate isn't hinted yet count as present.
+fn hinted_gone(tuple: &HeapTupleHeaderData) -> bool {
+    let aborted = tuple.t_infomask & HEAP_XMIN_FROZEN == HEAP_XMIN_INVALID;
+    let deleted = tuple.t_infomask & HEAP_XMAX_COMMITTED != 0
+        && tuple.t_infomask & (HEAP_XMAX_LOCK_ONLY | HEAP_XMAX_IS_MULTI) == 0;
+    aborted || deleted
+}
+
+/// Dumps the visible rows of a relation as `INSERT INTO table_name VALUES (...);`
+/// statements, one per line, as a last-resort logical dump from raw files.
+///
+/// Rows are left out only when their hint bits show them aborted or deleted, so a freshly
+/// written page exports every row, including any whose transaction never committed.
+/// `table_name` is written as given, so it must already be quoted if needed. Supported
+/// types are bool, the integer types, oid, float4/float8, name, text, varchar and bpchar;
+/// other types, compressed and TOASTed values are reported as errors.
+pub fn export_inserts<R: Read + Seek>(
+    reader: PageReader<R>,
+    table_name: &str,
+    desc: &TupleDesc,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    for page in reader {
+        let page = page?;
+        for res in page.iter_tuples() {
+            let (_, tuple) = res?;
+            if hinted_gone(&tuple) {
+                continue;
+            }
+            let values = tuple
+                .deform(desc)?
+                .iter()
+                .zip(&desc.attrs)
+                .map(|(value, attr)| match value {
+                    Datum::Value(bytes) => sql_literal(bytes, attr),
+                    Datum::Null => Ok("NULL".to_string()),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            writeln!(out, "INSERT INTO {} VALUES ({});", table_name, values.join(", "))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dto::{
+            build_tuple, AttrDesc, ItemIdData, PageLazy, HEAP_XMAX_COMMITTED, HEAP_XMAX_INVALID,
+            HEAP_XMIN_COMMITTED, HEAP_XMIN_INVALID, MOVED_PARTITIONS_BLOCK_NUMBER, MOVED_PARTITIONS_OFFSET_NUMBER,
+            PD_HAS_FREE_LINES,
+        },
+        relation::Relation,
+        test_util::{page_bytes, page_lazy, relation_reader, tuple, write_segments, FLOAT8, HEAP_PAGE, INT4, INT8, TEXT},
+    };
+    use sha2::Sha256;
+
+    #[test]
+    fn test_tuples_by_tid_strictly_increasing() {
+        let reader = relation_reader(&[
+            page_bytes(&[tuple(1, vec![1]), tuple(2, vec![2]), tuple(3, vec![3])]),
+            page_bytes(&[tuple(4, vec![4]), tuple(5, vec![5])]),
+        ]);
+        let tuples = tuples_by_tid(reader).unwrap();
+
+        assert_eq!(tuples.len(), 5);
+        assert!(tuples.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(
+            tuples.iter().map(|(_, t)| t.t_xmin).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        let (last_tid, _) = &tuples[4];
+        assert_eq!(last_tid.ip_blkid, BlockIdData { bi_hi: 0, bi_lo: 1 });
+        assert_eq!(last_tid.ip_posid, 2);
+    }
+
+    #[test]
+    fn test_block_numbers_after_skipped_pages() {
+        let mut flagged = page_lazy(&page_bytes(&[tuple(2, vec![2])]));
+        flagged.header_data.pd_flags |= PD_HAS_FREE_LINES;
+        let pages = [
+            page_bytes(&[tuple(1, vec![1])]),
+            vec![0; 8192],
+            [flagged.header_data.encode(), flagged.data].concat(),
+        ];
+        let reader = || relation_reader(&pages).skip_empty_pages(true);
+
+        let tids: Vec<_> = tuples_by_tid(reader()).unwrap().into_iter().map(|(tid, _)| tid.block_number()).collect();
+        assert_eq!(tids, vec![0, 2]);
+        assert_eq!(pages_with_free_slots(reader()).unwrap(), vec![2]);
+        let ends: Vec<_> = scan_events(reader())
+            .filter_map(|event| match event.unwrap() {
+                ScanEvent::PageEnd { block } => Some(block),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ends, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_scan_events() {
+        let first = [tuple(1, vec![1]), tuple(2, vec![2])];
+        let second = [tuple(3, vec![3])];
+        let reader = relation_reader(&[page_bytes(&first), page_bytes(&second)]);
+        let events = scan_events(reader).collect::<Result<Vec<_>, _>>().unwrap();
+
+        let header = |page: &[u8]| PageHeaderData::decode(page).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                ScanEvent::PageStart { block: 0, header: header(&page_bytes(&first)) },
+                ScanEvent::Tuple { block: 0, offset: 1, tuple: first[0].clone() },
+                ScanEvent::Tuple { block: 0, offset: 2, tuple: first[1].clone() },
+                ScanEvent::PageEnd { block: 0 },
+                ScanEvent::PageStart { block: 1, header: header(&page_bytes(&second)) },
+                ScanEvent::Tuple { block: 1, offset: 1, tuple: second[0].clone() },
+                ScanEvent::PageEnd { block: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relation_digest_identical_relations() {
+        let pages = [
+            page_bytes(&[tuple(1, vec![1]), tuple(2, vec![2])]),
+            page_bytes(&[tuple(3, vec![3])]),
+        ];
+        let a = relation_digest(relation_reader(&pages), Sha256::new(), false).unwrap();
+        let b = relation_digest(relation_reader(&pages), Sha256::new(), false).unwrap();
+        assert_eq!(a, b);
+
+        let other = [page_bytes(&[tuple(1, vec![1]), tuple(2, vec![9])])];
+        let c = relation_digest(relation_reader(&other), Sha256::new(), false).unwrap();
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_relation_digest_normalizes_hint_bits() {
+        let plain = [page_bytes(&[tuple(1, vec![1])])];
+        let mut hinted = tuple(1, vec![1]);
+        hinted.t_infomask = HEAP_XMIN_COMMITTED;
+        let hinted = [page_bytes(&[hinted])];
+
+        let a = relation_digest(relation_reader(&plain), Sha256::new(), false).unwrap();
+        let b = relation_digest(relation_reader(&hinted), Sha256::new(), false).unwrap();
+        assert_ne!(a, b);
+
+        let a = relation_digest(relation_reader(&plain), Sha256::new(), true).unwrap();
+        let b = relation_digest(relation_reader(&hinted), Sha256::new(), true).unwrap();
+        assert_eq!(a, b);
+
+        let mut aborted = tuple(1, vec![1]);
+        aborted.t_infomask = HEAP_XMIN_INVALID;
+        let aborted = [page_bytes(&[aborted])];
+        let c = relation_digest(relation_reader(&aborted), Sha256::new(), true).unwrap();
+        assert_eq!(a, c);
+
+        let mut all_visible = plain[0].clone();
+        // pd_flags
+        all_visible[10..12].copy_from_slice(&PD_ALL_VISIBLE.to_le_bytes());
+        let d = relation_digest(relation_reader(&[all_visible]), Sha256::new(), true).unwrap();
+        assert_eq!(a, d);
+    }
+
+    #[test]
+    fn test_relation_digest_corrupt_line_pointer() {
+        let mut page = page_bytes(&[tuple(1, vec![1])]);
+        // lp_off of the first line pointer, pointing into the page header
+        page[24..26].copy_from_slice(&(0x8000_u16 | 2).to_le_bytes());
+        assert!(relation_digest(relation_reader(&[page.clone()]), Sha256::new(), false).is_ok());
+        assert!(matches!(
+            relation_digest(relation_reader(&[page]), Sha256::new(), true),
+            Err(Error::InvalidByteEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_pages_with_free_slots() {
+        let full = page_bytes(&[tuple(1, vec![1]), tuple(2, vec![2])]);
+
+        let mut flagged = page_lazy(&full);
+        flagged.header_data.pd_flags |= PD_HAS_FREE_LINES;
+        let mut unused = page_lazy(&full);
+        unused.set_item_id(2, ItemIdData::default()).unwrap();
+
+        let to_bytes = |page: PageLazy| [page.header_data.encode(), page.data].concat();
+        let reader = relation_reader(&[full.clone(), to_bytes(flagged), full, to_bytes(unused)]);
+        assert_eq!(pages_with_free_slots(reader).unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_blocks_needing_replay() {
+        let with_lsn = |lsn: u64| {
+            let mut page = page_bytes(&[tuple(1, vec![1])]);
+            page[..4].copy_from_slice(&((lsn >> 32) as u32).encode());
+            page[4..8].copy_from_slice(&(lsn as u32).encode());
+            page
+        };
+        let redo = 0x1_0000_0100;
+        let reader = relation_reader(&[
+            with_lsn(redo - 1),
+            with_lsn(redo),
+            with_lsn(0),
+            with_lsn(0x2_0000_0000),
+            with_lsn(0xFF),
+        ]);
+        assert_eq!(blocks_needing_replay(reader, redo).unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_live_tuple_bytes() {
+        let deleted = |xmax: u32, data: Vec<u8>| {
+            let mut deleted = tuple(1, data);
+            deleted.t_xmax = xmax;
+            deleted.t_infomask = HEAP_XMIN_COMMITTED | crate::dto::HEAP_XMAX_COMMITTED;
+            deleted
+        };
+        let mut aborted = tuple(7, vec![0; 100]);
+        aborted.t_infomask = HEAP_XMIN_INVALID;
+        let mut pruned = page_lazy(&page_bytes(&[tuple(1, vec![0; 16]), tuple(2, vec![0; 40])]));
+        pruned.set_item_id(1, ItemIdData::default()).unwrap();
+
+        let reader = relation_reader(&[
+            // 23 + 9 and 23 + 17 live, plus a delete after oldest_xid that is still live
+            page_bytes(&[tuple(1, vec![0; 9]), tuple(2, vec![0; 17]), deleted(50, vec![0; 5])]),
+            // deleted before oldest_xid and an aborted insert
+            page_bytes(&[deleted(5, vec![0; 30]), aborted]),
+            [pruned.header_data.encode(), pruned.data].concat(),
+        ]);
+        assert_eq!(live_tuple_bytes(reader, 10).unwrap(), (23 + 9) + (23 + 17) + (23 + 5) + (23 + 40));
+    }
+
+    fn int4_relation(rows: u32) -> Vec<Vec<u8>> {
+        let desc = TupleDesc::new(vec![INT4]);
+        let tuples: Vec<_> = (0..rows)
+            .map(|i| build_tuple(&[Datum::Value(i.to_le_bytes().to_vec())], &desc, 2, 0).unwrap())
+            .collect();
+        tuples.chunks(200).map(page_bytes).collect()
+    }
+
+    #[test]
+    fn test_sample_column_size() {
+        let pages = int4_relation(2000);
+        let desc = TupleDesc::new(vec![INT4]);
+        let sample = sample_column(relation_reader(&pages), &desc, 0, 0.25, 1).unwrap();
+        assert!((400..=600).contains(&sample.len()), "sampled {} rows", sample.len());
+        assert!(sample.iter().all(|datum| matches!(datum, Datum::Value(v) if v.len() == 4)));
+
+        let again = sample_column(relation_reader(&pages), &desc, 0, 0.25, 1).unwrap();
+        assert_eq!(sample, again);
+        let reseeded = sample_column(relation_reader(&pages), &desc, 0, 0.25, 2).unwrap();
+        assert_ne!(sample, reseeded);
+
+        assert_eq!(sample_column(relation_reader(&pages), &desc, 0, 1.0, 1).unwrap().len(), 2000);
+    }
+
+    #[test]
+    fn test_sample_column_out_of_range() {
+        let pages = int4_relation(10);
+        let desc = TupleDesc::new(vec![INT4]);
+        assert!(sample_column(relation_reader(&pages), &desc, 1, 0.5, 1).is_err());
+        for rate in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(matches!(
+                sample_column(relation_reader(&pages), &desc, 0, rate, 1),
+                Err(Error::InvalidByteEncoding(_))
+            ));
+        }
+    }
+
+    fn version(t_xmin: u32, t_xmax: u32, block: u32, posid: u16) -> HeapTupleHeaderData {
+        let mut version = tuple(t_xmin, vec![0; 4]);
+        version.t_xmax = t_xmax;
+        version.t_ctid = ItemPointerData {
+            ip_blkid: BlockIdData::from_block_number(block),
+            ip_posid: posid,
+        };
+        version
+    }
+
+    #[test]
+    fn test_update_chains_across_pages() {
+        let mut reader = relation_reader(&[
+            page_bytes(&[version(5, 10, 1, 1), version(6, 0, 0, 2)]),
+            page_bytes(&[version(10, 11, 1, 2), version(11, 0, 1, 2)]),
+        ]);
+        let chains = update_chains(&mut reader, &TupleDesc::new(vec![INT4])).unwrap();
+
+        let xmins: Vec<Vec<u32>> = chains
+            .iter()
+            .map(|chain| chain.iter().map(|(_, t)| t.t_xmin).collect())
+            .collect();
+        assert_eq!(xmins, vec![vec![5, 10, 11], vec![6]]);
+        assert_eq!(chains[0][1].0.block_number(), 1);
+        assert_eq!(chains[0][1].0.ip_posid, 1);
+    }
+
+    #[test]
+    fn test_update_chains_terminators() {
+        let mut moved = version(5, 10, 0, 0);
+        moved.t_ctid = ItemPointerData {
+            ip_blkid: BlockIdData::from_block_number(MOVED_PARTITIONS_BLOCK_NUMBER),
+            ip_posid: MOVED_PARTITIONS_OFFSET_NUMBER,
+        };
+        let mut too_wide = version(31, 0, 0, 7);
+        too_wide.t_infomask2 = 2;
+        let mut reader = relation_reader(&[page_bytes(&[
+            moved,
+            // xmin doesn't match the referencing xmax, so not a successor
+            version(7, 0, 0, 3),
+            version(8, 0, 0, 3),
+            // a cycle
+            version(20, 21, 0, 5),
+            version(21, 20, 0, 4),
+            // more attributes than the descriptor has, so not a version of the same row
+            version(30, 31, 0, 7),
+            too_wide,
+        ])]);
+        let chains = update_chains(&mut reader, &TupleDesc::new(vec![INT4])).unwrap();
+
+        let lengths: Vec<_> = chains.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![1, 1, 1, 1, 1, 2]);
+        assert_eq!(chains[5][0].1.t_xmin, 20);
+    }
+
+    #[test]
+    fn test_update_chains_across_segments() {
+        let path = write_segments(
+            "update-chains",
+            &[
+                vec![page_bytes(&[version(5, 10, 1, 1)])],
+                vec![page_bytes(&[version(10, 0, 1, 1)])],
+            ],
+        );
+        let mut relation = Relation::new(&path).with_segment_size(1);
+        let chains = update_chains(&mut relation, &TupleDesc::new(vec![INT4])).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(chains.len(), 1);
+        let tids: Vec<_> = chains[0].iter().map(|(tid, _)| (tid.block_number(), tid.ip_posid)).collect();
+        assert_eq!(tids, vec![(0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn test_verify_relation_full() {
+        let mut bad_header = HEAP_PAGE.to_vec();
+        // pd_lower past pd_upper
+        bad_header[12..14].copy_from_slice(&8000_u16.encode());
+        let reader = relation_reader(&[
+            HEAP_PAGE.to_vec(),
+            vec![0; 8192],
+            // the checksum covers the block number, so the same page fails at block 2
+            HEAP_PAGE.to_vec(),
+            bad_header,
+            HEAP_PAGE[..512].to_vec(),
+        ]);
+        let report = verify_relation_full(reader, 0).unwrap();
+
+        assert_eq!(report.total_blocks, 4);
+        assert_eq!(report.zero_pages, vec![1]);
+        assert_eq!(report.checksum_failures, vec![2]);
+        assert_eq!(report.header_errors, vec![(3, "Invalid page header lower bound: 8000".to_string())]);
+        assert!(report.torn_tail);
+        assert!(report.has_failures());
+        assert!(report.to_string().contains("block 2: checksum mismatch"));
+    }
+
+    #[test]
+    fn test_verify_relation_full_upper_past_special() {
+        let mut page = HEAP_PAGE.to_vec();
+        let special = u16::decode(&page[14..16]).unwrap() - 8;
+        page[16..18].copy_from_slice(&special.encode());
+        let report = verify_relation_full(relation_reader(&[page]), 0).unwrap();
+        assert_eq!(report.header_errors, vec![(0, format!("Invalid page header special bound: {}", special))]);
+    }
+
+    #[test]
+    fn test_verify_relation_full_block_size() {
+        let mut page = page_lazy(&page_bytes(&[]));
+        page.header_data.pd_pagesize_version = 4096 | 4;
+        page.header_data.pd_upper = 4096;
+        page.header_data.pd_special = 4096;
+        page.data.truncate(4096 - 24);
+        page.set_checksum(0);
+        let reader = relation_reader(&[page.to_bytes(), page.to_bytes()]).with_block_size(4096);
+        let report = verify_relation_full(reader, 0).unwrap();
+        assert_eq!(report.total_blocks, 2);
+        assert!(report.header_errors.is_empty());
+        assert_eq!(report.checksum_failures, vec![1]);
+        assert!(!report.torn_tail);
+    }
+
+    #[test]
+    fn test_verify_relation_full_block_start() {
+        let reader = relation_reader(&[HEAP_PAGE.to_vec()]);
+        let report = verify_relation_full(reader, 0).unwrap();
+        assert!(!report.has_failures());
+
+        let reader = relation_reader(&[HEAP_PAGE.to_vec()]);
+        let report = verify_relation_full(reader, 131072).unwrap();
+        assert_eq!(report.checksum_failures, vec![131072]);
+    }
+
+    #[test]
+    fn test_column_width_profile() {
+        let desc = TupleDesc::new(vec![INT4, TEXT]);
+        let text = |len: usize| {
+            let mut bytes = vec![((len + 1) << 1) as u8 | 1];
+            bytes.extend(vec![b'x'; len]);
+            Datum::Value(bytes)
+        };
+        let mut external = vec![0x01, 18];
+        external.extend([0; 16]);
+        let rows = [
+            [Datum::Value(1_i32.to_le_bytes().to_vec()), text(10)],
+            [Datum::Value(2_i32.to_le_bytes().to_vec()), text(100)],
+            [Datum::Value(3_i32.to_le_bytes().to_vec()), Datum::Null],
+            [Datum::Value(4_i32.to_le_bytes().to_vec()), Datum::Value(external)],
+        ];
+        let tuples: Vec<_> = rows.iter().map(|row| build_tuple(row, &desc, 2, 0).unwrap()).collect();
+        let reader = relation_reader(&[page_bytes(&tuples[..2]), page_bytes(&tuples[2..])]);
+
+        let stats = column_width_profile(reader, &desc).unwrap();
+        assert_eq!(
+            stats[0],
+            ColumnWidthStats { attnum: 0, values: 4, nulls: 0, min: 4, max: 4, total: 16, external: 0 }
+        );
+        assert_eq!(
+            stats[1],
+            ColumnWidthStats { attnum: 1, values: 3, nulls: 1, min: 11, max: 101, total: 130, external: 1 }
+        );
+        assert_eq!(stats[0].avg(), 4.0);
+        assert!((stats[1].avg() - 130.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_export_inserts() {
+        let desc = TupleDesc::new(vec![INT4, TEXT, FLOAT8]);
+        let text = |s: &str| {
+            let mut bytes = vec![((s.len() + 1) << 1) as u8 | 1];
+            bytes.extend(s.as_bytes());
+            Datum::Value(bytes)
+        };
+        let row = |id: i32, name: Datum, value: f64| {
+            let values = [Datum::Value(id.to_le_bytes().to_vec()), name, Datum::Value(value.to_le_bytes().to_vec())];
+            let mut tuple = build_tuple(&values, &desc, 5, 0).unwrap();
+            tuple.t_infomask |= HEAP_XMIN_COMMITTED;
+            tuple
+        };
+        let mut deleted = row(3, text("gone"), 0.0);
+        deleted.t_xmax = 6;
+        deleted.t_infomask = deleted.t_infomask & !HEAP_XMAX_INVALID | HEAP_XMAX_COMMITTED;
+        let mut aborted = row(4, text("never"), 0.0);
+        aborted.t_infomask = aborted.t_infomask & !HEAP_XMIN_COMMITTED | HEAP_XMIN_INVALID;
+        // nothing has hinted its fate yet
+        let mut unhinted = row(5, text("maybe"), -2.25);
+        unhinted.t_infomask &= !HEAP_XMIN_COMMITTED;
+        // only locked, not deleted
+        let mut locked = row(6, text("kept"), 0.0);
+        locked.t_xmax = 7;
+        locked.t_infomask = locked.t_infomask & !HEAP_XMAX_INVALID | HEAP_XMAX_COMMITTED | HEAP_XMAX_LOCK_ONLY;
+        let reader = relation_reader(&[page_bytes(&[
+            row(1, text("it's"), 1.5),
+            row(2, Datum::Null, f64::NAN),
+            deleted,
+            aborted,
+            unhinted,
+            locked,
+        ])]);
+
+        let mut out = Vec::new();
+        export_inserts(reader, "t", &desc, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "INSERT INTO t VALUES (1, 'it''s', 1.5);\n\
+             INSERT INTO t VALUES (2, NULL, 'NaN');\n\
+             INSERT INTO t VALUES (5, 'maybe', -2.25);\n\
+             INSERT INTO t VALUES (6, 'kept', 0);\n"
+        );
+        let rows: Vec<_> = out.lines().map(|line| parse_insert(line, "t")).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["1", "it's", "1.5"],
+                vec!["2", "NULL", "NaN"],
+                vec!["5", "maybe", "-2.25"],
+                vec!["6", "kept", "0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float4_literal() {
+        let float4 = AttrDesc {
+            attlen: 4,
+            attalign: b'i',
+            attbyval: true,
+            typoid: 700,
+        };
+        assert_eq!(sql_literal(&0.1_f32.to_le_bytes(), &float4).unwrap(), "0.1");
+        assert_eq!(sql_literal(&f32::NEG_INFINITY.to_le_bytes(), &float4).unwrap(), "'-Infinity'");
+    }
+
+    #[test]
+    fn test_export_inserts_fixture() {
+        let desc = TupleDesc::new(vec![INT4, TEXT, INT8]);
+        // the fixture's tuples carry no commit hint bits, as written before any reader saw them
+        let mut out = Vec::new();
+        export_inserts(relation_reader(&[HEAP_PAGE.to_vec()]), "fixture_heap", &desc, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 40);
+        assert!(out.starts_with("INSERT INTO fixture_heap VALUES (1, 'row 1', 100);\n"));
+        let rows: Vec<_> = out.lines().map(|line| parse_insert(line, "fixture_heap")).collect();
+        for (id, row) in (1..=40).zip(&rows) {
+            assert_eq!(row[..2], [id.to_string(), format!("row {}", id)]);
+        }
+
+        let unsupported = TupleDesc::new(vec![AttrDesc { typoid: 1700, ..INT4 }, TEXT, INT8]);
+        assert!(export_inserts(relation_reader(&[HEAP_PAGE.to_vec()]), "fixture_heap", &unsupported, &mut Vec::new()).is_err());
+    }
+
+    /// Splits an `INSERT INTO <table> VALUES (...);` line into its values, quoted literals
+    /// unescaped. Panics on anything Postgres wouldn't parse there: an unterminated or
+    /// badly escaped string, or a bare word that is neither NULL, a boolean nor a number.
+    fn parse_insert(line: &str, table: &str) -> Vec<String> {
+        let values = line
+            .strip_prefix(&format!("INSERT INTO {} VALUES (", table))
+            .and_then(|rest| rest.strip_suffix(");"))
+            .unwrap_or_else(|| panic!("not an insert into {}: {}", table, line));
+        let mut chars = values.chars().peekable();
+        let mut parsed = Vec::new();
+        loop {
+            let mut value = String::new();
+            if chars.next_if_eq(&'\'').is_some() {
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.next_if_eq(&'\'').is_some() => value.push('\''),
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => panic!("unterminated literal in {}", line),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| *c != ',') {
+                    value.push(c);
+                }
+                let number = value.parse::<f64>().is_ok() && value.bytes().all(|b| b.is_ascii_digit() || b"+-.e".contains(&b));
+                assert!(number || ["NULL", "true", "false"].contains(&value.as_str()), "bad value {:?} in {}", value, line);
+            }
+            parsed.push(value);
+            match chars.next() {
+                None => return parsed,
+                Some(',') => assert_eq!(chars.next(), Some(' '), "missing space after comma in {}", line),
+                Some(c) => panic!("unexpected {:?} after a value in {}", c, line),
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{io::Cursor, path::PathBuf};
+
+use crate::{
+    dto::{
+        AttrDesc, BlockIdData, HeapTupleHeaderData, ItemIdData, ItemPointerData, LpFlags, PageHeaderData,
+        PageLazy, PageXLogRecPtr,
+    },
+    page_reader::PageReader,
+    relation::Relation,
+    util::ByteEncoded,
+};
+
+/// Block 0 of `fixture_heap (id int4, name text, amount int8)`, written by PostgreSQL 15
+/// with data checksums enabled, so its checksum is valid for block 0.
+pub(crate) const HEAP_PAGE: &[u8] = include_bytes!("../test-data/heap_page.bin");
+/// Leaf page (block 1) of a btree index on `fixture_heap (id)`.
+pub(crate) const BTREE_LEAF_PAGE: &[u8] = include_bytes!("../test-data/btree_leaf_page.bin");
+
+pub(crate) const INT2: AttrDesc = AttrDesc {
+    attlen: 2,
+    attalign: b's',
+    attbyval: true,
+    typoid: 21,
+};
+pub(crate) const INT4: AttrDesc = AttrDesc {
+    attlen: 4,
+    attalign: b'i',
+    attbyval: true,
+    typoid: 23,
+};
+pub(crate) const INT8: AttrDesc = AttrDesc {
+    attlen: 8,
+    attalign: b'd',
+    attbyval: true,
+    typoid: 20,
+};
+pub(crate) const FLOAT8: AttrDesc = AttrDesc {
+    attlen: 8,
+    attalign: b'd',
+    attbyval: true,
+    typoid: 701,
+};
+pub(crate) const TEXT: AttrDesc = AttrDesc {
+    attlen: -1,
+    attalign: b'i',
+    attbyval: false,
+    typoid: 25,
+};
+
+pub(crate) fn tuple(t_xmin: u32, data: Vec<u8>) -> HeapTupleHeaderData {
+    HeapTupleHeaderData {
+        t_xmin,
+        t_xmax: 0,
+        t_field3: 0,
+        t_ctid: ItemPointerData {
+            ip_blkid: BlockIdData { bi_hi: 0, bi_lo: 0 },
+            ip_posid: 0,
+        },
+        t_infomask2: 1,
+        t_infomask: 0,
+        t_hoff: 24,
+        data,
+    }
+}
+
+/// Lays out an 8KB heap page holding `tuples`, each behind a normal line pointer.
+pub(crate) fn page_bytes(tuples: &[HeapTupleHeaderData]) -> Vec<u8> {
+    let page_size = 8192_u16;
+    let header_size = PageHeaderData::byte_size();
+    let mut page = vec![0; page_size as usize];
+    let mut upper = page_size;
+    for (i, tuple) in tuples.iter().enumerate() {
+        let bytes = tuple.encode();
+        upper -= bytes.len() as u16;
+        page[upper as usize..upper as usize + bytes.len()].copy_from_slice(&bytes);
+
+        let item_id = ItemIdData::new(upper, bytes.len() as u16, LpFlags::Normal);
+        let lp_start = header_size as usize + i * ItemIdData::byte_size() as usize;
+        page[lp_start..lp_start + 4].copy_from_slice(&item_id.encode());
+    }
+    let header = PageHeaderData {
+        pd_lsn: PageXLogRecPtr {
+            xlogid: 0,
+            xrecoff: 0,
+        },
+        pd_checksum: 0,
+        pd_flags: 0,
+        pd_lower: header_size + tuples.len() as u16 * ItemIdData::byte_size(),
+        pd_upper: upper,
+        pd_special: page_size,
+        pd_pagesize_version: page_size | 4,
+        pd_prune_xid: 0,
+    };
+    page[..header_size as usize].copy_from_slice(&header.encode());
+    page
+}
+
+pub(crate) fn relation_reader(pages: &[Vec<u8>]) -> PageReader<Cursor<Vec<u8>>> {
+    PageReader::from_bytes(pages.concat())
+}
+
+/// Writes `segments` as `<dir>/16384`, `<dir>/16384.1`, ... and returns the base path.
+pub(crate) fn write_segments(name: &str, segments: &[Vec<Vec<u8>>]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pg-page-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let relation = Relation::new(dir.join("16384"));
+    for (segment, pages) in segments.iter().enumerate() {
+        std::fs::write(relation.segment_path(segment as u32), pages.concat()).unwrap();
+    }
+    dir.join("16384")
+}
+
+pub(crate) fn page_lazy(bytes: &[u8]) -> PageLazy {
+    let header_size = PageHeaderData::byte_size() as usize;
+    PageLazy {
+        header_data: PageHeaderData::decode(&bytes[..header_size]).unwrap(),
+        data: bytes[header_size..].to_vec(),
+    }
+}
+
+/// An empty page whose special space holds `special`.
+pub(crate) fn page_with_special(special: &[u8]) -> PageLazy {
+    let mut page = page_lazy(&page_bytes(&[]));
+    let special_start = page.header_data.page_size() - special.len();
+    page.header_data.pd_upper = special_start as u16;
+    page.header_data.pd_special = special_start as u16;
+    let data_start = special_start - PageHeaderData::byte_size() as usize;
+    page.data[data_start..].copy_from_slice(special);
+    page
+}
@@ -1,3 +1,8 @@
+#[cfg(feature = "jsonb")]
+pub mod jsonb;
+pub mod toast;
+pub mod varlena;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -16,22 +21,108 @@ pub enum ByteEncodeError {
     Utf16Error(#[from] std::string::FromUtf16Error),
     #[error("From UTF8 error: {0}")]
     FromUtf8Error(#[from] std::string::FromUtf8Error),
+    #[error("Decompression error: {0}")]
+    Decompression(String),
+    #[error("Invalid bool byte {0}, expected 0 or 1")]
+    InvalidBool(u8),
+    #[error("Invalid jsonb: {0}")]
+    InvalidJsonb(String),
+    #[error("Expected an external varlena, not an inline one")]
+    NotExternal,
+    #[error("Unexpected vartag {0}, expected an on-disk TOAST pointer")]
+    UnexpectedVartag(u8),
+    #[error("failed decoding {context}: {source}")]
+    Context {
+        context: &'static str,
+        source: Box<ByteEncodeError>,
+    },
+}
+
+impl ByteEncodeError {
+    /// Annotates the error with the struct or field being decoded when it happened,
+    /// such as `"PageHeaderData.pd_upper"`.
+    pub fn at(self, context: &'static str) -> Self {
+        ByteEncodeError::Context {
+            context,
+            source: Box::new(self),
+        }
+    }
+
+    /// The innermost error, under any `Context` annotations.
+    pub fn root_cause(&self) -> &ByteEncodeError {
+        match self {
+            ByteEncodeError::Context { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+}
+
+/// Adds [`ByteEncodeError::at`] to decoding results.
+pub trait DecodeContext<T> {
+    fn at(self, context: &'static str) -> ByteEncodeResult<T>;
 }
 
+impl<T> DecodeContext<T> for ByteEncodeResult<T> {
+    fn at(self, context: &'static str) -> ByteEncodeResult<T> {
+        self.map_err(|err| err.at(context))
+    }
+}
+
+/// MAXALIGN: the alignment PostgreSQL gives tuple storage and other on-page structures,
+/// the largest alignment any type needs (8 bytes on 64 bit platforms).
+pub const MAXIMUM_ALIGNOF: usize = 8;
+
+/// Rounds `len` up to a multiple of MAXIMUM_ALIGNOF, like the MAXALIGN macro.
+pub const fn maxalign(len: usize) -> usize {
+    (len + MAXIMUM_ALIGNOF - 1) & !(MAXIMUM_ALIGNOF - 1)
+}
+
+/// Byte order of the integers on a page. PostgreSQL writes pages in the byte order of the
+/// machine it runs on, so files copied from a big-endian server need `Big`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Fills `bytes` from `reader`, returning `None` if the reader was already at its end.
+/// Running out part way through is an error: a clean end of file only falls between
+/// reads, never inside one.
 pub fn read_exact_with_eof(
     bytes: &mut [u8],
     reader: &mut impl std::io::Read,
 ) -> ByteEncodeResult<Option<()>> {
-    match reader.read_exact(bytes) {
-        Ok(_) => Ok(Some(())),
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                Ok(None)
-            } else {
-                Err(e.into())
-            }
+    let filled = read_until_full(bytes, reader)?;
+    check_filled(filled, bytes.len())
+}
+
+/// Reads into `bytes` until it is full or the reader runs out, retrying interrupted reads,
+/// and returns how many bytes were read.
+pub fn read_until_full(bytes: &mut [u8], reader: &mut impl std::io::Read) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match reader.read(&mut bytes[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
         }
     }
+    Ok(filled)
+}
+
+/// The outcome of a read of `len` bytes that stopped after `filled`, as
+/// [`read_exact_with_eof`] reports it.
+pub(crate) fn check_filled(filled: usize, len: usize) -> ByteEncodeResult<Option<()>> {
+    match filled {
+        0 if len > 0 => Ok(None),
+        filled if filled < len => Err(ByteEncodeError::NotEnoughBytes {
+            expected: len,
+            actual: filled,
+        }),
+        _ => Ok(Some(())),
+    }
 }
 
 pub type ByteEncodeResult<T> = Result<T, ByteEncodeError>;
@@ -83,6 +174,12 @@ pub fn read_u8(bytes: &[u8]) -> u8 {
     u8::from_le_bytes(buf)
 }
 
+pub fn read_i8(bytes: &[u8]) -> i8 {
+    let mut buf = [0; 1];
+    buf.copy_from_slice(bytes);
+    i8::from_le_bytes(buf)
+}
+
 pub fn read_i16(bytes: &[u8]) -> i16 {
     let mut buf = [0; 2];
     buf.copy_from_slice(bytes);
@@ -101,6 +198,18 @@ pub fn read_i64(bytes: &[u8]) -> i64 {
     i64::from_le_bytes(buf)
 }
 
+pub fn read_f32(bytes: &[u8]) -> f32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    f32::from_le_bytes(buf)
+}
+
+pub fn read_f64(bytes: &[u8]) -> f64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    f64::from_le_bytes(buf)
+}
+
 pub fn write_u64(bytes: &mut [u8], value: u64) {
     bytes.copy_from_slice(&value.to_le_bytes());
 }
@@ -133,6 +242,14 @@ pub fn write_i64(bytes: &mut [u8], value: i64) {
     bytes.copy_from_slice(&value.to_le_bytes());
 }
 
+pub fn write_f32(bytes: &mut [u8], value: f32) {
+    bytes.copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_f64(bytes: &mut [u8], value: f64) {
+    bytes.copy_from_slice(&value.to_le_bytes());
+}
+
 pub fn write_string(bytes: &mut [u8], value: &str) {
     bytes.copy_from_slice(value.as_bytes());
 }
@@ -146,6 +263,10 @@ where
     Self: Sized,
 {
     fn encode(&self) -> Vec<u8>;
+    /// Appends the encoded bytes to `buf`, reusing its capacity.
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.encode());
+    }
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         let mut reader = std::io::Cursor::new(bytes);
         Self::decode_from_reader(&mut reader)
@@ -200,6 +321,10 @@ impl ByteEncoded for u64 {
         self.to_le_bytes().to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         Ok(read_u64(bytes))
     }
@@ -224,6 +349,10 @@ impl ByteEncoded for u32 {
         self.to_le_bytes().to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         Ok(read_u32(bytes))
     }
@@ -248,6 +377,10 @@ impl ByteEncoded for u16 {
         self.to_le_bytes().to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         Ok(read_u16(bytes))
     }
@@ -272,6 +405,10 @@ impl ByteEncoded for u8 {
         self.to_le_bytes().to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         Ok(read_u8(bytes))
     }
@@ -291,11 +428,246 @@ impl ByteEncoded for u8 {
     }
 }
 
+impl ByteEncoded for i64 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_i64(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        Ok(read_i64(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        8
+    }
+}
+
+impl ByteEncoded for i32 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_i32(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        Ok(read_i32(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        4
+    }
+}
+
+impl ByteEncoded for i16 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_i16(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 2];
+        reader.read_exact(&mut buf)?;
+        Ok(read_i16(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        2
+    }
+}
+
+impl ByteEncoded for i8 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_i8(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf)?;
+        Ok(read_i8(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        1
+    }
+}
+
+impl ByteEncoded for f64 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_f64(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        Ok(read_f64(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        8
+    }
+}
+
+impl ByteEncoded for f32 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        Ok(read_f32(bytes))
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.to_le_bytes())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        Ok(read_f32(&buf))
+    }
+
+    fn byte_size() -> u16 {
+        4
+    }
+}
+
+impl ByteEncoded for bool {
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        match read_u8(bytes) {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(ByteEncodeError::InvalidBool(byte)),
+        }
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&[*self as u8])?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf)
+    }
+
+    fn byte_size() -> u16 {
+        1
+    }
+}
+
+impl<const N: usize> ByteEncoded for [u8; N] {
+    fn encode(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        bytes.try_into().map_err(|_| ByteEncodeError::InvalidSize {
+            expected: N,
+            actual: bytes.len(),
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(self)?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut buf = [0; N];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn byte_size() -> u16 {
+        N as u16
+    }
+}
+
 impl ByteEncoded for String {
     fn encode(&self) -> Vec<u8> {
         self.as_bytes().to_vec()
     }
 
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         Ok(String::from_utf8(bytes.to_vec())?)
     }
@@ -317,15 +689,19 @@ where
 {
     fn encode(&self) -> Vec<u8> {
         let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
         for item in self {
-            buf.extend(item.encode());
+            item.encode_into(buf);
         }
-        buf
     }
 
     fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
         if T::byte_size() != 0 {
-            if bytes.len() % T::byte_size() as usize != 0 {
+            if !bytes.len().is_multiple_of(T::byte_size() as usize) {
                 return Err(ByteEncodeError::InvalidSize {
                     expected: T::byte_size() as usize,
                     actual: bytes.len(),
@@ -370,13 +746,90 @@ mod tests {
     use super::*;
     use crate::dto::*;
 
+    fn round_trip<T: ByteEncoded>(value: &T) -> T {
+        let encoded = value.encode();
+        assert_eq!(encoded.len(), T::byte_size() as usize);
+        let mut writer = Vec::new();
+        value.encode_into_writer(&mut writer).unwrap();
+        assert_eq!(writer, encoded);
+        let from_reader = T::decode_from_reader(&mut encoded.as_slice()).unwrap();
+        assert_eq!(from_reader.encode(), encoded);
+        T::decode(&encoded).unwrap()
+    }
+
+    #[test]
+    fn test_signed_round_trip() {
+        assert_eq!(round_trip(&-1_i8), -1);
+        assert_eq!(round_trip(&i16::MIN), i16::MIN);
+        assert_eq!(round_trip(&-123_456_i32), -123_456);
+        assert_eq!(round_trip(&i64::MIN), i64::MIN);
+        assert_eq!((-2_i32).encode(), vec![0xFE, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_bool() {
+        assert!(round_trip(&true));
+        assert!(!round_trip(&false));
+        assert_eq!(Vec::<bool>::decode(&[1, 0, 1]).unwrap(), vec![true, false, true]);
+        assert!(matches!(bool::decode(&[2]), Err(ByteEncodeError::InvalidBool(2))));
+        assert!(matches!(
+            bool::decode_from_reader(&mut [0xFF].as_slice()),
+            Err(ByteEncodeError::InvalidBool(0xFF))
+        ));
+    }
+
+    #[test]
+    fn test_byte_array() {
+        let marker: [u8; 16] = *b"pg-storage magic";
+        assert_eq!(<[u8; 16]>::byte_size(), 16);
+        assert_eq!(round_trip(&marker), marker);
+        assert!(matches!(
+            <[u8; 16]>::decode(&marker[..15]),
+            Err(ByteEncodeError::InvalidSize { expected: 16, actual: 15 })
+        ));
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        assert_eq!(round_trip(&-1.5_f32), -1.5);
+        assert_eq!(round_trip(&f64::NEG_INFINITY), f64::NEG_INFINITY);
+        assert_eq!(round_trip(&-0.0_f64).to_bits(), (-0.0_f64).to_bits());
+        assert!(round_trip(&f32::NAN).is_nan());
+        let nan = f64::from_bits(0x7FF8_0000_0000_0001);
+        assert_eq!(round_trip(&nan).to_bits(), nan.to_bits());
+    }
+
+    #[test]
+    fn test_read_exact_with_eof() {
+        let mut buf = [0; 4];
+        assert!(read_exact_with_eof(&mut buf, &mut [].as_slice()).unwrap().is_none());
+
+        let mut input = [1, 2, 3, 4].as_slice();
+        assert!(read_exact_with_eof(&mut buf, &mut input).unwrap().is_some());
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert!(read_exact_with_eof(&mut buf, &mut input).unwrap().is_none());
+
+        assert!(matches!(
+            read_exact_with_eof(&mut buf, &mut [1, 2, 3].as_slice()),
+            Err(ByteEncodeError::NotEnoughBytes { expected: 4, actual: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_maxalign() {
+        assert_eq!(maxalign(0), 0);
+        assert_eq!(maxalign(1), 8);
+        assert_eq!(maxalign(8), 8);
+        assert_eq!(maxalign(23), 24);
+        assert_eq!(maxalign(28), 32);
+    }
+
     #[test]
     fn test_item() {
         let item = HeapTupleHeaderData {
             t_xmin: 1,
             t_xmax: 2,
-            t_cid: 3,
-            t_xvac: 4,
+            t_field3: 3,
             t_ctid: ItemPointerData {
                 ip_blkid: BlockIdData { bi_hi: 5, bi_lo: 6 },
                 ip_posid: 6,
@@ -391,6 +844,65 @@ mod tests {
         assert_eq!(item, decoded);
     }
 
+    #[test]
+    fn test_encode_into_matches_encode() {
+        let page_header = PageHeaderData {
+            pd_lsn: PageXLogRecPtr {
+                xlogid: 1,
+                xrecoff: 2,
+            },
+            pd_checksum: 2,
+            pd_flags: 3,
+            pd_lower: 4,
+            pd_upper: 5,
+            pd_special: 6,
+            pd_pagesize_version: 7,
+            pd_prune_xid: 8,
+        };
+        let item = HeapTupleHeaderData {
+            t_xmin: 1,
+            t_xmax: 2,
+            t_field3: 3,
+            t_ctid: ItemPointerData {
+                ip_blkid: BlockIdData { bi_hi: 5, bi_lo: 6 },
+                ip_posid: 6,
+            },
+            t_infomask2: 7,
+            t_infomask: 8,
+            t_hoff: 9,
+            data: vec![1, 2, 3, 4, 5],
+        };
+
+        let mut buf = vec![0xAA];
+        page_header.encode_into(&mut buf);
+        item.encode_into(&mut buf);
+
+        let mut expected = vec![0xAA];
+        expected.extend(page_header.encode());
+        expected.extend(item.encode());
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_item_encode_into_writer() {
+        let item = HeapTupleHeaderData {
+            t_xmin: 1,
+            t_xmax: 2,
+            t_field3: 3,
+            t_ctid: ItemPointerData {
+                ip_blkid: BlockIdData { bi_hi: 5, bi_lo: 6 },
+                ip_posid: 6,
+            },
+            t_infomask2: 7,
+            t_infomask: 8,
+            t_hoff: 9,
+            data: vec![1, 2, 3, 4, 5],
+        };
+        let mut buf = Vec::new();
+        item.encode_into_writer(&mut buf).unwrap();
+        assert_eq!(buf, item.encode());
+    }
+
     #[test]
     fn test_item_pointer() {
         let item_pointer = ItemPointerData {
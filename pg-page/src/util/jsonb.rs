@@ -0,0 +1,266 @@
+//! `jsonb` datums (`src/include/utils/jsonb.h`): a container header, an array of JEntry
+//! words describing each child, then the children's data. Children that are containers
+//! or numerics start on a 4 byte boundary of the data area.
+
+use std::fmt::Write;
+
+use serde_json::{Map, Number, Value};
+
+use crate::util::{
+    read_i16, read_u16, read_u32,
+    varlena::{read_varlena, VarlenaHeader},
+    ByteEncodeError, ByteEncodeResult, GetByteSliceExt,
+};
+
+/// Mask of the element (array) or pair (object) count in a container header
+pub const JB_CMASK: u32 = 0x0FFF_FFFF;
+/// The container is an array holding the single scalar at the root of the document
+pub const JB_FSCALAR: u32 = 0x1000_0000;
+pub const JB_FOBJECT: u32 = 0x2000_0000;
+pub const JB_FARRAY: u32 = 0x4000_0000;
+
+/// Mask of the offset or length stored in a JEntry
+pub const JENTRY_OFFLENMASK: u32 = 0x0FFF_FFFF;
+pub const JENTRY_TYPEMASK: u32 = 0x7000_0000;
+/// The JEntry stores the end offset of its child rather than its length
+pub const JENTRY_HAS_OFF: u32 = 0x8000_0000;
+
+/// JEntry types
+pub const JENTRY_ISSTRING: u32 = 0x0000_0000;
+pub const JENTRY_ISNUMERIC: u32 = 0x1000_0000;
+pub const JENTRY_ISBOOL_FALSE: u32 = 0x2000_0000;
+pub const JENTRY_ISBOOL_TRUE: u32 = 0x3000_0000;
+pub const JENTRY_ISNULL: u32 = 0x4000_0000;
+pub const JENTRY_ISCONTAINER: u32 = 0x5000_0000;
+
+/// Sign bits of a numeric header
+const NUMERIC_SIGN_MASK: u16 = 0xC000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_SHORT: u16 = 0x8000;
+const NUMERIC_SPECIAL: u16 = 0xC000;
+const NUMERIC_DSCALE_MASK: u16 = 0x3FFF;
+const NUMERIC_SHORT_SIGN_MASK: u16 = 0x2000;
+const NUMERIC_SHORT_DSCALE_MASK: u16 = 0x1F80;
+const NUMERIC_SHORT_DSCALE_SHIFT: u16 = 7;
+const NUMERIC_SHORT_WEIGHT_SIGN_MASK: u16 = 0x0040;
+const NUMERIC_SHORT_WEIGHT_MASK: u16 = 0x003F;
+
+/// Deepest nesting of containers decoded before giving up, the same limit serde_json
+/// puts on parsing.
+pub const JSONB_MAX_DEPTH: usize = 128;
+
+/// Decodes a `jsonb` datum, varlena header included, as found in a tuple. Compressed and
+/// TOASTed datums have to be detoasted first.
+///
+/// Numbers go through `serde_json::Number`, so ones that don't fit an i64 or u64 lose
+/// precision unless serde_json's `arbitrary_precision` feature is on.
+pub fn decode_jsonb(bytes: &[u8]) -> ByteEncodeResult<Value> {
+    match VarlenaHeader::parse(bytes)? {
+        VarlenaHeader::Short { .. } | VarlenaHeader::Uncompressed { .. } => {}
+        header => return Err(invalid(format!("cannot decode {:?} jsonb, detoast it first", header))),
+    }
+    let (_, container) = read_varlena(bytes)?;
+    decode_container(container, 0)
+}
+
+fn invalid(message: impl Into<String>) -> ByteEncodeError {
+    ByteEncodeError::InvalidJsonb(message.into())
+}
+
+/// Rounds up to a multiple of 4, like INTALIGN.
+fn intalign(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn decode_container(bytes: &[u8], depth: usize) -> ByteEncodeResult<Value> {
+    if depth > JSONB_MAX_DEPTH {
+        return Err(invalid(format!("containers nested deeper than {}", JSONB_MAX_DEPTH)));
+    }
+    let header = read_u32(bytes.get_byte_slice(0, 4)?);
+    let count = (header & JB_CMASK) as usize;
+    let is_object = header & JB_FOBJECT != 0;
+    let entry_count = if is_object { count * 2 } else { count };
+    let data_start = 4 + 4 * entry_count;
+    // checked before allocating for the children, as the count comes from the datum
+    let entries = bytes.get_byte_slice(4, data_start)?;
+    let data = &bytes[data_start..];
+
+    let mut children = Vec::with_capacity(entry_count);
+    let mut start = 0;
+    for entry in entries.chunks_exact(4).map(read_u32) {
+        let offlen = (entry & JENTRY_OFFLENMASK) as usize;
+        let end = if entry & JENTRY_HAS_OFF != 0 { offlen } else { start + offlen };
+        if end < start {
+            return Err(invalid(format!("child ends at {} before its start {}", end, start)));
+        }
+        children.push(decode_child(entry, data, start, end, depth)?);
+        start = end;
+    }
+
+    if header & JB_FARRAY != 0 {
+        if header & JB_FSCALAR == 0 {
+            return Ok(Value::Array(children));
+        }
+        return match <[Value; 1]>::try_from(children) {
+            Ok([scalar]) => Ok(scalar),
+            Err(children) => Err(invalid(format!("scalar root holds {} values", children.len()))),
+        };
+    }
+    if is_object {
+        let values = children.split_off(count);
+        let mut object = Map::new();
+        for (key, value) in children.into_iter().zip(values) {
+            let Value::String(key) = key else {
+                return Err(invalid("object key is not a string"));
+            };
+            object.insert(key, value);
+        }
+        return Ok(Value::Object(object));
+    }
+    Err(invalid(format!("container header {:#x} is neither an array nor an object", header)))
+}
+
+/// Decodes the child a JEntry describes, stored in `data[start..end]`.
+fn decode_child(entry: u32, data: &[u8], start: usize, end: usize, depth: usize) -> ByteEncodeResult<Value> {
+    match entry & JENTRY_TYPEMASK {
+        JENTRY_ISSTRING => Ok(Value::String(std::str::from_utf8(data.get_byte_slice(start, end)?)?.to_string())),
+        JENTRY_ISNUMERIC => {
+            let numeric = numeric_to_string(data.get_byte_slice(intalign(start), end)?)?;
+            let number = numeric
+                .parse::<Number>()
+                .map_err(|err| invalid(format!("numeric {} is not a JSON number: {}", numeric, err)))?;
+            Ok(Value::Number(number))
+        }
+        JENTRY_ISBOOL_FALSE => Ok(Value::Bool(false)),
+        JENTRY_ISBOOL_TRUE => Ok(Value::Bool(true)),
+        JENTRY_ISNULL => Ok(Value::Null),
+        JENTRY_ISCONTAINER => decode_container(data.get_byte_slice(intalign(start), end)?, depth + 1),
+        other => Err(invalid(format!("unknown JEntry type {:#x}", other))),
+    }
+}
+
+/// The decimal text of a `numeric` datum, with as many fraction digits as its display scale.
+fn numeric_to_string(datum: &[u8]) -> ByteEncodeResult<String> {
+    let (_, body) = read_varlena(datum)?;
+    let header = read_u16(body.get_byte_slice(0, 2)?);
+    let (negative, weight, dscale, digits_start) = match header & NUMERIC_SIGN_MASK {
+        NUMERIC_SHORT => {
+            let mut weight = (header & NUMERIC_SHORT_WEIGHT_MASK) as i32;
+            if header & NUMERIC_SHORT_WEIGHT_SIGN_MASK != 0 {
+                weight |= !(NUMERIC_SHORT_WEIGHT_MASK as i32);
+            }
+            let dscale = (header & NUMERIC_SHORT_DSCALE_MASK) >> NUMERIC_SHORT_DSCALE_SHIFT;
+            (header & NUMERIC_SHORT_SIGN_MASK != 0, weight, dscale as usize, 2)
+        }
+        NUMERIC_SPECIAL => return Err(invalid("NaN and infinity are not valid jsonb numbers")),
+        sign => (
+            sign == NUMERIC_NEG,
+            read_i16(body.get_byte_slice(2, 4)?) as i32,
+            (header & NUMERIC_DSCALE_MASK) as usize,
+            4,
+        ),
+    };
+    // base 10000 digits, the first one of weight `weight`
+    let digits: Vec<i16> = body.get_byte_slice(digits_start, body.len())?.chunks_exact(2).map(read_i16).collect();
+    let digit = |index: i32| usize::try_from(index).ok().and_then(|i| digits.get(i)).copied().unwrap_or(0);
+
+    let mut text = String::new();
+    if negative {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    } else {
+        let _ = write!(text, "{}", digit(0));
+        for index in 1..=weight {
+            let _ = write!(text, "{:04}", digit(index));
+        }
+    }
+    if dscale > 0 {
+        let mut fraction = String::new();
+        let mut index = weight + 1;
+        while fraction.len() < dscale {
+            let _ = write!(fraction, "{:04}", digit(index));
+            index += 1;
+        }
+        fraction.truncate(dscale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// Column values of a `jsonb` table written by PostgreSQL 15, as heap_page_items
+    /// reports them.
+    const DOCUMENT: &str = "70030000050000200200008003000000040000000500000006000000080000100800001044000050080000103c00005069646e656774616773736d616c6c6e65737465642000000000802a002000000000a0070007000040000000b00000002000000040010000000d0000100e00001008000010780000002800000080800100881300003000000001a2d2042e163423200000000580010020000000ff810a00030000200400008004000000050000000700005005000000070000506c6973746e616d65656d70747900000000000040c3a974c3a900000000000020";
+
+    #[test]
+    fn test_decode_document() {
+        assert_eq!(
+            decode_jsonb(&hex(DOCUMENT)).unwrap(),
+            json!({
+                "id": 42,
+                "tags": [true, false, null, "x", 1.5, -12345678.9012, 100000000000000000000.0],
+                "nested": {"name": "été", "empty": {}, "list": []},
+                "small": 0.001,
+                "neg": -7,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_scalars() {
+        assert_eq!(decode_jsonb(&hex("1d010000500500008068656c6c6f")).unwrap(), json!("hello"));
+        assert_eq!(decode_jsonb(&hex("2301000050080000902000000000802a00")).unwrap(), json!(42));
+        assert_eq!(decode_jsonb(&hex("0b00000040")).unwrap(), json!([]));
+        assert_eq!(decode_jsonb(&hex("1301000050000000c0")).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let mut bytes = hex(DOCUMENT);
+        bytes.truncate(100);
+        assert!(decode_jsonb(&bytes).is_err());
+        // a 4 byte header claiming more than is there
+        assert!(decode_jsonb(&hex("70030000")).is_err());
+        // a container header claiming 2^28 - 1 elements, which must not be allocated for
+        assert!(matches!(
+            decode_jsonb(&[0x0B, 0xFF, 0xFF, 0xFF, 0x2F]),
+            Err(ByteEncodeError::NotEnoughBytes { .. })
+        ));
+    }
+
+    /// An uncompressed datum holding `depth` arrays nested in each other.
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        // the innermost, empty array
+        let mut container = JB_FARRAY.to_le_bytes().to_vec();
+        for _ in 0..depth {
+            let mut outer = (JB_FARRAY | 1).to_le_bytes().to_vec();
+            outer.extend((JENTRY_ISCONTAINER | container.len() as u32).to_le_bytes());
+            outer.extend(container);
+            container = outer;
+        }
+        let mut datum = ((container.len() as u32 + 4) << 2).to_le_bytes().to_vec();
+        datum.extend(container);
+        datum
+    }
+
+    #[test]
+    fn test_decode_depth_limit() {
+        let value = decode_jsonb(&nested_arrays(JSONB_MAX_DEPTH)).unwrap();
+        assert_eq!(value.pointer(&"/0".repeat(JSONB_MAX_DEPTH)), Some(&json!([])));
+        assert!(matches!(
+            decode_jsonb(&nested_arrays(JSONB_MAX_DEPTH + 1)),
+            Err(ByteEncodeError::InvalidJsonb(_))
+        ));
+    }
+}
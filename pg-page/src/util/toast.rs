@@ -0,0 +1,248 @@
+use crate::util::{
+    varlena::{VarlenaHeader, VARHDRSZ_EXTERNAL, VARTAG_ONDISK},
+    ByteEncodeError, ByteEncodeResult, ByteEncoded, GetByteSliceExt,
+};
+
+/// Size of a 4 byte varlena length word.
+pub const VARHDRSZ: u32 = 4;
+
+/// Low bits of `va_extinfo` holding the external (stored) size.
+pub const VARLENA_EXTSIZE_BITS: u32 = 30;
+pub const VARLENA_EXTSIZE_MASK: u32 = (1 << VARLENA_EXTSIZE_BITS) - 1;
+
+/// The compression method recorded in the high two bits of `va_extinfo`, or in the
+/// `va_tcinfo` of an inline compressed varlena.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum CompressionMethod {
+    Pglz = 0,
+    Lz4 = 1,
+}
+
+impl CompressionMethod {
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(CompressionMethod::Pglz),
+            1 => Some(CompressionMethod::Lz4),
+            _ => None,
+        }
+    }
+}
+
+///
+/// struct varatt_external is a traditional "TOAST pointer", that is, the
+/// information needed to fetch a Datum stored out-of-line in a TOAST table.
+/// The data is compressed if and only if the external size stored in
+/// va_extinfo is less than va_rawsize - VARHDRSZ.
+///
+/// This struct must not contain any padding, because we sometimes compare
+/// these pointers using memcmp.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct ToastPointer {
+    /// Original data size (includes header)
+    pub va_rawsize: i32,
+    /// External saved size (without header) and compression method
+    pub va_extinfo: u32,
+    /// Unique ID of value within TOAST table
+    pub va_valueid: u32,
+    /// RelID of TOAST table containing it
+    pub va_toastrelid: u32,
+}
+
+/// PostgreSQL's name for the on-disk TOAST pointer struct.
+pub type VaratteExternal = ToastPointer;
+
+impl ToastPointer {
+    /// Decodes an external datum as found in a tuple: the 0x01 marker, the VARTAG_ONDISK
+    /// tag and the 16 byte pointer body.
+    pub fn from_datum(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        match VarlenaHeader::parse(bytes)? {
+            VarlenaHeader::External { tag: VARTAG_ONDISK } => {}
+            VarlenaHeader::External { tag } => return Err(ByteEncodeError::UnexpectedVartag(tag)),
+            _ => return Err(ByteEncodeError::NotExternal),
+        }
+        Self::decode(bytes.get_byte_slice(VARHDRSZ_EXTERNAL, VARHDRSZ_EXTERNAL + Self::byte_size() as usize)?)
+    }
+
+    /// va_extsize in PostgreSQL 13 and earlier, which had no compression method bits.
+    pub fn va_extsize(&self) -> u32 {
+        self.ext_size()
+    }
+
+    /// Size of the value as stored in the TOAST table, without header.
+    pub fn ext_size(&self) -> u32 {
+        self.va_extinfo & VARLENA_EXTSIZE_MASK
+    }
+
+    /// Whether the TOAST table holds the value compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.ext_size() < (self.va_rawsize as u32).saturating_sub(VARHDRSZ)
+    }
+
+    /// The decompressed payload size and the method needed to get there, or `None` if the
+    /// value is stored uncompressed or with an unknown method.
+    pub fn compression(&self) -> Option<(usize, CompressionMethod)> {
+        if !self.is_compressed() {
+            return None;
+        }
+        let method = CompressionMethod::from_id(self.va_extinfo >> VARLENA_EXTSIZE_BITS)?;
+        Some(((self.va_rawsize as u32 - VARHDRSZ) as usize, method))
+    }
+}
+
+/// Decompresses an LZ4 compressed TOAST value (PostgreSQL 14+) that expands to `raw_size`
+/// bytes. `src` is the compressed payload, without the varlena header and `va_tcinfo`.
+#[cfg(feature = "lz4")]
+pub fn lz4_decompress(src: &[u8], raw_size: usize) -> ByteEncodeResult<Vec<u8>> {
+    let bytes = lz4_flex::block::decompress(src, raw_size)
+        .map_err(|e| ByteEncodeError::Decompression(e.to_string()))?;
+    if bytes.len() != raw_size {
+        return Err(ByteEncodeError::Decompression(format!(
+            "expected {} decompressed bytes, got {}",
+            raw_size,
+            bytes.len()
+        )));
+    }
+    Ok(bytes)
+}
+
+impl ByteEncoded for ToastPointer {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::byte_size() as usize);
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.va_rawsize.to_le_bytes());
+        self.va_extinfo.encode_into(buf);
+        self.va_valueid.encode_into(buf);
+        self.va_toastrelid.encode_into(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let va_rawsize = u32::decode(bytes.get_byte_slice(0, 4)?)? as i32;
+        let va_extinfo = u32::decode(bytes.get_byte_slice(4, 8)?)?;
+        let va_valueid = u32::decode(bytes.get_byte_slice(8, 12)?)?;
+        let va_toastrelid = u32::decode(bytes.get_byte_slice(12, 16)?)?;
+        Ok(ToastPointer {
+            va_rawsize,
+            va_extinfo,
+            va_valueid,
+            va_toastrelid,
+        })
+    }
+
+    fn encode_into_writer(&self, writer: &mut impl std::io::Write) -> ByteEncodeResult<()> {
+        Ok(writer.write_all(&self.encode())?)
+    }
+
+    fn decode_from_reader(reader: &mut impl std::io::Read) -> ByteEncodeResult<Self> {
+        let mut bytes = [0; 16];
+        reader.read_exact(&mut bytes)?;
+        Self::decode(&bytes)
+    }
+
+    fn byte_size() -> u16 {
+        16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::varlena::VARTAG_INDIRECT;
+
+    fn pointer(va_rawsize: i32, ext_size: u32, method: u32) -> ToastPointer {
+        ToastPointer {
+            va_rawsize,
+            va_extinfo: ext_size | method << VARLENA_EXTSIZE_BITS,
+            va_valueid: 16390,
+            va_toastrelid: 16387,
+        }
+    }
+
+    #[test]
+    fn test_pglz_pointer() {
+        let pointer = pointer(10004, 3200, 0);
+        assert_eq!(pointer.ext_size(), 3200);
+        assert_eq!(pointer.compression(), Some((10000, CompressionMethod::Pglz)));
+    }
+
+    #[test]
+    fn test_lz4_pointer() {
+        let pointer = pointer(10004, 2900, 1);
+        assert_eq!(pointer.ext_size(), 2900);
+        assert_eq!(pointer.compression(), Some((10000, CompressionMethod::Lz4)));
+    }
+
+    #[test]
+    fn test_uncompressed_pointer() {
+        let pointer = pointer(10004, 10000, 0);
+        assert!(!pointer.is_compressed());
+        assert_eq!(pointer.compression(), None);
+    }
+
+    /// A 3200 byte `text` value stored with `SET STORAGE EXTERNAL`, as found in the heap tuple.
+    const EXTERNAL_DATUM: [u8; 18] = [
+        0x01, 0x12, 0x84, 0x0c, 0x00, 0x00, 0x80, 0x0c, 0x00, 0x00, 0x15, 0x40, 0x00, 0x00, 0x13,
+        0x40, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_from_datum() {
+        let pointer = ToastPointer::from_datum(&EXTERNAL_DATUM).unwrap();
+        assert_eq!(pointer.va_rawsize, 3204);
+        assert_eq!(pointer.va_extsize(), 3200);
+        assert_eq!(pointer.va_valueid, 16405);
+        assert_eq!(pointer.va_toastrelid, 16403);
+        assert!(!pointer.is_compressed());
+
+        assert!(matches!(
+            ToastPointer::from_datum(&EXTERNAL_DATUM[..10]),
+            Err(ByteEncodeError::NotEnoughBytes { .. })
+        ));
+        assert!(matches!(
+            ToastPointer::from_datum(&[0x09, b'a', b'b', b'c']),
+            Err(ByteEncodeError::NotExternal)
+        ));
+        let mut indirect = EXTERNAL_DATUM;
+        indirect[1] = VARTAG_INDIRECT;
+        assert!(matches!(ToastPointer::from_datum(&indirect), Err(ByteEncodeError::UnexpectedVartag(VARTAG_INDIRECT))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pointer = pointer(10004, 2900, 1);
+        let bytes = pointer.encode();
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(ToastPointer::decode(&bytes).unwrap(), pointer);
+        assert_eq!(ToastPointer::decode_from_reader(&mut bytes.as_slice()).unwrap(), pointer);
+    }
+
+    /// `repeat('postgres lz4 ', 400)` as stored inline by a `text compression lz4` column:
+    /// 4 byte varlena header, `va_tcinfo`, then the LZ4 block.
+    #[cfg(feature = "lz4")]
+    const LZ4_DATUM: &str = "ce00000050140040df706f737467726573206c7a34200d00ff\
+        ffffffffffffffffffffffffffffffffffffff3f50206c7a3420";
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4_decompress() {
+        let datum: Vec<u8> = (0..LZ4_DATUM.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&LZ4_DATUM[i..i + 2], 16).unwrap())
+            .collect();
+        let tcinfo = u32::decode(&datum[4..8]).unwrap();
+        assert_eq!(
+            CompressionMethod::from_id(tcinfo >> VARLENA_EXTSIZE_BITS),
+            Some(CompressionMethod::Lz4)
+        );
+        let raw_size = (tcinfo & VARLENA_EXTSIZE_MASK) as usize;
+
+        let bytes = lz4_decompress(&datum[8..], raw_size).unwrap();
+        assert_eq!(bytes.len(), raw_size);
+        assert_eq!(bytes, "postgres lz4 ".repeat(400).into_bytes());
+        assert!(lz4_decompress(&datum[8..], raw_size + 1).is_err());
+    }
+}
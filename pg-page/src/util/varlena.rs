@@ -0,0 +1,170 @@
+//! Varlena (variable-length datum) headers, in the little-endian layout of the files this
+//! crate reads: the low bits of the first byte tell the header forms apart.
+
+use crate::util::{
+    toast::{CompressionMethod, VARLENA_EXTSIZE_BITS, VARLENA_EXTSIZE_MASK},
+    ByteEncodeError, ByteEncodeResult, ByteEncoded, GetByteSliceExt,
+};
+
+/// TOAST pointer to a datum held in memory elsewhere
+pub const VARTAG_INDIRECT: u8 = 1;
+/// read-only pointer to an expanded object
+pub const VARTAG_EXPANDED_RO: u8 = 2;
+/// read-write pointer to an expanded object
+pub const VARTAG_EXPANDED_RW: u8 = 3;
+/// TOAST pointer to a value stored in a TOAST table
+pub const VARTAG_ONDISK: u8 = 18;
+
+/// Header size of a 1 byte short varlena
+pub const VARHDRSZ_SHORT: usize = 1;
+/// Header size of an external varlena: the 0x01 marker and the vartag
+pub const VARHDRSZ_EXTERNAL: usize = 2;
+/// Header size of an inline compressed varlena: the length word and va_tcinfo
+pub const VARHDRSZ_COMPRESSED: usize = 8;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum VarlenaHeader {
+    /// VARATT_IS_1B: 1 byte header, values up to 126 bytes
+    Short { len: usize },
+    /// VARATT_IS_4B_U: 4 byte header, uncompressed
+    Uncompressed { len: usize },
+    /// VARATT_IS_4B_C: 4 byte header followed by va_tcinfo, compressed inline
+    Compressed {
+        len: usize,
+        raw_size: u32,
+        method: Option<CompressionMethod>,
+    },
+    /// VARATT_IS_EXTERNAL: a TOAST pointer of the given vartag
+    External { tag: u8 },
+}
+
+impl VarlenaHeader {
+    pub fn parse(bytes: &[u8]) -> ByteEncodeResult<Self> {
+        let first = *bytes.first().ok_or(ByteEncodeError::NotEnoughBytes {
+            expected: 1,
+            actual: 0,
+        })?;
+        if first == 0x01 {
+            let tag = *bytes.get(1).ok_or(ByteEncodeError::NotEnoughBytes {
+                expected: 2,
+                actual: bytes.len(),
+            })?;
+            return Ok(VarlenaHeader::External { tag });
+        }
+        if first & 0x01 == 0x01 {
+            return Ok(VarlenaHeader::Short {
+                len: (first >> 1) as usize,
+            });
+        }
+
+        let word = u32::decode(bytes.get_byte_slice(0, 4)?)?;
+        let len = (word >> 2) as usize;
+        if word & 0x03 == 0x02 {
+            let tcinfo = u32::decode(bytes.get_byte_slice(4, 8)?)?;
+            Ok(VarlenaHeader::Compressed {
+                len,
+                raw_size: tcinfo & VARLENA_EXTSIZE_MASK,
+                method: CompressionMethod::from_id(tcinfo >> VARLENA_EXTSIZE_BITS),
+            })
+        } else {
+            Ok(VarlenaHeader::Uncompressed { len })
+        }
+    }
+
+    /// Bytes the header itself takes.
+    pub fn header_len(&self) -> usize {
+        match self {
+            VarlenaHeader::Short { .. } => VARHDRSZ_SHORT,
+            VarlenaHeader::Uncompressed { .. } => 4,
+            VarlenaHeader::Compressed { .. } => VARHDRSZ_COMPRESSED,
+            VarlenaHeader::External { .. } => VARHDRSZ_EXTERNAL,
+        }
+    }
+
+    /// Total size of the datum including its header, like VARSIZE_ANY.
+    pub fn total_len(&self) -> usize {
+        match self {
+            VarlenaHeader::Short { len }
+            | VarlenaHeader::Uncompressed { len }
+            | VarlenaHeader::Compressed { len, .. } => *len,
+            VarlenaHeader::External { tag } => {
+                VARHDRSZ_EXTERNAL
+                    + match *tag {
+                        VARTAG_ONDISK => 16,
+                        // in-memory pointers, not found on disk
+                        _ => 8,
+                    }
+            }
+        }
+    }
+}
+
+/// Reads the varlena at the start of `bytes`, returning its total length and its payload:
+/// the data after the header, or the TOAST pointer body for external values.
+pub fn read_varlena(bytes: &[u8]) -> ByteEncodeResult<(usize, &[u8])> {
+    let header = VarlenaHeader::parse(bytes)?;
+    let total_len = header.total_len();
+    if total_len < header.header_len() {
+        return Err(ByteEncodeError::InvalidSize {
+            expected: header.header_len(),
+            actual: total_len,
+        });
+    }
+    Ok((total_len, bytes.get_byte_slice(header.header_len(), total_len)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short() {
+        // 'abc' with a 1 byte header: (1 + 3) << 1 | 1
+        let bytes = [0x09, b'a', b'b', b'c', 0xFF];
+        assert_eq!(VarlenaHeader::parse(&bytes).unwrap(), VarlenaHeader::Short { len: 4 });
+        assert_eq!(read_varlena(&bytes).unwrap(), (4, &b"abc"[..]));
+        assert_eq!(read_varlena(&[0x03]).unwrap(), (1, &[][..]));
+    }
+
+    #[test]
+    fn test_uncompressed() {
+        let mut bytes = ((4 + 200_u32) << 2).to_le_bytes().to_vec();
+        bytes.extend(vec![7; 200]);
+        assert_eq!(VarlenaHeader::parse(&bytes).unwrap(), VarlenaHeader::Uncompressed { len: 204 });
+        let (len, payload) = read_varlena(&bytes).unwrap();
+        assert_eq!(len, 204);
+        assert_eq!(payload, &[7; 200][..]);
+    }
+
+    #[test]
+    fn test_compressed() {
+        let mut bytes = (((8 + 3_u32) << 2) | 0x02).to_le_bytes().to_vec();
+        bytes.extend((5200_u32 | 1 << VARLENA_EXTSIZE_BITS).to_le_bytes());
+        bytes.extend([1, 2, 3]);
+        assert_eq!(
+            VarlenaHeader::parse(&bytes).unwrap(),
+            VarlenaHeader::Compressed {
+                len: 11,
+                raw_size: 5200,
+                method: Some(CompressionMethod::Lz4),
+            }
+        );
+        assert_eq!(read_varlena(&bytes).unwrap(), (11, &[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn test_external() {
+        let mut bytes = vec![0x01, VARTAG_ONDISK];
+        bytes.extend([0xAB; 16]);
+        assert_eq!(VarlenaHeader::parse(&bytes).unwrap(), VarlenaHeader::External { tag: VARTAG_ONDISK });
+        assert_eq!(read_varlena(&bytes).unwrap(), (18, &[0xAB; 16][..]));
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(read_varlena(&[]).is_err());
+        assert!(read_varlena(&[0x09, b'a']).is_err());
+        assert!(read_varlena(&[0x00, 0x01]).is_err());
+        assert!(read_varlena(&[0x04, 0, 0, 0]).is_err());
+    }
+}
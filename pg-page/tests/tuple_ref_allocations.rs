@@ -0,0 +1,53 @@
+//! Allocation counts for tuple iteration. These need a counting global allocator, so they
+//! live in their own test binary rather than changing the allocator for the unit tests.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io::Cursor,
+};
+
+use pg_page::dto::PageLazy;
+
+/// Block 0 of `fixture_heap`: 40 tuples.
+const HEAP_PAGE: &[u8] = include_bytes!("../test-data/heap_page.bin");
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts allocations per thread, so tests running in parallel don't see each other's.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Runs `f`, returning its result and the number of allocations it made on this thread.
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_iter_tuple_refs_does_not_allocate() {
+    let page = PageLazy::from_reader(&mut Cursor::new(HEAP_PAGE)).unwrap();
+    let (owned_xmins, owned_allocations) =
+        count_allocations(|| page.iter_tuples().map(|t| t.unwrap().1.t_xmin).sum::<u32>());
+    let (ref_xmins, ref_allocations) =
+        count_allocations(|| page.iter_tuple_refs().map(|t| t.unwrap().1.t_xmin).sum::<u32>());
+    assert_eq!(owned_xmins, ref_xmins);
+    assert_eq!(owned_allocations, 40);
+    assert_eq!(ref_allocations, 0);
+}
@@ -0,0 +1,267 @@
+use std::{collections::HashMap, ops::Deref, rc::Rc};
+
+use pg_page::{dto::PageLazy, page_reader::BlockSource};
+
+use crate::Error;
+
+/// A page held in the pool. The pool keeps it decoded until it is unpinned and evicted.
+///
+/// The pin lasts as long as the handle: dropping it, or passing it to
+/// [`BufferPool::unpin`], releases the pin, and a clone holds a pin of its own.
+#[derive(Debug, Clone)]
+pub struct PinnedPage {
+    block: u64,
+    page: Rc<PageLazy>,
+}
+
+impl PinnedPage {
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+}
+
+impl Deref for PinnedPage {
+    type Target = PageLazy;
+
+    fn deref(&self) -> &PageLazy {
+        &self.page
+    }
+}
+
+/// A cached page. Every `PinnedPage` handed out shares `page`, so the pins are the
+/// references beyond the buffer's own.
+struct Buffer {
+    page: Rc<PageLazy>,
+    last_used: u64,
+}
+
+impl Buffer {
+    fn pin_count(&self) -> usize {
+        Rc::strong_count(&self.page) - 1
+    }
+}
+
+/// A small buffer manager over a `BlockSource`.
+///
+/// Pinned pages stay cached; unpinned pages remain cached too but become eviction
+/// candidates, least recently used first, once the pool is at capacity.
+pub struct BufferPool<S: BlockSource> {
+    source: S,
+    capacity: usize,
+    buffers: HashMap<u64, Buffer>,
+    clock: u64,
+    reads: u64,
+}
+
+impl<S: BlockSource> BufferPool<S> {
+    /// A pool holding up to `capacity` pages.
+    ///
+    /// Panics if `capacity` is 0, as such a pool could never pin a page.
+    pub fn new(source: S, capacity: usize) -> Self {
+        assert!(capacity > 0, "buffer pool capacity must be positive");
+        BufferPool {
+            source,
+            capacity,
+            buffers: HashMap::new(),
+            clock: 0,
+            reads: 0,
+        }
+    }
+
+    /// Pins `block`, reading it from the source only if it is not already cached.
+    /// Returns `None` if the block lies past the end of the relation.
+    pub fn pin(&mut self, block: u64) -> Result<Option<PinnedPage>, Error> {
+        self.clock += 1;
+        if let Some(buffer) = self.buffers.get_mut(&block) {
+            buffer.last_used = self.clock;
+            return Ok(Some(PinnedPage {
+                block,
+                page: buffer.page.clone(),
+            }));
+        }
+
+        // pick the victim before reading, but only evict it once the block turns out to exist
+        let victim = if self.buffers.len() >= self.capacity {
+            Some(self.victim()?)
+        } else {
+            None
+        };
+        let page = match self.source.read_block(block)? {
+            Some(page) => Rc::new(page),
+            None => return Ok(None),
+        };
+        self.reads += 1;
+        if let Some(victim) = victim {
+            self.buffers.remove(&victim);
+        }
+        self.buffers.insert(
+            block,
+            Buffer {
+                page: page.clone(),
+                last_used: self.clock,
+            },
+        );
+        Ok(Some(PinnedPage { block, page }))
+    }
+
+    /// Releases a pin, the same as dropping the page. The page stays cached until it is
+    /// evicted.
+    pub fn unpin(&mut self, page: PinnedPage) {
+        drop(page);
+    }
+
+    pub fn pin_count(&self, block: u64) -> usize {
+        self.buffers.get(&block).map_or(0, Buffer::pin_count)
+    }
+
+    pub fn is_cached(&self, block: u64) -> bool {
+        self.buffers.contains_key(&block)
+    }
+
+    /// Number of blocks read from the source so far.
+    pub fn reads(&self) -> u64 {
+        self.reads
+    }
+
+    /// The least recently used unpinned block, the next to be evicted.
+    fn victim(&self) -> Result<u64, Error> {
+        self.buffers
+            .iter()
+            .filter(|(_, buffer)| buffer.pin_count() == 0)
+            .min_by_key(|(_, buffer)| buffer.last_used)
+            .map(|(block, _)| *block)
+            .ok_or(Error::NoUnpinnedBuffers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_page::{
+        dto::{PageHeaderData, PageLazy, PageXLogRecPtr},
+        util::ByteEncodeResult,
+    };
+
+    use super::*;
+
+    /// Serves empty pages, recording every block it is asked for.
+    struct CountingSource {
+        blocks: u64,
+        requested: Vec<u64>,
+    }
+
+    impl BlockSource for CountingSource {
+        fn read_block(&mut self, block: u64) -> ByteEncodeResult<Option<PageLazy>> {
+            self.requested.push(block);
+            if block >= self.blocks {
+                return Ok(None);
+            }
+            Ok(Some(PageLazy {
+                header_data: PageHeaderData {
+                    pd_lsn: PageXLogRecPtr {
+                        xlogid: 0,
+                        xrecoff: block as u32,
+                    },
+                    pd_checksum: 0,
+                    pd_flags: 0,
+                    pd_lower: 24,
+                    pd_upper: 8192,
+                    pd_special: 8192,
+                    pd_pagesize_version: 8192 | 4,
+                    pd_prune_xid: 0,
+                },
+                data: vec![0; 8192 - 24],
+            }))
+        }
+    }
+
+    fn pool(blocks: u64, capacity: usize) -> BufferPool<CountingSource> {
+        BufferPool::new(
+            CountingSource {
+                blocks,
+                requested: vec![],
+            },
+            capacity,
+        )
+    }
+
+    #[test]
+    fn test_pinned_pages_are_not_reread() {
+        let mut pool = pool(4, 2);
+        let root = pool.pin(0).unwrap().unwrap();
+        let leaf = pool.pin(3).unwrap().unwrap();
+        assert_eq!(leaf.header_data.pd_lsn.xrecoff, 3);
+
+        let root_again = pool.pin(0).unwrap().unwrap();
+        let leaf_again = pool.pin(3).unwrap().unwrap();
+        assert_eq!(root_again.header_data.pd_lsn.xrecoff, 0);
+        assert_eq!(pool.reads(), 2);
+        assert_eq!(pool.source.requested, vec![0, 3]);
+        assert_eq!(pool.pin_count(0), 2);
+
+        pool.unpin(root);
+        pool.unpin(root_again);
+        pool.unpin(leaf);
+        assert_eq!(pool.pin_count(0), 0);
+        assert_eq!(pool.pin_count(3), 1);
+        assert!(pool.is_cached(0));
+
+        // dropping a page releases its pin, and a clone pins it again
+        let leaf_copy = leaf_again.clone();
+        assert_eq!(pool.pin_count(3), 2);
+        drop(leaf_again);
+        drop(leaf_copy);
+        assert_eq!(pool.pin_count(3), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer pool capacity must be positive")]
+    fn test_zero_capacity() {
+        pool(1, 0);
+    }
+
+    #[test]
+    fn test_dropped_pages_can_be_evicted() {
+        let mut pool = pool(2, 1);
+        drop(pool.pin(0).unwrap().unwrap());
+        assert!(pool.pin(1).unwrap().is_some());
+        assert!(!pool.is_cached(0));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_unpinned() {
+        let mut pool = pool(4, 2);
+        let first = pool.pin(0).unwrap().unwrap();
+        let second = pool.pin(1).unwrap().unwrap();
+        pool.unpin(first);
+
+        let third = pool.pin(2).unwrap().unwrap();
+        assert!(!pool.is_cached(0));
+        assert!(pool.is_cached(1));
+        assert!(pool.is_cached(2));
+
+        assert!(matches!(pool.pin(3), Err(Error::NoUnpinnedBuffers)));
+        pool.unpin(second);
+        pool.unpin(third);
+        assert!(pool.pin(3).unwrap().is_some());
+        assert_eq!(pool.reads(), 4);
+    }
+
+    #[test]
+    fn test_pin_past_end() {
+        let mut pool = pool(1, 2);
+        assert!(pool.pin(1).unwrap().is_none());
+        assert!(!pool.is_cached(1));
+    }
+
+    #[test]
+    fn test_pin_past_end_keeps_cached_pages() {
+        let mut pool = pool(1, 1);
+        let page = pool.pin(0).unwrap().unwrap();
+        pool.unpin(page);
+
+        assert!(pool.pin(1).unwrap().is_none());
+        assert!(pool.is_cached(0));
+        assert!(pool.pin(0).unwrap().is_some());
+        assert_eq!(pool.reads(), 1);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod buffer_pool;
+
+pub use buffer_pool::*;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Page error: {0}")]
+    Page(#[from] pg_page::Error),
+    #[error("Error with byte decoding: {0}")]
+    ByteEncoding(#[from] pg_page::util::ByteEncodeError),
+    #[error("No unpinned buffers available")]
+    NoUnpinnedBuffers,
+}
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }